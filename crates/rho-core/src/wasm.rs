@@ -1,5 +1,6 @@
 use crate::normalize;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
 #[derive(Serialize, Deserialize)]
@@ -12,7 +13,18 @@ pub struct NormalizeOutput {
 pub struct ValidateOutput {
     pub ok: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub errors: Option<Vec<String>>,
+    pub errors: Option<Vec<ValidationError>>,
+}
+
+/// One failed check, shaped like `ValidationError` in the main crate
+#[derive(Serialize, Deserialize)]
+pub struct ValidationError {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub keyword: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, serde_json::Value>,
 }
 
 /// Initialize the WASM module (can be called for any setup if needed)
@@ -53,25 +65,30 @@ pub fn wasm_validate(_schema_cid: String, input: JsValue) -> Result<JsValue, JsV
 
     // Attempt to normalize (this validates the input follows CANON rules)
     match normalize::normalize(value) {
-        Ok(_) => {
-            let output = ValidateOutput {
-                ok: true,
-                errors: None,
-            };
-            serde_wasm_bindgen::to_value(&output)
-                .map_err(|e| JsValue::from_str(&format!("Failed to serialize output: {}", e)))
-        }
-        Err(e) => {
-            let output = ValidateOutput {
-                ok: false,
-                errors: Some(vec![format!("Validation error: {}", e)]),
-            };
-            serde_wasm_bindgen::to_value(&output)
-                .map_err(|e| JsValue::from_str(&format!("Failed to serialize output: {}", e)))
-        }
+        Ok(_) => to_js_object(&ValidateOutput {
+            ok: true,
+            errors: None,
+        }),
+        Err(e) => to_js_object(&ValidateOutput {
+            ok: false,
+            errors: Some(vec![ValidationError {
+                instance_path: String::new(),
+                schema_path: String::new(),
+                keyword: "canon".to_string(),
+                message: e.to_string(),
+                params: BTreeMap::new(),
+            }]),
+        }),
     }
 }
 
+/// Serialize maps as plain JS objects rather than `Map`s
+fn to_js_object<T: Serialize>(output: &T) -> Result<JsValue, JsValue> {
+    output
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize output: {}", e)))
+}
+
 /// Compute CID from raw bytes (base64url, no padding)
 #[wasm_bindgen]
 pub fn wasm_cid(bytes: &[u8]) -> String {
@@ -4,11 +4,12 @@
 // with compliance certification and bias metrics.
 
 use rho_circles::cas::Cas;
+use rho_circles::keystore::{KeyAlgorithm, KeyPair};
 use rho_circles::products::ai_passport::{
     register_model, register_with_hash, validate_compliance, verify_passport, BiasMetrics,
     ComplianceDoc, ModelInfo,
 };
-use rho_circles::types::Signature;
+use rho_circles::rc;
 use serde_json::json;
 
 fn main() {
@@ -42,11 +43,8 @@ fn main() {
     let model_weights = b"[Model weights binary data...]".to_vec();
     let compliance_pdf = b"[EU AI Act compliance documentation PDF...]".to_vec();
 
-    let auditor_signature = Signature {
-        algorithm: "mldsa3".to_string(), // Post-quantum signature
-        public_key: "independent_auditor_pq_key".to_string(),
-        signature: "auditor_pq_signature".to_string(),
-    };
+    // Post-quantum auditor key
+    let auditor_key = KeyPair::generate(KeyAlgorithm::MlDsa3);
 
     match register_model(
         model_info.clone(),
@@ -63,10 +61,11 @@ fn main() {
             "risk_category": "limited",
             "monitoring_enabled": true
         })),
-        vec![auditor_signature],
+        vec![],
         &cas,
     ) {
-        Ok(passport) => {
+        Ok(mut passport) => {
+            rc::sign(&mut passport.receipt_card, &auditor_key).unwrap();
             println!("✓ AI Model registered successfully!");
             println!("\nPassport details:");
             println!(
@@ -3,8 +3,9 @@
 // Demonstrates how to use product.api-notary to create cryptographic
 // receipts for B2B API transactions.
 
+use rho_circles::keystore::{KeyAlgorithm, KeyPair};
 use rho_circles::products::{notarize, verify_notary, ApiTransaction};
-use rho_circles::types::Signature;
+use rho_circles::rc;
 use serde_json::json;
 
 fn main() {
@@ -40,20 +41,15 @@ fn main() {
     println!("  Path: {}", transaction.path);
     println!("  Status: {}", transaction.status_code);
 
-    // In a real implementation, both parties would sign
-    let company_a_signature = Signature {
-        algorithm: "ed25519".to_string(),
-        public_key: "company_a_public_key_base64".to_string(),
-        signature: "company_a_signature_base64".to_string(),
-    };
-
-    let company_b_signature = Signature {
-        algorithm: "ed25519".to_string(),
-        public_key: "company_b_public_key_base64".to_string(),
-        signature: "company_b_signature_base64".to_string(),
-    };
+    // Both parties sign the content CID of the transaction
+    let company_a_key = KeyPair::generate(KeyAlgorithm::Ed25519);
+    let company_b_key = KeyPair::generate(KeyAlgorithm::Ed25519);
+    let content_cid = rc::content_cid(&serde_json::to_value(&transaction).unwrap()).unwrap();
 
-    let signatures = vec![company_a_signature, company_b_signature];
+    let signatures = vec![
+        company_a_key.sign_cid(&content_cid).unwrap(),
+        company_b_key.sign_cid(&content_cid).unwrap(),
+    ];
 
     // Notarize the transaction
     match notarize(transaction.clone(), signatures) {
@@ -4,8 +4,9 @@
 // and generate verifiable receipts (anti-fake news).
 
 use rho_circles::cas::Cas;
+use rho_circles::keystore::{KeyAlgorithm, KeyPair};
 use rho_circles::products::{sign_content, sign_json, verify_content, verify_json};
-use rho_circles::types::Signature;
+use rho_circles::rc;
 use serde_json::json;
 
 fn main() {
@@ -25,19 +26,16 @@ fn main() {
         "tags": ["technology", "security", "blockchain-alternative"]
     });
 
-    let publisher_signature = Signature {
-        algorithm: "ed25519".to_string(),
-        public_key: "tech_daily_publisher_key".to_string(),
-        signature: "tech_daily_signature".to_string(),
-    };
+    let publisher_key = KeyPair::generate(KeyAlgorithm::Ed25519);
 
     match sign_json(
         article.clone(),
         "Tech Daily - Verified Publisher".to_string(),
         "2024-01-15T10:00:00Z".to_string(),
-        vec![publisher_signature],
+        vec![],
     ) {
-        Ok(receipt) => {
+        Ok(mut receipt) => {
+            rc::sign(&mut receipt, &publisher_key).unwrap();
             println!("✓ Article signed successfully!");
             println!("\nReceipt details:");
             println!("  Content CID: {}", receipt.recibo.content_cid);
@@ -66,11 +64,7 @@ fn main() {
 
     let image_content = b"[Binary image data would be here...]".to_vec();
 
-    let photographer_signature = Signature {
-        algorithm: "ed25519".to_string(),
-        public_key: "photographer_public_key".to_string(),
-        signature: "photographer_signature".to_string(),
-    };
+    let photographer_key = KeyPair::generate(KeyAlgorithm::Ed25519);
 
     match sign_content(
         "image".to_string(),
@@ -78,7 +72,7 @@ fn main() {
         "Alice Photographer".to_string(),
         "2024-01-15T14:30:00Z".to_string(),
         image_content.clone(),
-        vec![photographer_signature],
+        vec![],
         Some(json!({
             "camera": "Canon EOS R5",
             "location": "Tech Conference 2024",
@@ -86,7 +80,8 @@ fn main() {
         })),
         &cas,
     ) {
-        Ok(receipt) => {
+        Ok(mut receipt) => {
+            rc::sign(&mut receipt.receipt_card, &photographer_key).unwrap();
             println!("✓ Image signed successfully!");
             println!("\nReceipt details:");
             println!("  Content CID: {}", receipt.signed_content.content_cid);
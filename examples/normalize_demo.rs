@@ -90,7 +90,7 @@ fn main() {
 
     // Example 5: Float rejection
     println!("Example 5: Float rejection (expected error)");
-    let input5 = json!({"value": 3.14});
+    let input5 = json!({"value": 2.5});
     match normalize(input5.clone()) {
         Ok(_) => println!("  Unexpected success!\n"),
         Err(e) => println!("  Input:  {}\n  Error:  {} ✓\n", input5, e),
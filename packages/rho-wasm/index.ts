@@ -8,9 +8,17 @@ export interface NormalizeOutput {
   cid: string;
 }
 
+export interface ValidationError {
+  instance_path: string;
+  schema_path: string;
+  keyword: string;
+  message: string;
+  params?: Record<string, unknown>;
+}
+
 export interface ValidateOutput {
   ok: boolean;
-  errors?: string[];
+  errors?: ValidationError[];
 }
 
 let isInitialized = false;
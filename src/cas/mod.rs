@@ -5,15 +5,20 @@ use std::sync::Mutex;
 
 /// Content Addressable Storage
 ///
-/// Stores content by its blake3 hash (CID)
+/// Stores content by its blake3 hash (CID). Alongside the content it keeps a
+/// small table of named refs pointing at CIDs, used for indexes that cannot be
+/// content-addressed themselves (e.g. "which RC produced this CID").
+#[derive(Debug)]
 pub struct Cas {
     storage: Mutex<HashMap<Cid, Vec<u8>>>,
+    refs: Mutex<HashMap<String, Cid>>,
 }
 
 impl Cas {
     pub fn new() -> Self {
         Self {
             storage: Mutex::new(HashMap::new()),
+            refs: Mutex::new(HashMap::new()),
         }
     }
 
@@ -37,6 +42,44 @@ impl Cas {
             .cloned()
             .ok_or_else(|| RhoError::Cas(format!("CID not found: {}", cid)))
     }
+
+    /// True if content is stored under `cid`
+    pub fn contains(&self, cid: &Cid) -> bool {
+        self.storage.lock().unwrap().contains_key(cid)
+    }
+
+    /// Point a named ref at a CID, replacing any previous target
+    pub fn set_ref(&self, name: &str, cid: Cid) {
+        let mut refs = self.refs.lock().unwrap();
+        refs.insert(name.to_string(), cid);
+    }
+
+    /// Point a named ref at `cid` only if it still points at `expected`
+    ///
+    /// `None` stands for an unset ref, as `expected` or as the new target.
+    /// Returns false, leaving the ref alone, if another writer moved it first.
+    pub fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        cid: Option<Cid>,
+    ) -> bool {
+        let mut refs = self.refs.lock().unwrap();
+        if refs.get(name) != expected {
+            return false;
+        }
+        match cid {
+            Some(cid) => refs.insert(name.to_string(), cid),
+            None => refs.remove(name),
+        };
+        true
+    }
+
+    /// Resolve a named ref
+    pub fn get_ref(&self, name: &str) -> Option<Cid> {
+        let refs = self.refs.lock().unwrap();
+        refs.get(name).cloned()
+    }
 }
 
 impl Default for Cas {
@@ -70,4 +113,44 @@ mod tests {
 
         assert_eq!(cid1, cid2);
     }
+
+    #[test]
+    fn test_cas_refs() {
+        let cas = Cas::new();
+        assert_eq!(cas.get_ref("head"), None);
+
+        let cid = cas.put(b"v1".to_vec()).unwrap();
+        cas.set_ref("head", cid.clone());
+        assert_eq!(cas.get_ref("head"), Some(cid));
+
+        let cid2 = cas.put(b"v2".to_vec()).unwrap();
+        cas.set_ref("head", cid2.clone());
+        assert_eq!(cas.get_ref("head"), Some(cid2));
+    }
+
+    #[test]
+    fn test_cas_compare_and_set_ref() {
+        let cas = Cas::new();
+        let (a, b) = ("a".to_string(), "b".to_string());
+
+        assert!(!cas.compare_and_set_ref("head", Some(&a), Some(b.clone())));
+        assert!(cas.compare_and_set_ref("head", None, Some(a.clone())));
+        assert!(!cas.compare_and_set_ref("head", None, Some(b.clone())));
+        assert!(cas.compare_and_set_ref("head", Some(&a), Some(b.clone())));
+        assert_eq!(cas.get_ref("head"), Some(b.clone()));
+        assert!(cas.compare_and_set_ref("head", Some(&b), None));
+        assert_eq!(cas.get_ref("head"), None);
+    }
+}
+
+/// Fixtures shared by tests across the crate
+#[cfg(test)]
+pub(crate) mod test_support {
+    use crate::types::Cid;
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    /// CID under which `label`'s bytes would be stored, as a fixture value
+    pub(crate) fn test_cid(label: &str) -> Cid {
+        BASE64.encode(blake3::hash(label.as_bytes()).as_bytes())
+    }
 }
@@ -0,0 +1,621 @@
+// Chip bytecode
+//
+// v2 layout:
+//
+//   program  := magic "RHOB" | version 0x02 | section* | checksum
+//   section  := type:u8 | length:varint | payload
+//   checksum := first 4 bytes of blake3 over everything before it
+//
+// Sections appear at most once each, in increasing type order: header
+// (opcode, chip type), spec CID, IO table (named ports), code (wiring
+// entries) and constants (strings referenced by code). Header and spec CID
+// are required. Unknown section types are skipped so later versions can add
+// sections. Code entries are `tag:u8 | length:varint | payload`. Varints are
+// unsigned LEB128 in their shortest form; strings and CIDs are
+// length-prefixed.
+//
+// v1 (`0x01 | opcode | cid_len:u8 | cid | inputs:u8 | 0x01 | wiring:u8 |
+// 32-byte op CIDs`) is still decoded. Its counts are u8 and wrap past 255.
+
+use crate::types::{ChipType, Cid, Opcode};
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+pub const MAGIC: [u8; 4] = *b"RHOB";
+pub const VERSION_1: u8 = 0x01;
+pub const VERSION_2: u8 = 0x02;
+const CHECKSUM_LEN: usize = 4;
+
+/// Section types
+pub const SECTION_HEADER: u8 = 0x01;
+pub const SECTION_SPEC: u8 = 0x02;
+pub const SECTION_IO: u8 = 0x03;
+pub const SECTION_CODE: u8 = 0x04;
+pub const SECTION_CONSTANTS: u8 = 0x05;
+
+/// Code entry tags
+pub const ENTRY_OP: u8 = 0x01;
+pub const ENTRY_CALL: u8 = 0x02;
+
+/// Port flag: the field may be absent
+const PORT_OPTIONAL: u8 = 0x01;
+
+/// A decoded program of either version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bytecode {
+    pub version: u8,
+    pub opcode: Opcode,
+    /// Not recorded by v1
+    pub chip_type: Option<ChipType>,
+    pub spec_cid: Cid,
+    /// v1 records only how many inputs there are; their names are empty
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    pub code: Vec<Instruction>,
+    pub constants: Vec<String>,
+}
+
+/// A declared input or output field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub optional: bool,
+}
+
+/// One wiring step
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// An op known only by the CID of its canonical JSON
+    Op { cid: Cid },
+    /// An op with its name, inputs and output as indices into the constants
+    Call {
+        cid: Cid,
+        op: usize,
+        inputs: Vec<usize>,
+        output: Option<usize>,
+    },
+}
+
+impl Instruction {
+    /// CID of the op's canonical JSON
+    pub fn cid(&self) -> &Cid {
+        match self {
+            Instruction::Op { cid } | Instruction::Call { cid, .. } => cid,
+        }
+    }
+}
+
+/// Encode a program as v2, whatever its `version`
+pub fn encode(program: &Bytecode) -> Result<Vec<u8>> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION_2);
+
+    let mut header = vec![program.opcode];
+    header.push(match program.chip_type {
+        Some(ChipType::Base) => 0,
+        Some(ChipType::Module) => 1,
+        Some(ChipType::Product) => 2,
+        None => return Err(invalid("v2 programs need a chip type")),
+    });
+    put_section(&mut out, SECTION_HEADER, &header);
+
+    put_section(&mut out, SECTION_SPEC, &decode_cid(&program.spec_cid)?);
+
+    let mut io = Vec::new();
+    for ports in [&program.inputs, &program.outputs] {
+        put_varint(&mut io, ports.len() as u64);
+        for port in ports {
+            put_bytes(&mut io, port.name.as_bytes());
+            io.push(if port.optional { PORT_OPTIONAL } else { 0 });
+        }
+    }
+    put_section(&mut out, SECTION_IO, &io);
+
+    let mut code = Vec::new();
+    put_varint(&mut code, program.code.len() as u64);
+    for instruction in &program.code {
+        let mut entry = Vec::new();
+        put_bytes(&mut entry, &decode_cid(instruction.cid())?);
+        let tag = match instruction {
+            Instruction::Op { .. } => ENTRY_OP,
+            Instruction::Call {
+                op, inputs, output, ..
+            } => {
+                put_varint(&mut entry, *op as u64);
+                put_varint(&mut entry, inputs.len() as u64);
+                for input in inputs {
+                    put_varint(&mut entry, *input as u64);
+                }
+                match output {
+                    Some(output) => {
+                        entry.push(1);
+                        put_varint(&mut entry, *output as u64);
+                    }
+                    None => entry.push(0),
+                }
+                ENTRY_CALL
+            }
+        };
+        code.push(tag);
+        put_bytes(&mut code, &entry);
+    }
+    put_section(&mut out, SECTION_CODE, &code);
+
+    let mut constants = Vec::new();
+    put_varint(&mut constants, program.constants.len() as u64);
+    for constant in &program.constants {
+        put_bytes(&mut constants, constant.as_bytes());
+    }
+    put_section(&mut out, SECTION_CONSTANTS, &constants);
+
+    let checksum = blake3::hash(&out);
+    out.extend_from_slice(&checksum.as_bytes()[..CHECKSUM_LEN]);
+    Ok(out)
+}
+
+/// Decode a v1 or v2 program
+pub fn decode(bytes: &[u8]) -> Result<Bytecode> {
+    match bytes.first() {
+        Some(&VERSION_1) => decode_v1(bytes),
+        _ if bytes.starts_with(&MAGIC) => decode_v2(bytes),
+        Some(version) => Err(invalid(&format!("unsupported version {}", version))),
+        None => Err(invalid("empty program")),
+    }
+}
+
+/// Opcode of a program
+///
+/// v2 programs are fully decoded and their checksum verified. For v1 only the
+/// two-byte prefix is read, as exec always has.
+pub fn opcode(bytes: &[u8]) -> Result<Opcode> {
+    match bytes {
+        [VERSION_1, opcode, ..] => Ok(*opcode),
+        _ if bytes.starts_with(&MAGIC) => Ok(decode_v2(bytes)?.opcode),
+        [version, _, ..] => Err(RhoError::Exec(format!("Unsupported version: {}", version))),
+        _ => Err(RhoError::Exec("Bytecode too short".to_string())),
+    }
+}
+
+fn decode_v1(bytes: &[u8]) -> Result<Bytecode> {
+    let mut reader = Reader::new(bytes);
+    reader.u8()?; // version
+    let opcode = reader.u8()?;
+    let spec_len = reader.u8()? as usize;
+    let spec_cid = BASE64.encode(reader.take(spec_len)?);
+    let input_count = reader.u8()?;
+    let output_count = reader.u8()?;
+    let wiring_count = reader.u8()?;
+    let code = (0..wiring_count)
+        .map(|_| {
+            Ok(Instruction::Op {
+                cid: BASE64.encode(reader.take(32)?),
+            })
+        })
+        .collect::<Result<_>>()?;
+    reader.finish()?;
+
+    let unnamed = |count| {
+        (0..count)
+            .map(|_| Port {
+                name: String::new(),
+                optional: false,
+            })
+            .collect()
+    };
+    Ok(Bytecode {
+        version: VERSION_1,
+        opcode,
+        chip_type: None,
+        spec_cid,
+        inputs: unnamed(input_count),
+        outputs: unnamed(output_count),
+        code,
+        constants: Vec::new(),
+    })
+}
+
+fn decode_v2(bytes: &[u8]) -> Result<Bytecode> {
+    if bytes.len() < MAGIC.len() + 1 + CHECKSUM_LEN {
+        return Err(invalid("program too short"));
+    }
+    let (content, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if blake3::hash(content).as_bytes()[..CHECKSUM_LEN] != *checksum {
+        return Err(invalid("checksum mismatch"));
+    }
+
+    let mut reader = Reader::new(&content[MAGIC.len()..]);
+    let version = reader.u8()?;
+    if version != VERSION_2 {
+        return Err(invalid(&format!("unsupported version {}", version)));
+    }
+
+    let mut header = None;
+    let mut spec_cid = None;
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    let mut code = Vec::new();
+    let mut constants = Vec::new();
+    let mut last_section = 0;
+    while !reader.is_empty() {
+        let section = reader.u8()?;
+        if section <= last_section {
+            return Err(invalid(&format!("section {} out of order", section)));
+        }
+        last_section = section;
+        let mut payload = Reader::new(reader.bytes()?);
+        match section {
+            SECTION_HEADER => {
+                let opcode = payload.u8()?;
+                let chip_type = match payload.u8()? {
+                    0 => ChipType::Base,
+                    1 => ChipType::Module,
+                    2 => ChipType::Product,
+                    other => return Err(invalid(&format!("unknown chip type {}", other))),
+                };
+                header = Some((opcode, chip_type));
+            }
+            SECTION_SPEC => spec_cid = Some(BASE64.encode(payload.take(payload.remaining())?)),
+            SECTION_IO => {
+                inputs = read_ports(&mut payload)?;
+                outputs = read_ports(&mut payload)?;
+            }
+            SECTION_CODE => {
+                for _ in 0..payload.varint()? {
+                    code.push(read_instruction(&mut payload)?);
+                }
+            }
+            SECTION_CONSTANTS => {
+                for _ in 0..payload.varint()? {
+                    constants.push(payload.string()?);
+                }
+            }
+            _ => continue,
+        }
+        payload.finish()?;
+    }
+
+    let (opcode, chip_type) = header.ok_or_else(|| invalid("missing header section"))?;
+    let spec_cid = spec_cid
+        .filter(|cid| !cid.is_empty())
+        .ok_or_else(|| invalid("missing spec CID section"))?;
+    for instruction in &code {
+        if let Instruction::Call {
+            op, inputs, output, ..
+        } = instruction
+        {
+            let mut indices = std::iter::once(op).chain(inputs).chain(output);
+            if indices.any(|&i| i >= constants.len()) {
+                return Err(invalid("constant index out of range"));
+            }
+        }
+    }
+
+    Ok(Bytecode {
+        version,
+        opcode,
+        chip_type: Some(chip_type),
+        spec_cid,
+        inputs,
+        outputs,
+        code,
+        constants,
+    })
+}
+
+fn read_ports(reader: &mut Reader) -> Result<Vec<Port>> {
+    let mut ports = Vec::new();
+    for _ in 0..reader.varint()? {
+        let name = reader.string()?;
+        let flags = reader.u8()?;
+        if flags & !PORT_OPTIONAL != 0 {
+            return Err(invalid(&format!("unknown port flags {:#04x}", flags)));
+        }
+        ports.push(Port {
+            name,
+            optional: flags & PORT_OPTIONAL != 0,
+        });
+    }
+    Ok(ports)
+}
+
+fn read_instruction(reader: &mut Reader) -> Result<Instruction> {
+    let tag = reader.u8()?;
+    let mut entry = Reader::new(reader.bytes()?);
+    let cid = BASE64.encode(entry.bytes()?);
+    let instruction = match tag {
+        ENTRY_OP => Instruction::Op { cid },
+        ENTRY_CALL => {
+            let op = entry.index()?;
+            let inputs = (0..entry.varint()?)
+                .map(|_| entry.index())
+                .collect::<Result<_>>()?;
+            let output = match entry.u8()? {
+                0 => None,
+                1 => Some(entry.index()?),
+                other => return Err(invalid(&format!("bad output marker {}", other))),
+            };
+            Instruction::Call {
+                cid,
+                op,
+                inputs,
+                output,
+            }
+        }
+        other => return Err(invalid(&format!("unknown code entry tag {}", other))),
+    };
+    entry.finish()?;
+    Ok(instruction)
+}
+
+fn decode_cid(cid: &Cid) -> Result<Vec<u8>> {
+    BASE64
+        .decode(cid)
+        .map_err(|e| RhoError::Compile(format!("Invalid CID {}: {}", cid, e)))
+}
+
+fn put_section(out: &mut Vec<u8>, section: u8, payload: &[u8]) {
+    out.push(section);
+    put_bytes(out, payload);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Unsigned LEB128
+pub fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn invalid(message: &str) -> RhoError {
+    RhoError::Exec(format!("Invalid bytecode: {}", message))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(invalid("unexpected end of program"));
+        }
+        let taken = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Shortest-form unsigned LEB128
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(invalid("varint overflows u64"));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                if byte == 0 && shift > 0 {
+                    return Err(invalid("varint not in shortest form"));
+                }
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint too long"))
+    }
+
+    fn index(&mut self) -> Result<usize> {
+        usize::try_from(self.varint()?).map_err(|_| invalid("index out of range"))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.index()?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid("trailing bytes"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::test_support::test_cid;
+
+    fn program() -> Bytecode {
+        Bytecode {
+            version: VERSION_2,
+            opcode: 10,
+            chip_type: Some(ChipType::Module),
+            spec_cid: test_cid("spec"),
+            inputs: vec![
+                Port {
+                    name: "message".to_string(),
+                    optional: false,
+                },
+                Port {
+                    name: "fields".to_string(),
+                    optional: true,
+                },
+            ],
+            outputs: vec![Port {
+                name: "rc".to_string(),
+                optional: false,
+            }],
+            code: vec![
+                Instruction::Call {
+                    cid: test_cid("normalize"),
+                    op: 0,
+                    inputs: vec![1],
+                    output: Some(2),
+                },
+                Instruction::Op {
+                    cid: test_cid("opaque"),
+                },
+            ],
+            constants: vec!["rho.normalize".into(), "params".into(), "norm".into()],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = encode(&program()).unwrap();
+        assert!(bytes.starts_with(b"RHOB\x02"));
+        assert_eq!(decode(&bytes).unwrap(), program());
+        assert_eq!(opcode(&bytes).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_counts_past_255_do_not_wrap() {
+        let mut program = program();
+        program.inputs = (0..300)
+            .map(|i| Port {
+                name: format!("in{}", i),
+                optional: false,
+            })
+            .collect();
+        program.code = (0..1000)
+            .map(|i| Instruction::Op {
+                cid: test_cid(&i.to_string()),
+            })
+            .collect();
+        let decoded = decode(&encode(&program).unwrap()).unwrap();
+        assert_eq!(decoded.inputs.len(), 300);
+        assert_eq!(decoded.code.len(), 1000);
+        assert_eq!(decoded, program);
+    }
+
+    #[test]
+    fn test_checksum_and_structure_checked() {
+        let bytes = encode(&program()).unwrap();
+
+        let mut flipped = bytes.clone();
+        flipped[8] ^= 0x01;
+        assert!(decode(&flipped)
+            .unwrap_err()
+            .to_string()
+            .contains("checksum"));
+
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(opcode(&flipped).is_err());
+
+        // A call pointing past the constant pool
+        let mut program = program();
+        program.constants.truncate(2);
+        let bytes = encode(&program).unwrap();
+        assert!(decode(&bytes)
+            .unwrap_err()
+            .to_string()
+            .contains("constant index"));
+    }
+
+    #[test]
+    fn test_trailing_and_missing_bytes_rejected() {
+        let seal = |mut content: Vec<u8>| {
+            let checksum = blake3::hash(&content);
+            content.extend_from_slice(&checksum.as_bytes()[..CHECKSUM_LEN]);
+            content
+        };
+        let header = |payload: &[u8]| {
+            let mut content = b"RHOB\x02".to_vec();
+            put_section(&mut content, SECTION_HEADER, payload);
+            put_section(&mut content, SECTION_SPEC, blake3::hash(b"spec").as_bytes());
+            seal(content)
+        };
+
+        assert!(decode(&header(&[2, 0])).is_ok());
+        let err = decode(&header(&[2, 0, 9])).unwrap_err();
+        assert!(err.to_string().contains("trailing bytes"));
+        let err = decode(&header(&[2])).unwrap_err();
+        assert!(err.to_string().contains("unexpected end"));
+
+        let err = decode(&seal(b"RHOB\x02".to_vec())).unwrap_err();
+        assert!(err.to_string().contains("missing header"));
+    }
+
+    #[test]
+    fn test_unknown_sections_are_skipped() {
+        let bytes = encode(&program()).unwrap();
+        let mut extended = bytes[..bytes.len() - CHECKSUM_LEN].to_vec();
+        put_section(&mut extended, 0x40, b"future");
+        let checksum = blake3::hash(&extended);
+        extended.extend_from_slice(&checksum.as_bytes()[..CHECKSUM_LEN]);
+        assert_eq!(decode(&extended).unwrap(), program());
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, 16_384, u64::MAX] {
+            let mut out = Vec::new();
+            put_varint(&mut out, value);
+            let mut reader = Reader::new(&out);
+            assert_eq!(reader.varint().unwrap(), value);
+            assert!(reader.is_empty());
+        }
+        // Overlong encodings are rejected
+        assert!(Reader::new(&[0x80, 0x00]).varint().is_err());
+        assert!(Reader::new(&[0xff; 11]).varint().is_err());
+    }
+
+    #[test]
+    fn test_decodes_v1() {
+        let spec = blake3::hash(b"spec");
+        let op = blake3::hash(b"op");
+        let mut bytes = vec![VERSION_1, 2, 32];
+        bytes.extend_from_slice(spec.as_bytes());
+        bytes.extend_from_slice(&[1, 1, 1]);
+        bytes.extend_from_slice(op.as_bytes());
+
+        let program = decode(&bytes).unwrap();
+        assert_eq!(program.version, VERSION_1);
+        assert_eq!(program.opcode, 2);
+        assert_eq!(program.chip_type, None);
+        assert_eq!(program.spec_cid, test_cid("spec"));
+        assert_eq!(program.inputs.len(), 1);
+        assert_eq!(
+            program.code,
+            vec![Instruction::Op {
+                cid: test_cid("op")
+            }]
+        );
+
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(opcode(&[VERSION_1, 7]).unwrap(), 7);
+    }
+}
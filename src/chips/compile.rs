@@ -1,13 +1,15 @@
+use crate::chips::bytecode::{self, Bytecode, Instruction, Port, VERSION_2};
 use crate::chips::normalize;
 use crate::types::{ChipSpec, ChipType, CompileOutput};
 use crate::{Result, RhoError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::Value;
 
-/// Compile a chip_spec into deterministic TLV bytecode
+/// Compile a chip_spec into deterministic bytecode (format v2, see `bytecode`)
 ///
 /// THE CANON:
 /// 1. chip_spec → normalize() → canonical bytes
-/// 2. Canonical spec → TLV bytecode
+/// 2. Canonical spec → bytecode
 /// 3. bytecode → blake3 → rb_cid
 pub fn compile(
     chip_spec: ChipSpec,
@@ -31,8 +33,8 @@ pub fn compile(
     // Step 4: Validate canonical spec
     validate_chip_spec(&canonical_spec)?;
 
-    // Step 5: Compile canonical spec to TLV bytecode
-    let bytecode = compile_to_tlv(&canonical_spec, &normalized.cid)?;
+    // Step 5: Compile canonical spec to bytecode
+    let bytecode = compile_to_bytecode(&canonical_spec, &normalized.cid)?;
 
     // Step 6: Generate rb_cid from bytecode (THE CANON)
     let rb_cid = BASE64.encode(blake3::hash(&bytecode).as_bytes());
@@ -53,47 +55,88 @@ fn validate_chip_spec(spec: &ChipSpec) -> Result<()> {
     Ok(())
 }
 
-fn compile_to_tlv(spec: &ChipSpec, spec_cid: &str) -> Result<Vec<u8>> {
-    let mut bytecode = Vec::new();
+fn compile_to_bytecode(spec: &ChipSpec, spec_cid: &str) -> Result<Vec<u8>> {
+    let mut constants = Vec::new();
+    let code = spec
+        .wiring
+        .iter()
+        .flatten()
+        .map(|op| instruction(op, &mut constants))
+        .collect::<Result<_>>()?;
 
-    // Version
-    bytecode.push(0x01);
+    let program = Bytecode {
+        version: VERSION_2,
+        opcode: spec.opcode.unwrap_or(0),
+        chip_type: Some(spec.chip_type.clone()),
+        spec_cid: spec_cid.to_string(),
+        inputs: ports(&spec.inputs)?,
+        outputs: ports(&spec.outputs)?,
+        code,
+        constants,
+    };
+    bytecode::encode(&program)
+}
 
-    // Opcode
-    bytecode.push(spec.opcode.unwrap_or(0));
+/// Declared fields of a port, in canonical (sorted) order
+fn ports(fields: &Value) -> Result<Vec<Port>> {
+    let fields = fields
+        .as_object()
+        .ok_or_else(|| RhoError::Compile("Chip inputs and outputs must be objects".to_string()))?;
+    Ok(fields
+        .iter()
+        .map(|(name, descriptor)| Port {
+            name: name.clone(),
+            optional: descriptor.get("optional") == Some(&Value::Bool(true)),
+        })
+        .collect())
+}
 
-    // Spec CID (for traceability - embed the canonical spec CID)
-    let spec_cid_bytes = BASE64
-        .decode(spec_cid)
-        .map_err(|e| RhoError::Compile(format!("Invalid spec CID: {}", e)))?;
-    bytecode.push(spec_cid_bytes.len() as u8);
-    bytecode.extend_from_slice(&spec_cid_bytes);
+/// A wiring op, keyed by the CID of its canonical JSON
+///
+/// Ops of the form `{"op", "inputs"?, "output"?}` with string values also
+/// record their names in the constant pool.
+fn instruction(op: &Value, constants: &mut Vec<String>) -> Result<Instruction> {
+    let cid = normalize(op.clone())?.cid;
 
-    // Input count
-    bytecode.push(match &spec.inputs {
-        serde_json::Value::Object(m) => m.len() as u8,
-        _ => 1,
+    let name = op.get("op").and_then(Value::as_str);
+    let inputs = match op.get("inputs") {
+        None => Some(vec![]),
+        Some(inputs) => inputs
+            .as_array()
+            .and_then(|inputs| inputs.iter().map(Value::as_str).collect::<Option<Vec<_>>>()),
+    };
+    let output = match op.get("output") {
+        None => Some(None),
+        Some(output) => output.as_str().map(Some),
+    };
+    let known_keys = op.as_object().is_some_and(|op| {
+        op.keys()
+            .all(|k| ["op", "inputs", "output"].contains(&k.as_str()))
     });
 
-    // Output count
-    bytecode.push(0x01);
-
-    // Wiring (for modules)
-    if let Some(wiring) = &spec.wiring {
-        bytecode.push(wiring.len() as u8);
-        for op in wiring {
-            // Each wiring op should also be normalized
-            let op_normalized = normalize(op.clone())?;
-            let op_cid_bytes = BASE64
-                .decode(&op_normalized.cid)
-                .map_err(|e| RhoError::Compile(format!("Invalid op CID: {}", e)))?;
-            bytecode.extend_from_slice(&op_cid_bytes);
-        }
-    } else {
-        bytecode.push(0x00);
+    match (name, inputs, output) {
+        (Some(name), Some(inputs), Some(output)) if known_keys => Ok(Instruction::Call {
+            cid,
+            op: constant(name, constants),
+            inputs: inputs
+                .into_iter()
+                .map(|input| constant(input, constants))
+                .collect(),
+            output: output.map(|output| constant(output, constants)),
+        }),
+        _ => Ok(Instruction::Op { cid }),
     }
+}
 
-    Ok(bytecode)
+/// Index of `value` in the constant pool, adding it on first use
+fn constant(value: &str, constants: &mut Vec<String>) -> usize {
+    match constants.iter().position(|c| c == value) {
+        Some(index) => index,
+        None => {
+            constants.push(value.to_string());
+            constants.len() - 1
+        }
+    }
 }
 
 #[cfg(test)]
@@ -154,11 +197,12 @@ mod tests {
         };
 
         let result = compile(spec, None).unwrap();
-        let decoded = BASE64.decode(&result.rb_bytes).unwrap();
+        let program = bytecode::decode(&BASE64.decode(&result.rb_bytes).unwrap()).unwrap();
 
-        // Should have version and opcode
-        assert_eq!(decoded[0], 0x01);
-        assert_eq!(decoded[1], 0x02);
+        // Should have version and opcode, and the null input dropped
+        assert_eq!(program.version, VERSION_2);
+        assert_eq!(program.opcode, 0x02);
+        assert_eq!(program.inputs.len(), 1);
     }
 
     #[test]
@@ -182,13 +226,76 @@ mod tests {
         assert_eq!(r1.rb_bytes, r2.rb_bytes);
     }
 
+    #[test]
+    fn test_bytecode_embeds_spec_cid() {
+        let spec = ChipSpec {
+            chip: "test".to_string(),
+            version: "1.0.0".to_string(),
+            chip_type: ChipType::Base,
+            inputs: json!({"value": {}}),
+            outputs: json!({}),
+            determinism: None,
+            opcode: Some(2),
+            wiring: None,
+        };
+        let spec_cid = normalize(serde_json::to_value(&spec).unwrap()).unwrap().cid;
+
+        let result = compile(spec, None).unwrap();
+        let program = bytecode::decode(&BASE64.decode(&result.rb_bytes).unwrap()).unwrap();
+        assert_eq!(program.spec_cid, spec_cid);
+    }
+
+    #[test]
+    fn test_compile_large_module_does_not_wrap() {
+        let inputs: serde_json::Map<String, Value> = (0..300)
+            .map(|i| {
+                (
+                    format!("in{}", i),
+                    json!({"type": "integer", "optional": i % 2 == 0}),
+                )
+            })
+            .collect();
+        let wiring: Vec<Value> = (0..300)
+            .map(|i| json!({"op": "rho.normalize", "inputs": [format!("in{}", i)], "output": format!("n{}", i)}))
+            .chain([json!({"op": "rho.custom", "weight": 3})])
+            .collect();
+        let spec = ChipSpec {
+            chip: "wide".to_string(),
+            version: "1.0.0".to_string(),
+            chip_type: ChipType::Module,
+            inputs: Value::Object(inputs),
+            outputs: json!({"rc": {"type": "object"}}),
+            determinism: None,
+            opcode: Some(10),
+            wiring: Some(wiring),
+        };
+
+        let result = compile(spec, None).unwrap();
+        let program = bytecode::decode(&BASE64.decode(&result.rb_bytes).unwrap()).unwrap();
+        assert_eq!(program.chip_type, Some(ChipType::Module));
+        assert_eq!(program.inputs.len(), 300);
+        assert_eq!(program.inputs.iter().filter(|p| p.optional).count(), 150);
+        assert_eq!(program.code.len(), 301);
+
+        // Names go through the constant pool; unknown shapes stay opaque
+        match &program.code[0] {
+            Instruction::Call { op, output, .. } => {
+                assert_eq!(program.constants[*op], "rho.normalize");
+                assert_eq!(program.constants[output.unwrap()], "n0");
+            }
+            other => panic!("unexpected instruction {:?}", other),
+        }
+        assert!(matches!(program.code[300], Instruction::Op { .. }));
+        assert_eq!(program.constants.len(), 601);
+    }
+
     #[test]
     fn test_compile_rejects_float() {
         let spec = ChipSpec {
             chip: "test".to_string(),
             version: "1.0.0".to_string(),
             chip_type: ChipType::Base,
-            inputs: json!({"value": 3.14}),
+            inputs: json!({"value": 2.5}),
             outputs: json!({}),
             determinism: Some("spec→rb".to_string()),
             opcode: Some(2),
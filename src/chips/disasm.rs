@@ -0,0 +1,175 @@
+// Bytecode disassembly
+//
+// Renders a decoded program as text for debugging chip builds. Each wiring
+// step's op CID is looked up in CAS and shown as the canonical JSON it names;
+// `mod.chip.build` stores those ops, so built chips resolve fully.
+
+use crate::cas::Cas;
+use crate::chips::bytecode::{self, Bytecode, Instruction, Port};
+use crate::types::Cid;
+use crate::Result;
+use std::fmt::Write as _;
+
+/// Disassemble the bytecode stored under `rb_cid`
+pub fn disassemble_cid(rb_cid: &Cid, cas: &Cas) -> Result<String> {
+    disassemble(&cas.get(rb_cid)?, cas)
+}
+
+/// Disassemble a v1 or v2 program
+///
+/// Fails if the bytes do not decode; op CIDs missing from CAS are marked
+/// rather than treated as errors.
+pub fn disassemble(bytes: &[u8], cas: &Cas) -> Result<String> {
+    Ok(render(&bytecode::decode(bytes)?, cas))
+}
+
+fn render(program: &Bytecode, cas: &Cas) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "version   {}", program.version);
+    let _ = writeln!(out, "opcode    {}", program.opcode);
+    if let Some(chip_type) = &program.chip_type {
+        let _ = writeln!(out, "type      {:?}", chip_type);
+    }
+    let _ = writeln!(out, "spec      {}", program.spec_cid);
+    render_ports(&mut out, "inputs", &program.inputs);
+    render_ports(&mut out, "outputs", &program.outputs);
+
+    let _ = writeln!(out, "code      {}", program.code.len());
+    for (index, instruction) in program.code.iter().enumerate() {
+        let _ = match instruction {
+            Instruction::Op { .. } => writeln!(out, "  {:04}  op", index),
+            Instruction::Call {
+                op, inputs, output, ..
+            } => {
+                let name = |i: &usize| program.constants[*i].as_str();
+                let inputs: Vec<&str> = inputs.iter().map(name).collect();
+                let output = output
+                    .as_ref()
+                    .map(|o| format!(" -> {}", name(o)))
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "  {:04}  call {}({}){}",
+                    index,
+                    name(op),
+                    inputs.join(", "),
+                    output
+                )
+            }
+        };
+        let cid = instruction.cid();
+        let _ = writeln!(out, "        cid  {}", cid);
+        let _ = writeln!(out, "        json {}", resolve(cid, cas));
+    }
+
+    let _ = writeln!(out, "constants {}", program.constants.len());
+    for (index, constant) in program.constants.iter().enumerate() {
+        let _ = writeln!(out, "  {:04}  {:?}", index, constant);
+    }
+    out
+}
+
+fn render_ports(out: &mut String, label: &str, ports: &[Port]) {
+    let _ = writeln!(out, "{:<9} {}", label, ports.len());
+    for port in ports.iter().filter(|port| !port.name.is_empty()) {
+        let optional = if port.optional { " (optional)" } else { "" };
+        let _ = writeln!(out, "  {}{}", port.name, optional);
+    }
+}
+
+/// Canonical JSON of an op, or a marker if CAS does not hold it
+fn resolve(cid: &Cid, cas: &Cas) -> String {
+    match cas.get(cid) {
+        Ok(bytes) => String::from_utf8(bytes).unwrap_or_else(|_| "<not UTF-8>".to_string()),
+        Err(_) => "<not in CAS>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chips::compile;
+    use crate::clock::test_clock;
+    use crate::types::{ChipSpec, ChipType};
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+    use serde_json::json;
+
+    fn log_spec() -> ChipSpec {
+        ChipSpec {
+            chip: "mod.log".to_string(),
+            version: "1.0.0".to_string(),
+            chip_type: ChipType::Module,
+            inputs: json!({
+                "message": {"type": "string"},
+                "fields": {"type": "object", "optional": true}
+            }),
+            outputs: json!({"rc": {"type": "object"}}),
+            determinism: None,
+            opcode: Some(10),
+            wiring: Some(vec![
+                json!({"op": "rho.normalize", "inputs": ["params"], "output": "norm"}),
+                json!({"op": "rho.rc.emit", "inputs": ["norm.value"], "output": "rc", "note": 1}),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_disassembles_and_resolves_ops() {
+        let cas = Cas::new();
+        let spec = log_spec();
+        // Only the first op is in CAS
+        let first = crate::chips::normalize(spec.wiring.as_ref().unwrap()[0].clone()).unwrap();
+        cas.put(BASE64.decode(&first.bytes).unwrap()).unwrap();
+
+        let compiled = compile(spec, None).unwrap();
+        let text = disassemble(&BASE64.decode(&compiled.rb_bytes).unwrap(), &cas).unwrap();
+
+        assert!(text.contains("version   2\n"));
+        assert!(text.contains("type      Module\n"));
+        assert!(text.contains("inputs    2\n  fields (optional)\n  message\n"));
+        assert!(text.contains("  0000  call rho.normalize(params) -> norm\n"));
+        assert!(text.contains(r#"json {"inputs":["params"],"op":"rho.normalize","output":"norm"}"#));
+        assert!(text.contains("  0001  op\n"));
+        assert!(text.contains("json <not in CAS>"));
+        assert!(text.contains("constants 3\n  0000  \"rho.normalize\"\n"));
+    }
+
+    #[test]
+    fn test_disassembles_built_chip_by_cid() {
+        let cas = Cas::new();
+        let clock = test_clock();
+        let spec = serde_json::to_value(log_spec()).unwrap();
+        let published = crate::modules::publish(spec, "owner".to_string(), &cas, &clock).unwrap();
+        let chip_cid = published.body["chip_cid"].as_str().unwrap().to_string();
+        let built = crate::modules::build(chip_cid, &cas, &clock).unwrap();
+        let rb_cid = built.body["rb_cid"].as_str().unwrap().to_string();
+
+        let text = disassemble_cid(&rb_cid, &cas).unwrap();
+        assert!(!text.contains("<not in CAS>"));
+        assert!(text.contains(r#""note":1"#));
+    }
+
+    #[test]
+    fn test_rejects_undecodable_bytes() {
+        let cas = Cas::new();
+        assert!(disassemble(&[], &cas).is_err());
+        assert!(disassemble(b"RHOB\x02", &cas).is_err());
+
+        let compiled = compile(log_spec(), None).unwrap();
+        let mut bytes = BASE64.decode(&compiled.rb_bytes).unwrap();
+        bytes.push(0);
+        assert!(disassemble(&bytes, &cas).is_err());
+    }
+
+    #[test]
+    fn test_disassembles_v1() {
+        let cas = Cas::new();
+        let mut bytes = vec![0x01, 2, 32];
+        bytes.extend_from_slice(blake3::hash(b"spec").as_bytes());
+        bytes.extend_from_slice(&[1, 1, 0]);
+
+        let text = disassemble(&bytes, &cas).unwrap();
+        assert!(text.starts_with("version   1\nopcode    2\nspec      "));
+        assert!(text.contains("inputs    1\noutputs   1\ncode      0\n"));
+    }
+}
@@ -1,9 +1,8 @@
 use crate::cas::Cas;
-use crate::chips::normalize;
-use crate::types::{Cid, ExecOutput};
+use crate::chips::{bytecode, normalize, validate};
+use crate::types::{Cid, ExecOutput, Opcode};
 use crate::{Result, RhoError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
-use serde_json::json;
 use serde_json::Value;
 
 /// Execute bytecode with given inputs
@@ -22,17 +21,8 @@ pub fn exec(rb_cid: Cid, inputs: Value, cas: &Cas) -> Result<ExecOutput> {
     // Bytecode should be raw bytes (not base64 - that's for transmission)
     let bytecode = rb_bytes;
 
-    // Step 2: Parse bytecode
-    if bytecode.len() < 2 {
-        return Err(RhoError::Exec("Bytecode too short".to_string()));
-    }
-
-    let version = bytecode[0];
-    let opcode = bytecode[1];
-
-    if version != 0x01 {
-        return Err(RhoError::Exec(format!("Unsupported version: {}", version)));
-    }
+    // Step 2: Parse bytecode (v2, or v1 for programs compiled before it)
+    let opcode = bytecode::opcode(&bytecode)?;
 
     // Step 3: Normalize inputs (THE CANON: all inputs must be canonical)
     let normalized_inputs = normalize(inputs)?;
@@ -49,8 +39,16 @@ pub fn exec(rb_cid: Cid, inputs: Value, cas: &Cas) -> Result<ExecOutput> {
             canonical_inputs
         }
         3 => {
-            // rho.validate - would need schema_cid from inputs
-            json!({"status": "validated", "input_cid": normalized_inputs.cid})
+            // rho.validate - validate `value` against the schema at `schema_cid`
+            let (Some(value), Some(schema_cid)) = (
+                canonical_inputs.get("value"),
+                canonical_inputs["schema_cid"].as_str(),
+            ) else {
+                return Err(RhoError::Exec(
+                    "rho.validate needs value and schema_cid".to_string(),
+                ));
+            };
+            serde_json::to_value(validate(value.clone(), schema_cid.to_string(), cas)?)?
         }
         _ => {
             // For other opcodes, echo canonical inputs
@@ -75,6 +73,14 @@ pub fn exec(rb_cid: Cid, inputs: Value, cas: &Cas) -> Result<ExecOutput> {
     })
 }
 
+/// True if `exec` computes the declared outputs of chips with this opcode
+///
+/// The other opcodes are placeholders that echo their canonical inputs, so
+/// their results say nothing about a spec's `outputs`.
+pub fn computes_outputs(opcode: Opcode) -> bool {
+    opcode == 3
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,7 +145,7 @@ mod tests {
         let bytecode = vec![0x01, 0x00];
         let rb_cid = cas.put(bytecode).unwrap();
 
-        let inputs = json!({"value": 3.14});
+        let inputs = json!({"value": 2.5});
         let result = exec(rb_cid, inputs, &cas);
 
         // THE CANON: only i64 integers
@@ -147,6 +153,59 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("i64"));
     }
 
+    #[test]
+    fn test_exec_v2_program() {
+        let cas = Cas::new();
+        let spec = crate::types::ChipSpec {
+            chip: "test".to_string(),
+            version: "1.0.0".to_string(),
+            chip_type: crate::types::ChipType::Base,
+            inputs: json!({"value": {}}),
+            outputs: json!({}),
+            determinism: None,
+            opcode: Some(2),
+            wiring: None,
+        };
+        let compiled = crate::chips::compile(spec, None).unwrap();
+        let bytecode = BASE64.decode(&compiled.rb_bytes).unwrap();
+        let rb_cid = cas.put(bytecode.clone()).unwrap();
+
+        let result = exec(rb_cid, json!({"value": 1}), &cas).unwrap();
+        assert_eq!(result.body, json!({"value": 1}));
+
+        // A corrupted program fails its checksum
+        let mut corrupted = bytecode;
+        corrupted[6] ^= 0xff;
+        let rb_cid = cas.put(corrupted).unwrap();
+        let err = exec(rb_cid, json!({"value": 1}), &cas).unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn test_exec_validate() {
+        let cas = Cas::new();
+        let schema = json!({"type": "integer"});
+        let schema_cid = cas.put(serde_json::to_vec(&schema).unwrap()).unwrap();
+        let rb_cid = cas.put(vec![0x01, 0x03]).unwrap();
+
+        let ok = exec(
+            rb_cid.clone(),
+            json!({"value": 1, "schema_cid": schema_cid}),
+            &cas,
+        );
+        assert_eq!(ok.unwrap().body, json!({"valid": true}));
+        let bad = exec(
+            rb_cid.clone(),
+            json!({"value": "x", "schema_cid": schema_cid}),
+            &cas,
+        );
+        let body = bad.unwrap().body;
+        assert_eq!(body["valid"], false);
+        assert_eq!(body["errors"][0]["keyword"], "type");
+
+        assert!(exec(rb_cid, json!({"value": 1}), &cas).is_err());
+    }
+
     #[test]
     fn test_exec_missing_bytecode() {
         let cas = Cas::new();
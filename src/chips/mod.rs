@@ -1,13 +1,21 @@
+pub mod bytecode;
 pub mod compile;
+pub mod disasm;
 pub mod exec;
 pub mod normalize;
 pub mod policy;
+pub mod schema_cache;
+pub mod schema_ref;
+pub mod spec_schema;
 pub mod validate;
 
 pub use compile::compile;
 pub use exec::exec;
 pub use normalize::normalize;
-pub use policy::policy_eval;
+pub use policy::{
+    load_keyset, load_policy, policy_eval, policy_eval_with, policy_trace, store_keyset,
+    store_policy, store_trace,
+};
 pub use validate::validate;
 
 /// Base transistor opcodes
@@ -0,0 +1,509 @@
+// Static analysis of policies
+//
+// Policies are monotone: adding a proof can never turn a passing policy into
+// a failing one. That makes every policy equivalent to a set of minimal
+// requirement sets (its disjunctive normal form), from which satisfiability,
+// tautologies and "what is still missing" all follow.
+
+use super::parser::{Policy, PolicyNode};
+use super::{evaluate_policy, verified_signers};
+use crate::cas::Cas;
+use crate::keystore::KeyAlgorithm;
+use crate::types::{Cid, Proof};
+use crate::{Result, RhoError};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// One thing a set of proofs must provide
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Requirement {
+    /// A proof by any key of this algorithm
+    Algorithm(KeyAlgorithm),
+    /// A proof by this key, of a specific algorithm if given
+    Key {
+        algorithm: Option<KeyAlgorithm>,
+        key_id: Cid,
+    },
+    /// A proof by any key of this key set
+    KeySet(Cid),
+}
+
+impl Requirement {
+    fn node(&self) -> PolicyNode {
+        match self {
+            Requirement::Algorithm(KeyAlgorithm::Ed25519) => PolicyNode::Ed25519,
+            Requirement::Algorithm(KeyAlgorithm::MlDsa3) => PolicyNode::MlDsa3,
+            Requirement::Key { algorithm, key_id } => PolicyNode::Key {
+                algorithm: *algorithm,
+                key_id: key_id.clone(),
+            },
+            Requirement::KeySet(cid) => PolicyNode::KeySet(cid.clone()),
+        }
+    }
+
+    fn policy(&self) -> Policy {
+        Policy {
+            node: self.node(),
+            span: Default::default(),
+        }
+    }
+}
+
+/// Same spelling as the policy atom
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.policy())
+    }
+}
+
+/// A set of requirements that together satisfy a policy
+pub type RequirementSet = BTreeSet<Requirement>;
+
+/// Constant-fold `true`/`false` and flatten nested combinators
+///
+/// The result evaluates the same as the input for every set of proofs.
+/// Thresholds that become all-or-nothing are rewritten to `hybrid-and` or
+/// `hybrid-or`, and single-argument combinators are replaced by the argument.
+pub fn simplify(policy: &Policy) -> Policy {
+    let node = match &policy.node {
+        PolicyNode::HybridAnd(args) => fold(args, args.len()),
+        PolicyNode::HybridOr(args) => fold(args, 1),
+        PolicyNode::Threshold(k, args) => fold(args, *k),
+        other => other.clone(),
+    };
+    Policy {
+        node,
+        span: policy.span,
+    }
+}
+
+/// Simplify a k-of-n combination
+fn fold(args: &[Policy], k: usize) -> PolicyNode {
+    let mut k = k;
+    let mut rest = Vec::new();
+    for arg in args {
+        let arg = simplify(arg);
+        match arg.node {
+            PolicyNode::True => k = k.saturating_sub(1),
+            PolicyNode::False => {}
+            _ => rest.push(arg),
+        }
+    }
+
+    if k == 0 {
+        return PolicyNode::True;
+    }
+    if k > rest.len() {
+        return PolicyNode::False;
+    }
+    if rest.len() == 1 {
+        return rest.remove(0).node;
+    }
+    if k == rest.len() {
+        PolicyNode::HybridAnd(flatten(rest, true))
+    } else if k == 1 {
+        PolicyNode::HybridOr(flatten(rest, false))
+    } else {
+        PolicyNode::Threshold(k, rest)
+    }
+}
+
+/// Splice arguments of nested `hybrid-and` (or `hybrid-or`) into the parent
+fn flatten(args: Vec<Policy>, and: bool) -> Vec<Policy> {
+    let mut flat = Vec::with_capacity(args.len());
+    for arg in args {
+        match arg.node {
+            PolicyNode::HybridAnd(inner) if and => flat.extend(inner),
+            PolicyNode::HybridOr(inner) if !and => flat.extend(inner),
+            _ => flat.push(arg),
+        }
+    }
+    flat
+}
+
+/// Minimal requirement sets that satisfy the policy
+///
+/// Each set is sufficient on its own and no set contains another. An empty
+/// list means the policy can never pass; a list holding the empty set means
+/// it always passes. Sets that ask for one key under two algorithms are
+/// dropped, since a key has exactly one algorithm.
+///
+/// The policies under a threshold never share a signer (see `parse`), so a
+/// union across them asks for that many distinct signers.
+///
+/// The number of sets can grow exponentially with thresholds, so do not call
+/// this on untrusted policies; `is_satisfiable` and `is_tautology` do not.
+pub fn minimal_sets(policy: &Policy) -> Vec<RequirementSet> {
+    let sets = match &policy.node {
+        PolicyNode::True => vec![RequirementSet::new()],
+        PolicyNode::False => Vec::new(),
+        PolicyNode::Ed25519
+        | PolicyNode::MlDsa3
+        | PolicyNode::Key { .. }
+        | PolicyNode::KeySet(_) => {
+            vec![RequirementSet::from([requirement(&policy.node)])]
+        }
+        PolicyNode::HybridAnd(args) => product(args.iter().map(minimal_sets)),
+        PolicyNode::HybridOr(args) => args.iter().flat_map(minimal_sets).collect(),
+        PolicyNode::Threshold(k, args) => {
+            let children: Vec<Vec<RequirementSet>> = args.iter().map(minimal_sets).collect();
+            let mut sets = Vec::new();
+            for combination in combinations(children.len(), *k) {
+                sets.extend(product(combination.iter().map(|&i| children[i].clone())));
+            }
+            sets
+        }
+    };
+    minimize(sets.into_iter().filter(is_feasible).collect())
+}
+
+/// True if some set of proofs satisfies the policy
+///
+/// Folds the tree instead of enumerating `minimal_sets`, so it is safe on
+/// untrusted policies. Keys named under both algorithms are the one coupling
+/// between branches: each such key is tried as either algorithm, and more
+/// than `MAX_CONFLICTING_KEYS` of them is an error.
+pub fn is_satisfiable(policy: &Policy) -> Result<bool> {
+    let (mut ed25519, mut mldsa3) = (BTreeSet::new(), BTreeSet::new());
+    collect_keys(policy, &mut ed25519, &mut mldsa3);
+    let conflicting: Vec<&Cid> = ed25519.intersection(&mldsa3).copied().collect();
+    if conflicting.len() > MAX_CONFLICTING_KEYS {
+        return Err(RhoError::Policy(format!(
+            "Policy names {} keys under both algorithms; at most {} are analysed",
+            conflicting.len(),
+            MAX_CONFLICTING_KEYS
+        )));
+    }
+
+    // Bit i set: the i-th conflicting key is an ML-DSA key
+    for choice in 0..1u32 << conflicting.len() {
+        let signs = |node: &PolicyNode| match node {
+            PolicyNode::Key {
+                algorithm: Some(algorithm),
+                key_id,
+            } => match conflicting.iter().position(|k| *k == key_id) {
+                Some(i) => (*algorithm == KeyAlgorithm::MlDsa3) == (choice >> i & 1 == 1),
+                None => true,
+            },
+            _ => true,
+        };
+        if holds(policy, &signs) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// True if the policy passes without any proofs
+pub fn is_tautology(policy: &Policy) -> bool {
+    holds(policy, &|_| false)
+}
+
+/// Keys conflicting across algorithms that `is_satisfiable` will search
+pub const MAX_CONFLICTING_KEYS: usize = 10;
+
+/// Evaluate with `signs` deciding each signature atom
+fn holds(policy: &Policy, signs: &dyn Fn(&PolicyNode) -> bool) -> bool {
+    let met = |args: &[Policy]| args.iter().filter(|a| holds(a, signs)).count();
+    match &policy.node {
+        PolicyNode::True => true,
+        PolicyNode::False => false,
+        PolicyNode::HybridAnd(args) => met(args) == args.len(),
+        PolicyNode::HybridOr(args) => met(args) > 0,
+        PolicyNode::Threshold(k, args) => met(args) >= *k,
+        atom => signs(atom),
+    }
+}
+
+fn collect_keys<'a>(
+    policy: &'a Policy,
+    ed25519: &mut BTreeSet<&'a Cid>,
+    mldsa3: &mut BTreeSet<&'a Cid>,
+) {
+    match &policy.node {
+        PolicyNode::Key {
+            algorithm: Some(KeyAlgorithm::Ed25519),
+            key_id,
+        } => {
+            ed25519.insert(key_id);
+        }
+        PolicyNode::Key {
+            algorithm: Some(KeyAlgorithm::MlDsa3),
+            key_id,
+        } => {
+            mldsa3.insert(key_id);
+        }
+        PolicyNode::HybridAnd(args)
+        | PolicyNode::HybridOr(args)
+        | PolicyNode::Threshold(_, args) => {
+            for arg in args {
+                collect_keys(arg, ed25519, mldsa3);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// What each minimal way of satisfying the policy still needs
+///
+/// Requirements already met by valid proofs over `message_cid` are removed
+/// from every minimal set. The result is minimized again, so if the policy
+/// already passes it is the single empty set. `keyset:` requirements are
+/// only checked when `cas` is given.
+pub fn missing(
+    policy: &Policy,
+    proofs: Vec<Proof>,
+    message_cid: &Cid,
+    cas: Option<&Cas>,
+) -> Result<Vec<RequirementSet>> {
+    let signers = verified_signers(&proofs, message_cid);
+    let mut remaining = Vec::new();
+    for set in minimal_sets(policy) {
+        let mut still = RequirementSet::new();
+        for req in set {
+            let met = match (&req, cas) {
+                (Requirement::KeySet(_), None) => false,
+                _ => evaluate_policy(&req.policy(), &signers, cas)?,
+            };
+            if !met {
+                still.insert(req);
+            }
+        }
+        remaining.push(still);
+    }
+    Ok(minimize(remaining))
+}
+
+fn requirement(node: &PolicyNode) -> Requirement {
+    match node {
+        PolicyNode::Ed25519 => Requirement::Algorithm(KeyAlgorithm::Ed25519),
+        PolicyNode::MlDsa3 => Requirement::Algorithm(KeyAlgorithm::MlDsa3),
+        PolicyNode::Key { algorithm, key_id } => Requirement::Key {
+            algorithm: *algorithm,
+            key_id: key_id.clone(),
+        },
+        PolicyNode::KeySet(cid) => Requirement::KeySet(cid.clone()),
+        other => unreachable!("not an atom: {:?}", other),
+    }
+}
+
+/// Every union of one set from each alternative list
+fn product(lists: impl Iterator<Item = Vec<RequirementSet>>) -> Vec<RequirementSet> {
+    let mut acc = vec![RequirementSet::new()];
+    for list in lists {
+        let mut next = Vec::with_capacity(acc.len() * list.len());
+        for left in &acc {
+            for right in &list {
+                next.push(left.union(right).cloned().collect());
+            }
+        }
+        acc = minimize(next);
+    }
+    acc
+}
+
+/// All k-element subsets of `0..n`, as sorted index lists
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut out = Vec::new();
+    let mut current = Vec::with_capacity(k);
+    fn go(start: usize, n: usize, k: usize, current: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            go(i + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+    go(0, n, k, &mut current, &mut out);
+    out
+}
+
+/// Deduplicate, sort, and drop sets that contain another set
+fn minimize(sets: Vec<RequirementSet>) -> Vec<RequirementSet> {
+    let unique: BTreeSet<RequirementSet> = sets.into_iter().collect();
+    let mut by_size: Vec<RequirementSet> = unique.into_iter().collect();
+    by_size.sort_by_key(|s| s.len());
+
+    let mut kept: Vec<RequirementSet> = Vec::new();
+    for set in by_size {
+        if !kept.iter().any(|k| k.is_subset(&set)) {
+            kept.push(set);
+        }
+    }
+    kept.sort();
+    kept
+}
+
+/// False if the set asks for the same key under two algorithms
+fn is_feasible(set: &RequirementSet) -> bool {
+    let mut seen: Vec<(&Cid, KeyAlgorithm)> = Vec::new();
+    for req in set {
+        if let Requirement::Key {
+            algorithm: Some(algorithm),
+            key_id,
+        } = req
+        {
+            if seen.iter().any(|(id, a)| *id == key_id && a != algorithm) {
+                return false;
+            }
+            seen.push((key_id, *algorithm));
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::*;
+    use crate::keystore::KeyPair;
+
+    fn simplified(source: &str) -> String {
+        simplify(&parse(source).unwrap()).to_string()
+    }
+
+    fn sets(source: &str) -> Vec<Vec<String>> {
+        minimal_sets(&parse(source).unwrap())
+            .into_iter()
+            .map(|set| set.iter().map(|r| r.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_simplify_constants() {
+        assert_eq!(simplified("hybrid-and(true,ed25519)"), "ed25519");
+        assert_eq!(simplified("hybrid-and(false,ed25519)"), "false");
+        assert_eq!(simplified("hybrid-or(true,ed25519)"), "true");
+        assert_eq!(simplified("hybrid-or(false,false)"), "false");
+        assert_eq!(
+            simplified("threshold(2,true,key:a,key:b)"),
+            "hybrid-or(key:a,key:b)"
+        );
+        assert_eq!(
+            simplified("threshold(2,false,key:a,key:b)"),
+            "hybrid-and(key:a,key:b)"
+        );
+        assert_eq!(simplified("threshold(2,false,false,key:a)"), "false");
+        assert_eq!(
+            simplified("threshold(2,key:a,key:b,key:c)"),
+            "threshold(2,key:a,key:b,key:c)"
+        );
+    }
+
+    #[test]
+    fn test_simplify_flattens() {
+        assert_eq!(
+            simplified("hybrid-and(ed25519,hybrid-and(mldsa3,hybrid-and(key:a,true)))"),
+            "hybrid-and(ed25519,mldsa3,key:a)"
+        );
+        assert_eq!(
+            simplified("hybrid-or(hybrid-or(key:a,key:b),hybrid-and(key:c,key:d))"),
+            "hybrid-or(key:a,key:b,hybrid-and(key:c,key:d))"
+        );
+    }
+
+    #[test]
+    fn test_minimal_sets() {
+        assert_eq!(
+            sets("threshold(2,key:a,key:b,key:c)"),
+            vec![
+                vec!["key:a", "key:b"],
+                vec!["key:a", "key:c"],
+                vec!["key:b", "key:c"]
+            ]
+        );
+        // `key:a` alone satisfies the second branch, which absorbs the first
+        assert_eq!(
+            sets("hybrid-or(hybrid-and(key:a,key:b),key:a)"),
+            vec![vec!["key:a"]]
+        );
+        assert_eq!(
+            sets("hybrid-and(ed25519:r,keyset:auditors)"),
+            vec![vec!["ed25519:r", "keyset:auditors"]]
+        );
+    }
+
+    fn satisfiable(source: &str) -> bool {
+        let policy = parse(source).unwrap();
+        let satisfiable = is_satisfiable(&policy).unwrap();
+        assert_eq!(satisfiable, !minimal_sets(&policy).is_empty(), "{}", source);
+        satisfiable
+    }
+
+    #[test]
+    fn test_satisfiability() {
+        assert!(!satisfiable("hybrid-and(ed25519,false)"));
+        assert!(!satisfiable("hybrid-and(ed25519:k,mldsa3:k)"));
+        assert!(satisfiable("hybrid-and(ed25519:k,mldsa3:j)"));
+        assert!(satisfiable(
+            "hybrid-and(hybrid-or(ed25519:k,mldsa3:j),mldsa3:k)"
+        ));
+        assert!(!satisfiable(
+            "hybrid-and(hybrid-or(ed25519:k,mldsa3:j),mldsa3:k,ed25519:j)"
+        ));
+        assert!(satisfiable("threshold(2,ed25519:a,mldsa3:b,false)"));
+        assert!(!satisfiable("threshold(2,ed25519:a,false,false)"));
+        assert!(is_tautology(&parse("hybrid-or(ed25519,true)").unwrap()));
+        assert!(!is_tautology(
+            &parse("threshold(1,ed25519,mldsa3)").unwrap()
+        ));
+        assert!(is_tautology(
+            &parse("threshold(2,true,key:a,true)").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_satisfiability_of_wide_threshold_is_fast() {
+        let keys: Vec<String> = (0..24).map(|i| format!("key:k{}", i)).collect();
+        let policy = parse(&format!("threshold(12,{})", keys.join(","))).unwrap();
+        assert!(is_satisfiable(&policy).unwrap());
+        assert!(!is_tautology(&policy));
+    }
+
+    #[test]
+    fn test_satisfiability_caps_conflicting_keys() {
+        let pairs: Vec<String> = (0..=MAX_CONFLICTING_KEYS)
+            .map(|i| format!("hybrid-or(ed25519:k{0},mldsa3:k{0})", i))
+            .collect();
+        let policy = parse(&format!("hybrid-and({})", pairs.join(","))).unwrap();
+        assert!(is_satisfiable(&policy).is_err());
+    }
+
+    #[test]
+    fn test_missing_requirements() {
+        let keys: Vec<KeyPair> = (0..3)
+            .map(|_| KeyPair::generate(KeyAlgorithm::Ed25519))
+            .collect();
+        let ids: Vec<Cid> = keys.iter().map(|k| k.key_id()).collect();
+        let policy = parse(&format!(
+            "threshold(2,key:{},key:{},key:{})",
+            ids[0], ids[1], ids[2]
+        ))
+        .unwrap();
+        let message = "message".to_string();
+
+        let proofs = vec![keys[0].prove(&message).unwrap()];
+        let still = missing(&policy, proofs, &message, None).unwrap();
+        let mut expected: Vec<RequirementSet> = [1, 2]
+            .iter()
+            .map(|&i| {
+                RequirementSet::from([Requirement::Key {
+                    algorithm: None,
+                    key_id: ids[i].clone(),
+                }])
+            })
+            .collect();
+        expected.sort();
+        assert_eq!(still, expected);
+
+        let proofs = vec![
+            keys[0].prove(&message).unwrap(),
+            keys[2].prove(&message).unwrap(),
+        ];
+        let still = missing(&policy, proofs, &message, None).unwrap();
+        assert_eq!(still, vec![RequirementSet::new()]);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{Result, RhoError};
+
+/// Byte range of a token or node in the policy source
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// 1-based column of the span start, counted in characters
+    pub fn column(&self, source: &str) -> usize {
+        source[..self.start].chars().count() + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Keyword, atom or number: letters, digits, `-` and `_`, plus `:` and
+    /// the base64 characters `+`, `/`, `=` so that `key:<cid>` is one token
+    Ident(String),
+    LParen,
+    RParen,
+    Comma,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Split a policy expression into tokens, skipping whitespace
+///
+/// The returned list always ends with an `Eof` token.
+pub fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        let single = |kind| Token {
+            kind,
+            span: Span {
+                start,
+                end: start + ch.len_utf8(),
+            },
+        };
+        match ch {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                tokens.push(single(TokenKind::LParen));
+                chars.next();
+            }
+            ')' => {
+                tokens.push(single(TokenKind::RParen));
+                chars.next();
+            }
+            ',' => {
+                tokens.push(single(TokenKind::Comma));
+                chars.next();
+            }
+            c if is_ident_char(c) => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !is_ident_char(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Ident(source[start..end].to_string()),
+                    span: Span { start, end },
+                });
+            }
+            other => {
+                return Err(RhoError::PolicySyntax {
+                    column: Span { start, end: start }.column(source),
+                    message: format!("unexpected character {:?}", other),
+                });
+            }
+        }
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span {
+            start: source.len(),
+            end: source.len(),
+        },
+    });
+    Ok(tokens)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '+' | '/' | '=')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_with_whitespace() {
+        let tokens = tokenize(" hybrid-and ( ed25519 ,mldsa3 ) ").unwrap();
+        let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Ident("hybrid-and".to_string()),
+                TokenKind::LParen,
+                TokenKind::Ident("ed25519".to_string()),
+                TokenKind::Comma,
+                TokenKind::Ident("mldsa3".to_string()),
+                TokenKind::RParen,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let tokens = tokenize("a ,b").unwrap();
+        assert_eq!(tokens[1].span, Span { start: 2, end: 3 });
+        assert_eq!(tokens[2].span.column("a ,b"), 4);
+    }
+
+    #[test]
+    fn test_tokenize_key_reference() {
+        let tokens = tokenize("threshold(2,key:ab+/c=)").unwrap();
+        assert_eq!(tokens[2].kind, TokenKind::Ident("2".to_string()));
+        assert_eq!(tokens[4].kind, TokenKind::Ident("key:ab+/c=".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_rejects_unknown_character() {
+        match tokenize("ed25519 & mldsa3") {
+            Err(RhoError::PolicySyntax { column, .. }) => assert_eq!(column, 9),
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,580 @@
+pub mod analysis;
+mod lexer;
+mod parser;
+
+pub use analysis::{is_satisfiable, is_tautology, minimal_sets, missing, simplify, Requirement};
+pub use lexer::Span;
+pub use parser::{parse, Policy, PolicyNode, MAX_DEPTH};
+
+use crate::cas::Cas;
+use crate::chips::normalize;
+use crate::keystore;
+use crate::types::{Cid, PolicyEvalOutput, PolicyTrace, Proof, Signature};
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// Body type of a key set stored in CAS
+pub const KEYSET_TYPE: &str = "rho.keyset";
+
+/// Evaluate a signature policy expression against provided proofs
+///
+/// A proof only counts if its `message_cid` is `message_cid` and its
+/// signature verifies over that CID with its public key; anything else is
+/// ignored, whatever its algorithm label says.
+///
+/// See `parse` for the grammar. Syntax errors carry the offending column.
+/// Policies with `keyset:` atoms need `policy_eval_with`.
+pub fn policy_eval(
+    policy_expr: String,
+    proofs: Vec<Proof>,
+    message_cid: &Cid,
+) -> Result<PolicyEvalOutput> {
+    eval(&policy_expr, proofs, message_cid, None)
+}
+
+/// Evaluate a policy expression, resolving `keyset:` atoms from `cas`
+pub fn policy_eval_with(
+    policy_expr: String,
+    proofs: Vec<Proof>,
+    message_cid: &Cid,
+    cas: &Cas,
+) -> Result<PolicyEvalOutput> {
+    eval(&policy_expr, proofs, message_cid, Some(cas))
+}
+
+/// Evaluate a policy expression and explain the outcome
+///
+/// Same result as `policy_eval_with`, plus a trace of every node. Combinators
+/// do not short-circuit in trace mode, so every branch is reported.
+pub fn policy_trace(
+    policy_expr: String,
+    proofs: Vec<Proof>,
+    message_cid: &Cid,
+    cas: Option<&Cas>,
+) -> Result<PolicyEvalOutput> {
+    let policy = parse(&policy_expr)?;
+    let signers = verified_signers(&proofs, message_cid);
+    let trace = trace_policy(&policy, &signers, cas)?;
+    Ok(PolicyEvalOutput {
+        result: trace.result,
+        trace: Some(trace),
+    })
+}
+
+/// Store a trace in CAS under the CID of its canonical JSON
+pub fn store_trace(trace: &PolicyTrace, cas: &Cas) -> Result<Cid> {
+    let normalized = normalize(serde_json::to_value(trace)?)?;
+    cas.put(BASE64.decode(&normalized.bytes)?)
+}
+
+fn eval(
+    policy_expr: &str,
+    proofs: Vec<Proof>,
+    message_cid: &Cid,
+    cas: Option<&Cas>,
+) -> Result<PolicyEvalOutput> {
+    // Parse the policy expression
+    let policy = parse(policy_expr)?;
+
+    // Evaluate the policy against the proofs that verify
+    let signers = verified_signers(&proofs, message_cid);
+    let result = evaluate_policy(&policy, &signers, cas)?;
+
+    Ok(PolicyEvalOutput {
+        result,
+        trace: None,
+    })
+}
+
+/// Store a named key set in CAS for `keyset:` atoms
+///
+/// Key IDs are sorted and deduplicated, so the same set always gets the same
+/// CID.
+pub fn store_keyset(key_ids: &[Cid], cas: &Cas) -> Result<Cid> {
+    if key_ids.is_empty() {
+        return Err(RhoError::Policy("Key set is empty".to_string()));
+    }
+    let keys: BTreeSet<&Cid> = key_ids.iter().collect();
+    let normalized = normalize(json!({"type": KEYSET_TYPE, "keys": keys}))?;
+    cas.put(BASE64.decode(&normalized.bytes)?)
+}
+
+/// Load the key IDs of a key set stored with `store_keyset`
+pub fn load_keyset(keyset_cid: &Cid, cas: &Cas) -> Result<Vec<Cid>> {
+    let value: Value = serde_json::from_slice(&cas.get(keyset_cid)?)?;
+    if value["type"] != KEYSET_TYPE {
+        return Err(RhoError::Policy(format!("{} is not a key set", keyset_cid)));
+    }
+    Ok(serde_json::from_value(value["keys"].clone())?)
+}
+
+/// Store a policy in CAS under the CID of its canonical form
+pub fn store_policy(policy: &Policy, cas: &Cas) -> Result<Cid> {
+    cas.put(policy.to_string().into_bytes())
+}
+
+/// Load a policy stored with `store_policy`
+pub fn load_policy(policy_cid: &Cid, cas: &Cas) -> Result<Policy> {
+    let bytes = cas.get(policy_cid)?;
+    let source = String::from_utf8(bytes)
+        .map_err(|_| RhoError::Policy(format!("Policy {} is not UTF-8", policy_cid)))?;
+    let policy = parse(&source)?;
+    if policy.to_string() != source {
+        return Err(RhoError::Policy(format!(
+            "Policy {} is not in canonical form",
+            policy_cid
+        )));
+    }
+    Ok(policy)
+}
+
+/// Check a proof's signature over `message_cid`
+fn proof_is_valid(proof: &Proof, message_cid: &Cid) -> bool {
+    if proof.message_cid != *message_cid {
+        return false;
+    }
+    let signature = Signature {
+        algorithm: proof.algorithm.clone(),
+        public_key: proof.public_key.clone(),
+        signature: proof.signature.clone(),
+    };
+    keystore::verify_cid(&signature, message_cid).unwrap_or(false)
+}
+
+/// A verified signer: algorithm and key ID
+struct Signer {
+    algorithm: String,
+    key_id: Cid,
+}
+
+/// Key IDs of the proofs that are valid signatures over `message_cid`
+pub fn verified_key_ids(proofs: &[Proof], message_cid: &Cid) -> Vec<Cid> {
+    verified_signers(proofs, message_cid)
+        .into_iter()
+        .map(|s| s.key_id)
+        .collect()
+}
+
+/// Signers of the proofs that are valid signatures over the message
+fn verified_signers(proofs: &[Proof], message_cid: &Cid) -> Vec<Signer> {
+    proofs
+        .iter()
+        .filter(|p| proof_is_valid(p, message_cid))
+        .filter_map(|p| {
+            let public_key = BASE64.decode(&p.public_key).ok()?;
+            Some(Signer {
+                algorithm: p.algorithm.to_lowercase(),
+                key_id: keystore::key_id(&public_key),
+            })
+        })
+        .collect()
+}
+
+/// Evaluate policy tree against verified signers
+fn evaluate_policy(policy: &Policy, signers: &[Signer], cas: Option<&Cas>) -> Result<bool> {
+    Ok(match &policy.node {
+        PolicyNode::True => true,
+        PolicyNode::False => false,
+        PolicyNode::Ed25519 => signers.iter().any(|s| s.algorithm == "ed25519"),
+        PolicyNode::MlDsa3 => signers.iter().any(|s| s.algorithm == "mldsa3"),
+        PolicyNode::Key { algorithm, key_id } => signers
+            .iter()
+            .any(|s| s.key_id == *key_id && algorithm.is_none_or(|a| s.algorithm == a.as_str())),
+        PolicyNode::KeySet(keyset_cid) => {
+            let cas = cas.ok_or_else(|| {
+                RhoError::Policy(format!("keyset:{} needs a CAS to resolve", keyset_cid))
+            })?;
+            let keys = load_keyset(keyset_cid, cas)?;
+            signers.iter().any(|s| keys.contains(&s.key_id))
+        }
+        PolicyNode::HybridAnd(policies) => {
+            // Short-circuit: all must be true
+            for p in policies {
+                if !evaluate_policy(p, signers, cas)? {
+                    return Ok(false);
+                }
+            }
+            true
+        }
+        PolicyNode::HybridOr(policies) => {
+            // Short-circuit: at least one must be true
+            for p in policies {
+                if evaluate_policy(p, signers, cas)? {
+                    return Ok(true);
+                }
+            }
+            false
+        }
+        PolicyNode::Threshold(k, policies) => {
+            // `parse` keeps one signer from satisfying two of the policies,
+            // so this counts distinct signers
+            let mut met = 0;
+            for p in policies {
+                if evaluate_policy(p, signers, cas)? {
+                    met += 1;
+                }
+            }
+            met >= *k
+        }
+    })
+}
+
+/// Evaluate every node of the tree, recording outcomes
+fn trace_policy(policy: &Policy, signers: &[Signer], cas: Option<&Cas>) -> Result<PolicyTrace> {
+    let children = match &policy.node {
+        PolicyNode::HybridAnd(policies)
+        | PolicyNode::HybridOr(policies)
+        | PolicyNode::Threshold(_, policies) => policies
+            .iter()
+            .map(|p| trace_policy(p, signers, cas))
+            .collect::<Result<Vec<_>>>()?,
+        _ => Vec::new(),
+    };
+    let met = children.iter().filter(|c| c.result).count();
+
+    let mut satisfied_by = BTreeSet::new();
+    let result = match &policy.node {
+        PolicyNode::HybridAnd(_) => met == children.len(),
+        PolicyNode::HybridOr(_) => met > 0,
+        PolicyNode::Threshold(k, _) => met >= *k,
+        _ => {
+            for signer in signers {
+                let single = std::slice::from_ref(signer);
+                if evaluate_policy(policy, single, cas)? {
+                    satisfied_by.insert(signer.key_id.clone());
+                }
+            }
+            evaluate_policy(policy, signers, cas)?
+        }
+    };
+
+    Ok(PolicyTrace {
+        policy: policy.to_string(),
+        result,
+        satisfied_by: satisfied_by.into_iter().collect(),
+        children,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::keystore::{KeyAlgorithm, KeyPair};
+
+    fn message() -> Cid {
+        "message_cid".to_string()
+    }
+
+    fn make_proof(algorithm: &str) -> Proof {
+        let key = KeyPair::generate(KeyAlgorithm::parse(algorithm).unwrap());
+        key.prove(&message()).unwrap()
+    }
+
+    #[test]
+    fn test_policy_eval_true() {
+        let result = policy_eval("true".to_string(), vec![], &message()).unwrap();
+        assert!(result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_false() {
+        let result = policy_eval("false".to_string(), vec![], &message()).unwrap();
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_ed25519() {
+        let proofs = vec![make_proof("ed25519")];
+        let result = policy_eval("ed25519".to_string(), proofs, &message()).unwrap();
+        assert!(result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_ed25519_missing() {
+        let proofs = vec![make_proof("mldsa3")];
+        let result = policy_eval("ed25519".to_string(), proofs, &message()).unwrap();
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_hybrid_and_success() {
+        let proofs = vec![make_proof("ed25519"), make_proof("mldsa3")];
+        let result =
+            policy_eval("hybrid-and(ed25519,mldsa3)".to_string(), proofs, &message()).unwrap();
+        assert!(result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_hybrid_and_failure() {
+        let proofs = vec![make_proof("ed25519")];
+        let result =
+            policy_eval("hybrid-and(ed25519,mldsa3)".to_string(), proofs, &message()).unwrap();
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_hybrid_or_success() {
+        let proofs = vec![make_proof("ed25519")];
+        let result =
+            policy_eval("hybrid-or(ed25519,mldsa3)".to_string(), proofs, &message()).unwrap();
+        assert!(result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_hybrid_or_failure() {
+        let proofs = vec![];
+        let result =
+            policy_eval("hybrid-or(ed25519,mldsa3)".to_string(), proofs, &message()).unwrap();
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_nested() {
+        let proofs = vec![make_proof("ed25519")];
+        let result = policy_eval(
+            "hybrid-or(hybrid-and(ed25519,mldsa3),ed25519)".to_string(),
+            proofs,
+            &message(),
+        )
+        .unwrap();
+        assert!(result.result); // Second branch succeeds
+    }
+
+    #[test]
+    fn test_policy_eval_whitespace() {
+        let proofs = vec![make_proof("ed25519"), make_proof("mldsa3")];
+        let result = policy_eval(
+            "hybrid-and (ed25519, mldsa3)".to_string(),
+            proofs,
+            &message(),
+        )
+        .unwrap();
+        assert!(result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_rejects_unbalanced() {
+        let result = policy_eval("hybrid-or(ed25519))".to_string(), vec![], &message());
+        assert!(matches!(
+            result,
+            Err(RhoError::PolicySyntax { column: 19, .. })
+        ));
+    }
+
+    #[test]
+    fn test_store_policy_by_canonical_cid() {
+        let cas = Cas::new();
+        let a = store_policy(&parse("hybrid-or( ed25519 ,mldsa3 )").unwrap(), &cas).unwrap();
+        let b = store_policy(&parse("hybrid-or(ed25519,mldsa3)").unwrap(), &cas).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(
+            load_policy(&a, &cas).unwrap().to_string(),
+            "hybrid-or(ed25519,mldsa3)"
+        );
+
+        let raw = cas.put(b"hybrid-or( ed25519 ,mldsa3 )".to_vec()).unwrap();
+        assert!(load_policy(&raw, &cas).is_err());
+    }
+
+    #[test]
+    fn test_policy_eval_rejects_forged_proof() {
+        let mut forged = make_proof("ed25519");
+        forged.signature = make_proof("ed25519").signature;
+        let result = policy_eval("ed25519".to_string(), vec![forged], &message()).unwrap();
+        assert!(!result.result);
+
+        let label_only = Proof {
+            algorithm: "ed25519".to_string(),
+            public_key: "test_key".to_string(),
+            signature: "test_sig".to_string(),
+            message_cid: message(),
+        };
+        let result = policy_eval("ed25519".to_string(), vec![label_only], &message()).unwrap();
+        assert!(!result.result);
+    }
+
+    #[test]
+    fn test_policy_eval_threshold_needs_distinct_signers() {
+        let officer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let proofs = vec![officer.prove(&message()).unwrap()];
+        for policy in [
+            format!("threshold(2,key:{0},key:{0},key:other)", officer.key_id()),
+            format!("threshold(2,ed25519,key:{})", officer.key_id()),
+        ] {
+            assert!(matches!(
+                policy_eval(policy, proofs.clone(), &message()),
+                Err(RhoError::PolicySyntax { .. })
+            ));
+        }
+
+        // The same signature twice is still one signer
+        let policy = format!("threshold(2,key:{},key:other)", officer.key_id());
+        let twice = vec![proofs[0].clone(), proofs[0].clone()];
+        assert!(!policy_eval(policy, twice, &message()).unwrap().result);
+    }
+
+    #[test]
+    fn test_policy_eval_threshold_of_officers() {
+        let officers: Vec<KeyPair> = (0..3)
+            .map(|_| KeyPair::generate(KeyAlgorithm::Ed25519))
+            .collect();
+        let policy = format!(
+            "threshold(2,key:{},key:{},key:{})",
+            officers[0].key_id(),
+            officers[1].key_id(),
+            officers[2].key_id()
+        );
+
+        let one = vec![officers[1].prove(&message()).unwrap()];
+        assert!(!policy_eval(policy.clone(), one, &message()).unwrap().result);
+
+        let two = vec![
+            officers[0].prove(&message()).unwrap(),
+            officers[2].prove(&message()).unwrap(),
+        ];
+        assert!(policy_eval(policy.clone(), two, &message()).unwrap().result);
+
+        // Someone else's signature does not count towards the threshold
+        let outsider = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mixed = vec![
+            officers[0].prove(&message()).unwrap(),
+            outsider.prove(&message()).unwrap(),
+        ];
+        assert!(!policy_eval(policy, mixed, &message()).unwrap().result);
+    }
+
+    #[test]
+    fn test_policy_eval_key_algorithm() {
+        let key = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let proofs = vec![key.prove(&message()).unwrap()];
+        let eval = |expr: String| {
+            policy_eval(expr, proofs.clone(), &message())
+                .unwrap()
+                .result
+        };
+
+        assert!(eval(format!("key:{}", key.key_id())));
+        assert!(eval(format!("mldsa3:{}", key.key_id())));
+        assert!(!eval(format!("ed25519:{}", key.key_id())));
+    }
+
+    #[test]
+    fn test_policy_eval_release_key_plus_auditor() {
+        let cas = Cas::new();
+        let release = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let auditors: Vec<KeyPair> = (0..3)
+            .map(|_| KeyPair::generate(KeyAlgorithm::Ed25519))
+            .collect();
+        let ids: Vec<Cid> = auditors.iter().map(|k| k.key_id()).collect();
+        let keyset = store_keyset(&ids, &cas).unwrap();
+        assert_eq!(load_keyset(&keyset, &cas).unwrap().len(), 3);
+
+        let policy = format!("hybrid-and(ed25519:{},keyset:{})", release.key_id(), keyset);
+        let eval = |proofs: Vec<Proof>| {
+            policy_eval_with(policy.clone(), proofs, &message(), &cas)
+                .unwrap()
+                .result
+        };
+
+        assert!(!eval(vec![release.prove(&message()).unwrap()]));
+        assert!(!eval(vec![auditors[2].prove(&message()).unwrap()]));
+        assert!(eval(vec![
+            release.prove(&message()).unwrap(),
+            auditors[2].prove(&message()).unwrap(),
+        ]));
+
+        // Without a CAS the key set cannot be resolved
+        let proofs = vec![release.prove(&message()).unwrap()];
+        assert!(policy_eval(policy, proofs, &message()).is_err());
+    }
+
+    #[test]
+    fn test_policy_trace_explains_failure() {
+        let officers: Vec<KeyPair> = (0..2)
+            .map(|_| KeyPair::generate(KeyAlgorithm::Ed25519))
+            .collect();
+        let policy = format!(
+            "hybrid-or(hybrid-and(ed25519,mldsa3),threshold(2,key:{},key:{}))",
+            officers[0].key_id(),
+            officers[1].key_id()
+        );
+        let proofs = vec![officers[0].prove(&message()).unwrap()];
+
+        let output = policy_trace(policy.clone(), proofs.clone(), &message(), None).unwrap();
+        let plain = policy_eval(policy.clone(), proofs, &message()).unwrap();
+        assert_eq!(output.result, plain.result);
+        assert!(!output.result);
+
+        let trace = output.trace.unwrap();
+        assert_eq!(trace.policy, policy);
+        let and = &trace.children[0];
+        assert!(!and.result);
+        assert_eq!(and.children[0].satisfied_by, vec![officers[0].key_id()]);
+        assert!(!and.children[1].result);
+        assert!(and.children[1].satisfied_by.is_empty());
+
+        let threshold = &trace.children[1];
+        assert!(!threshold.result);
+        assert!(threshold.children[0].result);
+        assert!(!threshold.children[1].result);
+    }
+
+    #[test]
+    fn test_policy_trace_matches_eval() {
+        let key = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let proofs = vec![make_proof("ed25519"), key.prove(&message()).unwrap()];
+        for policy in [
+            "true".to_string(),
+            "hybrid-and(ed25519,mldsa3)".to_string(),
+            "hybrid-and(ed25519,hybrid-or(false,true))".to_string(),
+            format!("threshold(1,false,ed25519:{})", key.key_id()),
+            format!("threshold(2,ed25519,mldsa3:{})", key.key_id()),
+        ] {
+            let traced = policy_trace(policy.clone(), proofs.clone(), &message(), None).unwrap();
+            let plain = policy_eval(policy.clone(), proofs.clone(), &message()).unwrap();
+            assert_eq!(traced.result, plain.result, "{}", policy);
+        }
+    }
+
+    #[test]
+    fn test_store_trace_is_canonical() {
+        let cas = Cas::new();
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let proofs = vec![key.prove(&message()).unwrap()];
+        let trace = |expr: &str| {
+            policy_trace(expr.to_string(), proofs.clone(), &message(), None)
+                .unwrap()
+                .trace
+                .unwrap()
+        };
+
+        let a = store_trace(&trace("hybrid-or(ed25519, mldsa3)"), &cas).unwrap();
+        let b = store_trace(&trace("hybrid-or(ed25519,mldsa3)"), &cas).unwrap();
+        assert_eq!(a, b);
+
+        let stored: PolicyTrace = serde_json::from_slice(&cas.get(&a).unwrap()).unwrap();
+        assert_eq!(stored, trace("hybrid-or(ed25519,mldsa3)"));
+    }
+
+    #[test]
+    fn test_policy_eval_binds_message() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let other = "other_message".to_string();
+
+        // Valid signature, but over a different message
+        let proof = key.prove(&other).unwrap();
+        let result = policy_eval("ed25519".to_string(), vec![proof.clone()], &message()).unwrap();
+        assert!(!result.result);
+
+        // Relabelling message_cid does not help: the signature is over `other`
+        let relabelled = Proof {
+            message_cid: message(),
+            ..proof
+        };
+        let result = policy_eval("ed25519".to_string(), vec![relabelled], &message()).unwrap();
+        assert!(!result.result);
+    }
+}
@@ -0,0 +1,539 @@
+use super::lexer::{tokenize, Span, Token, TokenKind};
+use crate::keystore::KeyAlgorithm;
+use crate::types::Cid;
+use crate::{Result, RhoError};
+use std::fmt;
+
+/// Deepest nesting of combinators `parse` accepts; policies come from
+/// untrusted documents and are evaluated recursively
+pub const MAX_DEPTH: usize = 64;
+
+/// Policy AST node
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolicyNode {
+    True,
+    False,
+    Ed25519,
+    MlDsa3,
+    /// A valid proof by the key with this key ID, of any algorithm when
+    /// `algorithm` is `None`
+    Key {
+        algorithm: Option<KeyAlgorithm>,
+        key_id: Cid,
+    },
+    /// A valid proof by any key of the key set stored in CAS under this CID
+    KeySet(Cid),
+    HybridAnd(Vec<Policy>),
+    HybridOr(Vec<Policy>),
+    /// At least `k` of the policies hold
+    Threshold(usize, Vec<Policy>),
+}
+
+/// A parsed policy with the source span it came from
+///
+/// Equality ignores spans, so two policies are equal when their canonical
+/// forms are.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub node: PolicyNode,
+    pub span: Span,
+}
+
+impl PartialEq for Policy {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+/// Canonical form: no whitespace, `,` between arguments
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, args) = match &self.node {
+            PolicyNode::True => return f.write_str("true"),
+            PolicyNode::False => return f.write_str("false"),
+            PolicyNode::Ed25519 => return f.write_str("ed25519"),
+            PolicyNode::MlDsa3 => return f.write_str("mldsa3"),
+            PolicyNode::Key { algorithm, key_id } => {
+                let prefix = algorithm.map_or("key", |a| a.as_str());
+                return write!(f, "{}:{}", prefix, key_id);
+            }
+            PolicyNode::KeySet(cid) => return write!(f, "keyset:{}", cid),
+            PolicyNode::HybridAnd(args) => ("hybrid-and", args),
+            PolicyNode::HybridOr(args) => ("hybrid-or", args),
+            PolicyNode::Threshold(k, args) => {
+                write!(f, "threshold({}", k)?;
+                for arg in args {
+                    write!(f, ",{}", arg)?;
+                }
+                return f.write_str(")");
+            }
+        };
+        write!(f, "{}(", name)?;
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", arg)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Parse a policy expression
+///
+/// Grammar (whitespace between tokens is ignored):
+/// policy = combinator "(" list ")"
+///        | "threshold" "(" k "," list ")"
+///        | key | "keyset:" cid
+///        | "ed25519" | "mldsa3" | "true" | "false"
+/// list = policy ("," policy)*
+/// combinator = "hybrid-and" | "hybrid-or"
+/// key = ("key" | "ed25519" | "mldsa3") ":" key_id
+///
+/// `k` must be between 1 and the number of policies in the list, and
+/// combinators nest at most `MAX_DEPTH` deep. When `k` is 2 or more, no
+/// signer may be able to satisfy two of the threshold's policies, so that
+/// `k` satisfied policies always mean `k` different signers. A `keyset:`
+/// may hold any key, so it can only stand next to `true` and `false`.
+pub fn parse(source: &str) -> Result<Policy> {
+    let mut parser = Parser {
+        source,
+        tokens: tokenize(source)?,
+        pos: 0,
+        depth: 0,
+    };
+    let policy = parser.policy()?;
+    let next = parser.peek();
+    if next.kind != TokenKind::Eof {
+        return Err(parser.error(next, format!("unexpected {} after policy", describe(next))));
+    }
+    Ok(policy)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Combinators currently open
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn policy(&mut self) -> Result<Policy> {
+        let token = self.advance();
+        let name = match &token.kind {
+            TokenKind::Ident(name) => name.as_str(),
+            _ => {
+                return Err(self.error(
+                    &token,
+                    format!("expected a policy, found {}", describe(&token)),
+                ))
+            }
+        };
+
+        let node = match name {
+            "true" => PolicyNode::True,
+            "false" => PolicyNode::False,
+            "ed25519" => PolicyNode::Ed25519,
+            "mldsa3" => PolicyNode::MlDsa3,
+            "hybrid-and" | "hybrid-or" | "threshold" if self.depth == MAX_DEPTH => {
+                return Err(self.error(
+                    &token,
+                    format!("policy nested deeper than {} levels", MAX_DEPTH),
+                ))
+            }
+            "hybrid-and" | "hybrid-or" => {
+                self.depth += 1;
+                let (args, end) = self.arguments(name)?;
+                self.depth -= 1;
+                let node = if name == "hybrid-and" {
+                    PolicyNode::HybridAnd(args)
+                } else {
+                    PolicyNode::HybridOr(args)
+                };
+                return Ok(Policy {
+                    node,
+                    span: Span {
+                        start: token.span.start,
+                        end,
+                    },
+                });
+            }
+            "threshold" => {
+                self.depth += 1;
+                let (k, args, end) = self.threshold()?;
+                self.depth -= 1;
+                return Ok(Policy {
+                    node: PolicyNode::Threshold(k, args),
+                    span: Span {
+                        start: token.span.start,
+                        end,
+                    },
+                });
+            }
+            reference if reference.contains(':') => self.reference(&token, reference)?,
+            other => return Err(self.error(&token, format!("unknown policy '{}'", other))),
+        };
+        Ok(Policy {
+            node,
+            span: token.span,
+        })
+    }
+
+    /// `key:<id>`, `ed25519:<id>`, `mldsa3:<id>` or `keyset:<cid>`
+    fn reference(&self, token: &Token, reference: &str) -> Result<PolicyNode> {
+        let (prefix, cid) = reference.split_once(':').unwrap_or((reference, ""));
+        if cid.is_empty() || cid.contains(':') {
+            return Err(self.error(token, format!("expected a CID after '{}:'", prefix)));
+        }
+        let cid = cid.to_string();
+        match prefix {
+            "key" => Ok(PolicyNode::Key {
+                algorithm: None,
+                key_id: cid,
+            }),
+            "ed25519" => Ok(PolicyNode::Key {
+                algorithm: Some(KeyAlgorithm::Ed25519),
+                key_id: cid,
+            }),
+            "mldsa3" => Ok(PolicyNode::Key {
+                algorithm: Some(KeyAlgorithm::MlDsa3),
+                key_id: cid,
+            }),
+            "keyset" => Ok(PolicyNode::KeySet(cid)),
+            other => Err(self.error(token, format!("unknown reference '{}:'", other))),
+        }
+    }
+
+    /// `"(" k "," list ")"`; returns `k`, the policies and the end of `)`
+    fn threshold(&mut self) -> Result<(usize, Vec<Policy>, usize)> {
+        self.open("threshold")?;
+        let count = self.advance();
+        let k = match &count.kind {
+            TokenKind::Ident(digits) if digits.bytes().all(|b| b.is_ascii_digit()) => {
+                digits.parse::<usize>().ok()
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            self.error(
+                &count,
+                format!("expected a threshold count, found {}", describe(&count)),
+            )
+        })?;
+
+        let comma = self.advance();
+        if comma.kind != TokenKind::Comma {
+            return Err(self.error(
+                &comma,
+                format!(
+                    "expected ',' after threshold count, found {}",
+                    describe(&comma)
+                ),
+            ));
+        }
+
+        let (args, end) = self.list()?;
+        if k == 0 || k > args.len() {
+            return Err(self.error(
+                &count,
+                format!(
+                    "threshold {} is out of range for {} policies",
+                    k,
+                    args.len()
+                ),
+            ));
+        }
+        if k >= 2 {
+            for (i, arg) in args.iter().enumerate() {
+                if let Some(earlier) = args[..i].iter().find(|a| may_share_signer(a, arg)) {
+                    return Err(RhoError::PolicySyntax {
+                        column: arg.span.column(self.source),
+                        message: format!(
+                            "threshold policies '{}' and '{}' can be satisfied by the same signer",
+                            earlier, arg
+                        ),
+                    });
+                }
+            }
+        }
+        Ok((k, args, end))
+    }
+
+    /// `"(" list ")"`; returns the arguments and the end of `)`
+    fn arguments(&mut self, combinator: &str) -> Result<(Vec<Policy>, usize)> {
+        self.open(combinator)?;
+        self.list()
+    }
+
+    fn open(&mut self, combinator: &str) -> Result<()> {
+        let open = self.advance();
+        if open.kind != TokenKind::LParen {
+            return Err(self.error(
+                &open,
+                format!(
+                    "expected '(' after {}, found {}",
+                    combinator,
+                    describe(&open)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `policy ("," policy)* ")"`; returns the policies and the end of `)`
+    fn list(&mut self) -> Result<(Vec<Policy>, usize)> {
+        let mut args = vec![self.policy()?];
+        loop {
+            let token = self.advance();
+            match token.kind {
+                TokenKind::Comma => args.push(self.policy()?),
+                TokenKind::RParen => return Ok((args, token.span.end)),
+                _ => {
+                    return Err(self.error(
+                        &token,
+                        format!("expected ',' or ')', found {}", describe(&token)),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn error(&self, token: &Token, message: String) -> RhoError {
+        RhoError::PolicySyntax {
+            column: token.span.column(self.source),
+            message,
+        }
+    }
+}
+
+/// True if one signer could satisfy an atom of `a` and an atom of `b`
+fn may_share_signer(a: &Policy, b: &Policy) -> bool {
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    signature_atoms(a, &mut left);
+    signature_atoms(b, &mut right);
+    left.iter()
+        .any(|x| right.iter().any(|y| atoms_overlap(x, y)))
+}
+
+fn signature_atoms<'a>(policy: &'a Policy, atoms: &mut Vec<&'a PolicyNode>) {
+    match &policy.node {
+        PolicyNode::True | PolicyNode::False => {}
+        PolicyNode::HybridAnd(args)
+        | PolicyNode::HybridOr(args)
+        | PolicyNode::Threshold(_, args) => {
+            for arg in args {
+                signature_atoms(arg, atoms);
+            }
+        }
+        atom => atoms.push(atom),
+    }
+}
+
+fn atoms_overlap(a: &PolicyNode, b: &PolicyNode) -> bool {
+    let algorithm_of = |node: &PolicyNode| match node {
+        PolicyNode::Ed25519 => Some(KeyAlgorithm::Ed25519),
+        PolicyNode::MlDsa3 => Some(KeyAlgorithm::MlDsa3),
+        _ => None,
+    };
+    match (a, b) {
+        (PolicyNode::KeySet(_), _) | (_, PolicyNode::KeySet(_)) => true,
+        (PolicyNode::Key { key_id: x, .. }, PolicyNode::Key { key_id: y, .. }) => x == y,
+        (PolicyNode::Key { algorithm, .. }, other) | (other, PolicyNode::Key { algorithm, .. }) => {
+            algorithm.is_none() || *algorithm == algorithm_of(other)
+        }
+        _ => algorithm_of(a) == algorithm_of(b),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match &token.kind {
+        TokenKind::Ident(name) => format!("'{}'", name),
+        TokenKind::LParen => "'('".to_string(),
+        TokenKind::RParen => "')'".to_string(),
+        TokenKind::Comma => "','".to_string(),
+        TokenKind::Eof => "end of input".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn syntax_error(source: &str) -> (usize, String) {
+        match parse(source) {
+            Err(RhoError::PolicySyntax { column, message }) => (column, message),
+            other => panic!("expected syntax error for {:?}, got {:?}", source, other),
+        }
+    }
+
+    #[test]
+    fn test_parse_tolerates_whitespace() {
+        let policy = parse(" hybrid-and (ed25519 , mldsa3) ").unwrap();
+        assert_eq!(policy.to_string(), "hybrid-and(ed25519,mldsa3)");
+        assert_eq!(policy.span, Span { start: 1, end: 30 });
+    }
+
+    #[test]
+    fn test_canonical_round_trip() {
+        for source in [
+            "true",
+            "hybrid-or(hybrid-and(ed25519,mldsa3),ed25519)",
+            "hybrid-and(hybrid-or(false,mldsa3))",
+        ] {
+            let policy = parse(source).unwrap();
+            assert_eq!(policy.to_string(), source);
+            assert_eq!(parse(&policy.to_string()).unwrap(), policy);
+        }
+    }
+
+    #[test]
+    fn test_rejects_extra_closing_paren() {
+        let (column, message) = syntax_error("hybrid-or(ed25519))");
+        assert_eq!(column, 19);
+        assert!(message.contains("')'"));
+    }
+
+    #[test]
+    fn test_rejects_missing_closing_paren() {
+        let (column, message) = syntax_error("hybrid-and(ed25519,mldsa3");
+        assert_eq!(column, 26);
+        assert!(message.contains("end of input"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_atom() {
+        let (column, message) = syntax_error("hybrid-and(ed25519, rsa)");
+        assert_eq!(column, 21);
+        assert_eq!(message, "unknown policy 'rsa'");
+    }
+
+    #[test]
+    fn test_rejects_empty_arguments() {
+        let (column, _) = syntax_error("hybrid-or()");
+        assert_eq!(column, 11);
+        let (column, _) = syntax_error("hybrid-or(ed25519,)");
+        assert_eq!(column, 19);
+    }
+
+    #[test]
+    fn test_parse_key_atoms() {
+        let policy = parse("hybrid-and(key:rel+/=, ed25519:a, mldsa3:b, keyset:c)").unwrap();
+        let PolicyNode::HybridAnd(args) = &policy.node else {
+            panic!("expected hybrid-and, got {:?}", policy.node);
+        };
+        assert_eq!(
+            args[0].node,
+            PolicyNode::Key {
+                algorithm: None,
+                key_id: "rel+/=".to_string()
+            }
+        );
+        assert_eq!(
+            args[1].node,
+            PolicyNode::Key {
+                algorithm: Some(KeyAlgorithm::Ed25519),
+                key_id: "a".to_string()
+            }
+        );
+        assert_eq!(args[3].node, PolicyNode::KeySet("c".to_string()));
+        assert_eq!(
+            policy.to_string(),
+            "hybrid-and(key:rel+/=,ed25519:a,mldsa3:b,keyset:c)"
+        );
+    }
+
+    #[test]
+    fn test_parse_threshold() {
+        let policy = parse("threshold( 2, key:a, key:b, key:c )").unwrap();
+        assert_eq!(policy.to_string(), "threshold(2,key:a,key:b,key:c)");
+        assert!(matches!(policy.node, PolicyNode::Threshold(2, ref args) if args.len() == 3));
+        assert_eq!(parse(&policy.to_string()).unwrap(), policy);
+    }
+
+    #[test]
+    fn test_rejects_bad_threshold() {
+        let (column, message) = syntax_error("threshold(4,key:a,key:b,key:c)");
+        assert_eq!(column, 11);
+        assert_eq!(message, "threshold 4 is out of range for 3 policies");
+
+        let (column, _) = syntax_error("threshold(0,key:a)");
+        assert_eq!(column, 11);
+        let (column, message) = syntax_error("threshold(key:a,key:b)");
+        assert_eq!(column, 11);
+        assert!(message.starts_with("expected a threshold count"));
+        let (column, _) = syntax_error("threshold(1 key:a)");
+        assert_eq!(column, 13);
+    }
+
+    #[test]
+    fn test_rejects_threshold_policies_one_signer_can_meet() {
+        // One officer must not count twice towards "2 of 3"
+        let (column, message) = syntax_error("threshold(2,key:a,key:a,key:b)");
+        assert_eq!(column, 19);
+        assert_eq!(
+            message,
+            "threshold policies 'key:a' and 'key:a' can be satisfied by the same signer"
+        );
+        let (column, _) = syntax_error("threshold(2,ed25519,key:a)");
+        assert_eq!(column, 21);
+        let (column, _) = syntax_error("threshold(2, key:a, hybrid-or(mldsa3, ed25519:a))");
+        assert_eq!(column, 21);
+        syntax_error("threshold(2,ed25519,ed25519:a)");
+        syntax_error("threshold(2,keyset:officers,key:a)");
+
+        for source in [
+            "threshold(2,ed25519:a,ed25519:b,mldsa3)",
+            "threshold(2,ed25519,mldsa3:a,true)",
+            "threshold(1,key:a,key:a,keyset:x)",
+            "threshold(2,hybrid-and(key:a,key:b),key:c)",
+        ] {
+            assert!(parse(source).is_ok(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_reference() {
+        let (column, message) = syntax_error("hybrid-or(key:)");
+        assert_eq!(column, 11);
+        assert_eq!(message, "expected a CID after 'key:'");
+        let (_, message) = syntax_error("rsa:abc");
+        assert_eq!(message, "unknown reference 'rsa:'");
+    }
+
+    #[test]
+    fn test_rejects_deep_nesting() {
+        let nested =
+            |depth: usize| format!("{}ed25519{}", "hybrid-or(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+
+        let (column, message) = syntax_error(&nested(MAX_DEPTH + 1));
+        assert_eq!(column, MAX_DEPTH * "hybrid-or(".len() + 1);
+        assert_eq!(message, "policy nested deeper than 64 levels");
+
+        // Far too deep to recurse through; fails instead of overflowing
+        let (_, message) = syntax_error(&nested(50_000));
+        assert!(message.starts_with("policy nested deeper"));
+        let (_, message) = syntax_error(&"threshold(1,".repeat(50_000));
+        assert!(message.starts_with("policy nested deeper"));
+    }
+
+    #[test]
+    fn test_rejects_combinator_without_arguments() {
+        let (column, message) = syntax_error("hybrid-and");
+        assert_eq!(column, 11);
+        assert!(message.starts_with("expected '(' after hybrid-and"));
+    }
+}
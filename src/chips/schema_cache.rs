@@ -0,0 +1,252 @@
+use crate::cas::Cas;
+use crate::chips::{normalize, schema_ref, validate};
+use crate::types::Cid;
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use jsonschema::JSONSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Default number of compiled schemas kept by a cache
+pub const DEFAULT_SCHEMA_CACHE_CAPACITY: usize = 256;
+
+/// Counters of a `SchemaCache`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+/// Compiled JSON Schemas keyed by schema_cid
+///
+/// A CID names immutable bytes, so a validator compiled for a CID never goes
+/// stale. The cache is bounded; when full, the least recently used entry is
+/// evicted.
+pub struct SchemaCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Cid, Entry>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+struct Entry {
+    schema: Arc<JSONSchema>,
+    last_used: u64,
+}
+
+impl SchemaCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Compiled validator for `schema_cid`, fetching and compiling on a miss
+    ///
+    /// The schema must be stored in `cas` even when its validator is cached,
+    /// since a cache may be shared between stores.
+    pub fn get_or_compile(&self, schema_cid: &Cid, cas: &Cas) -> Result<Arc<JSONSchema>> {
+        if cas.contains(schema_cid) {
+            if let Some(schema) = self.lookup(schema_cid) {
+                return Ok(schema);
+            }
+        }
+
+        let schema_bytes = cas
+            .get(schema_cid)
+            .map_err(|e| RhoError::Validate(format!("Schema not found in CAS: {}", e)))?;
+        let schema_json: Value = serde_json::from_slice(&schema_bytes)
+            .map_err(|e| RhoError::Validate(format!("Invalid schema JSON: {}", e)))?;
+        let compiled = compile(&schema_json, cas)?;
+        Ok(self.insert(schema_cid.clone(), compiled))
+    }
+
+    /// Add an already compiled schema, e.g. right after storing it
+    pub fn insert(&self, schema_cid: Cid, schema: JSONSchema) -> Arc<JSONSchema> {
+        let schema = Arc::new(schema);
+        if self.capacity == 0 {
+            return schema;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        if !inner.entries.contains_key(&schema_cid) && inner.entries.len() >= self.capacity {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(cid, _)| cid.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+                inner.stats.evictions += 1;
+            }
+        }
+        inner.entries.insert(
+            schema_cid,
+            Entry {
+                schema: schema.clone(),
+                last_used: tick,
+            },
+        );
+        schema
+    }
+
+    /// True if a validator for `schema_cid` is cached
+    pub fn contains(&self, schema_cid: &Cid) -> bool {
+        self.inner.lock().unwrap().entries.contains_key(schema_cid)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            entries: inner.entries.len(),
+            capacity: self.capacity,
+            ..inner.stats
+        }
+    }
+
+    /// Drop all compiled schemas; counters are kept
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    fn lookup(&self, schema_cid: &Cid) -> Option<Arc<JSONSchema>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+        match inner.entries.get_mut(schema_cid) {
+            Some(entry) => {
+                entry.last_used = tick;
+                let schema = entry.schema.clone();
+                inner.stats.hits += 1;
+                Some(schema)
+            }
+            None => {
+                inner.stats.misses += 1;
+                None
+            }
+        }
+    }
+}
+
+impl Default for SchemaCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SCHEMA_CACHE_CAPACITY)
+    }
+}
+
+impl fmt::Debug for SchemaCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaCache")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Store `schema` in CAS, cache its validator and point `ref_name` at it
+///
+/// The schema must compile. Returns the schema CID.
+pub fn register(schema: Value, ref_name: &str, cas: &Cas) -> Result<Cid> {
+    let compiled = compile(&schema, cas)?;
+    let normalized = normalize(schema)?;
+    let schema_cid = cas.put(BASE64.decode(&normalized.bytes)?)?;
+    validate::schema_cache().insert(schema_cid.clone(), compiled);
+    cas.set_ref(ref_name, schema_cid.clone());
+    Ok(schema_cid)
+}
+
+/// Compile a JSON Schema, resolving `cas:` and `rho://` references
+pub fn compile(schema: &Value, cas: &Cas) -> Result<JSONSchema> {
+    schema_ref::compile_with_refs(schema, cas).map(|(compiled, _)| compiled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store(cas: &Cas, schema: Value) -> Cid {
+        cas.put(serde_json::to_vec(&schema).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_hits_after_first_compile() {
+        let cas = Cas::new();
+        let cid = store(&cas, json!({"type": "string"}));
+        let cache = SchemaCache::new(4);
+
+        let first = cache.get_or_compile(&cid, &cas).unwrap();
+        let second = cache.get_or_compile(&cid, &cas).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(second.is_valid(&json!("ok")));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cas = Cas::new();
+        let a = store(&cas, json!({"type": "string"}));
+        let b = store(&cas, json!({"type": "integer"}));
+        let c = store(&cas, json!({"type": "boolean"}));
+        let cache = SchemaCache::new(2);
+
+        cache.get_or_compile(&a, &cas).unwrap();
+        cache.get_or_compile(&b, &cas).unwrap();
+        cache.get_or_compile(&a, &cas).unwrap(); // a is now newer than b
+        cache.get_or_compile(&c, &cas).unwrap(); // evicts b
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+
+        cache.get_or_compile(&a, &cas).unwrap();
+        assert_eq!(cache.stats().hits, 2);
+        cache.get_or_compile(&b, &cas).unwrap();
+        assert_eq!(cache.stats().misses, 4);
+    }
+
+    #[test]
+    fn test_errors_are_not_cached() {
+        let cas = Cas::new();
+        let cache = SchemaCache::new(2);
+        let bad = store(&cas, json!({"type": 5}));
+        assert!(cache.get_or_compile(&bad, &cas).is_err());
+        assert!(cache.get_or_compile(&"missing".to_string(), &cas).is_err());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_cached_schema_must_be_in_cas() {
+        let (cas, other) = (Cas::new(), Cas::new());
+        let cid = store(&cas, json!({"type": "string"}));
+        let cache = SchemaCache::new(2);
+        cache.get_or_compile(&cid, &cas).unwrap();
+        assert!(cache.get_or_compile(&cid, &other).is_err());
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_caching() {
+        let cas = Cas::new();
+        let cid = store(&cas, json!({"type": "string"}));
+        let cache = SchemaCache::new(0);
+        cache.get_or_compile(&cid, &cas).unwrap();
+        cache.get_or_compile(&cid, &cas).unwrap();
+        assert_eq!(cache.stats().hits, 0);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -0,0 +1,316 @@
+// Cross-CAS `$ref` resolution for JSON Schemas
+//
+// A schema may `$ref` another schema stored in CAS as `cas:<cid>` or
+// `rho://<cid>`, optionally followed by a `#/json/pointer` fragment. The
+// referenced schemas are loaded from CAS before compiling; nothing is ever
+// fetched over the network, and any other scheme is rejected. References
+// between documents must not form a cycle; content addressing rules that out
+// for honest stores, but the walk checks anyway rather than loop.
+
+use crate::cas::Cas;
+use crate::chips::validate::schema_options;
+use crate::types::Cid;
+use crate::{Result, RhoError};
+use jsonschema::{JSONSchema, SchemaResolver, SchemaResolverError};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use url::Url;
+
+/// URI prefixes resolved through CAS
+pub const REF_SCHEMES: [&str; 2] = ["cas:", "rho://"];
+
+/// Schema CID named by a `cas:` or `rho://` reference, fragment stripped
+pub fn ref_cid(reference: &str) -> Option<Cid> {
+    let rest = REF_SCHEMES
+        .iter()
+        .find_map(|scheme| reference.strip_prefix(scheme))?;
+    let cid = rest.split('#').next().unwrap_or_default();
+    (!cid.is_empty()).then(|| cid.to_string())
+}
+
+/// CAS references directly made by `schema`, in document order
+///
+/// Fails on an absolute reference with any other scheme.
+pub fn direct_refs(schema: &Value) -> Result<Vec<Cid>> {
+    let mut refs = Vec::new();
+    collect_refs(schema, &mut refs)?;
+    Ok(refs)
+}
+
+/// Every schema reachable from `schema_cid` through `$ref`, itself included
+///
+/// The CIDs are sorted; pinning them keeps the schema compilable. Fails on a
+/// missing schema or a reference cycle.
+pub fn schema_closure(schema_cid: &Cid, cas: &Cas) -> Result<Vec<Cid>> {
+    let mut documents = BTreeMap::new();
+    visit(
+        schema_cid,
+        &|cid| load(cid, cas),
+        &mut Vec::new(),
+        &mut documents,
+    )?;
+    Ok(documents.into_keys().collect())
+}
+
+/// Compile `schema`, resolving its CAS references
+///
+/// Returns the validator and the sorted closure of referenced schema CIDs
+/// (the root is not included unless referenced).
+pub fn compile_with_refs(schema: &Value, cas: &Cas) -> Result<(JSONSchema, Vec<Cid>)> {
+    let mut documents = BTreeMap::new();
+    let mut stack = Vec::new();
+    for cid in direct_refs(schema)? {
+        visit(&cid, &|cid| load(cid, cas), &mut stack, &mut documents)?;
+    }
+    let closure = documents.keys().cloned().collect();
+
+    let compiled = schema_options()
+        .with_resolver(CasResolver { documents })
+        .compile(schema)
+        .map_err(|e| RhoError::Validate(format!("Failed to compile schema: {}", e)))?;
+    Ok((compiled, closure))
+}
+
+/// Depth-first load of `cid` and its references; `stack` is the current path
+fn visit(
+    cid: &Cid,
+    load: &dyn Fn(&Cid) -> Result<Value>,
+    stack: &mut Vec<Cid>,
+    documents: &mut BTreeMap<Cid, Arc<Value>>,
+) -> Result<()> {
+    if let Some(start) = stack.iter().position(|c| c == cid) {
+        let mut cycle = stack[start..].to_vec();
+        cycle.push(cid.clone());
+        return Err(RhoError::Validate(format!(
+            "Schema reference cycle: {}",
+            cycle.join(" -> ")
+        )));
+    }
+    if documents.contains_key(cid) {
+        return Ok(());
+    }
+
+    let document = load(cid)?;
+    stack.push(cid.clone());
+    for child in direct_refs(&document)? {
+        visit(&child, load, stack, documents)?;
+    }
+    stack.pop();
+    documents.insert(cid.clone(), Arc::new(document));
+    Ok(())
+}
+
+fn load(cid: &Cid, cas: &Cas) -> Result<Value> {
+    let bytes = cas
+        .get(cid)
+        .map_err(|e| RhoError::Validate(format!("Referenced schema {} not found: {}", cid, e)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| RhoError::Validate(format!("Referenced schema {} is not JSON: {}", cid, e)))
+}
+
+fn collect_refs(value: &Value, refs: &mut Vec<Cid>) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                match (key.as_str(), child) {
+                    ("$ref", Value::String(reference)) => {
+                        if let Some(cid) = ref_cid(reference) {
+                            if !refs.contains(&cid) {
+                                refs.push(cid);
+                            }
+                        } else if Url::parse(reference).is_ok() {
+                            // References are resolved lazily while validating, so
+                            // refuse foreign schemes before they can be fetched
+                            return Err(RhoError::Validate(format!(
+                                "Unsupported schema reference {}: only cas: and rho:// are resolved",
+                                reference
+                            )));
+                        }
+                    }
+                    _ => collect_refs(child, refs)?,
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_refs(item, refs)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Serves preloaded CAS documents to the compiler; never goes to the network
+struct CasResolver {
+    documents: BTreeMap<Cid, Arc<Value>>,
+}
+
+impl SchemaResolver for CasResolver {
+    fn resolve(
+        &self,
+        _root_schema: &Value,
+        url: &Url,
+        original_reference: &str,
+    ) -> std::result::Result<Arc<Value>, SchemaResolverError> {
+        // The URL parser may alter a base64 CID (e.g. lowercase a `rho://`
+        // host), so prefer the reference as written
+        let cid = ref_cid(original_reference)
+            .or_else(|| ref_cid(url.as_str()))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unsupported schema reference {}: only cas: and rho:// are resolved",
+                    original_reference
+                )
+            })?;
+        self.documents
+            .get(&cid)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("schema {} is not in CAS", cid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chips::validate;
+    use serde_json::json;
+
+    fn store(cas: &Cas, schema: Value) -> Cid {
+        cas.put(serde_json::to_vec(&schema).unwrap()).unwrap()
+    }
+
+    fn shared_definitions(cas: &Cas) -> (Cid, Cid) {
+        let cid = store(
+            cas,
+            json!({"type": "string", "pattern": "^[A-Za-z0-9+/]+=*$"}),
+        );
+        let timestamp = store(
+            cas,
+            json!({
+                "definitions": {
+                    "rfc3339": {"type": "string", "pattern": "^\\d{4}-\\d{2}-\\d{2}T"}
+                }
+            }),
+        );
+        (cid, timestamp)
+    }
+
+    #[test]
+    fn test_ref_cid() {
+        assert_eq!(ref_cid("cas:abc/d+e="), Some("abc/d+e=".to_string()));
+        assert_eq!(ref_cid("rho://ABC#/definitions/x"), Some("ABC".to_string()));
+        assert_eq!(ref_cid("cas:"), None);
+        assert_eq!(ref_cid("#/definitions/x"), None);
+        assert_eq!(ref_cid("https://example.com/s.json"), None);
+    }
+
+    #[test]
+    fn test_passport_refs_shared_definitions() {
+        let cas = Cas::new();
+        let (cid, timestamp) = shared_definitions(&cas);
+        let passport = store(
+            &cas,
+            json!({
+                "type": "object",
+                "properties": {
+                    "owner": {"$ref": format!("cas:{}", cid)},
+                    "issued_at": {"$ref": format!("rho://{}#/definitions/rfc3339", timestamp)}
+                },
+                "required": ["owner", "issued_at"]
+            }),
+        );
+
+        let ok = json!({"owner": "aGVsbG8=", "issued_at": "2026-01-01T00:00:00Z"});
+        assert!(validate(ok, passport.clone(), &cas).unwrap().valid);
+
+        let bad_owner = json!({"owner": "not a cid!", "issued_at": "2026-01-01T00:00:00Z"});
+        assert!(!validate(bad_owner, passport.clone(), &cas).unwrap().valid);
+
+        let bad_time = json!({"owner": "aGVsbG8=", "issued_at": "yesterday"});
+        assert!(!validate(bad_time, passport.clone(), &cas).unwrap().valid);
+
+        let mut expected = vec![passport.clone(), cid, timestamp];
+        expected.sort();
+        assert_eq!(schema_closure(&passport, &cas).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_closure_is_transitive() {
+        let cas = Cas::new();
+        let leaf = store(&cas, json!({"type": "integer"}));
+        let middle = store(&cas, json!({"items": {"$ref": format!("cas:{}", leaf)}}));
+        let root = json!({"properties": {"xs": {"$ref": format!("cas:{}", middle)}}});
+
+        let (compiled, closure) = compile_with_refs(&root, &cas).unwrap();
+        let mut expected = vec![leaf, middle];
+        expected.sort();
+        assert_eq!(closure, expected);
+        assert!(compiled.is_valid(&json!({"xs": [1, 2]})));
+        assert!(!compiled.is_valid(&json!({"xs": ["a"]})));
+    }
+
+    #[test]
+    fn test_cycle_detected() {
+        // Unreachable with real hashes, so load from a hand-built table
+        let table: BTreeMap<Cid, Value> = [
+            ("a", json!({"properties": {"b": {"$ref": "cas:b"}}})),
+            ("b", json!({"items": {"$ref": "rho://c#/definitions/x"}})),
+            ("c", json!({"definitions": {"x": {"$ref": "cas:a"}}})),
+        ]
+        .into_iter()
+        .map(|(cid, schema)| (cid.to_string(), schema))
+        .collect();
+        let load = |cid: &Cid| Ok(table[cid].clone());
+
+        let err = visit(
+            &"a".to_string(),
+            &load,
+            &mut Vec::new(),
+            &mut BTreeMap::new(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("a -> b -> c -> a"));
+    }
+
+    #[test]
+    fn test_shared_reference_is_not_a_cycle() {
+        let cas = Cas::new();
+        let leaf = store(&cas, json!({"type": "integer"}));
+        let root = json!({
+            "properties": {
+                "x": {"$ref": format!("cas:{}", leaf)},
+                "y": {"items": {"$ref": format!("cas:{}", leaf)}}
+            }
+        });
+        let (_, closure) = compile_with_refs(&root, &cas).unwrap();
+        assert_eq!(closure, vec![leaf]);
+    }
+
+    #[test]
+    fn test_missing_reference_fails() {
+        let cas = Cas::new();
+        let root = json!({"$ref": "cas:bm90IGluIGNhcw=="});
+        let err = compile_with_refs(&root, &cas).unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_no_network_fallback() {
+        let cas = Cas::new();
+        let root = json!({"properties": {"x": {"$ref": "https://example.com/schema.json"}}});
+        let err = compile_with_refs(&root, &cas).unwrap_err();
+        assert!(err.to_string().contains("Unsupported schema reference"));
+
+        // Local pointers are still fine
+        let local = json!({
+            "definitions": {"n": {"type": "integer"}},
+            "items": {"$ref": "#/definitions/n"}
+        });
+        let (compiled, closure) = compile_with_refs(&local, &cas).unwrap();
+        assert!(closure.is_empty());
+        assert!(compiled.is_valid(&json!([1])));
+        assert!(!compiled.is_valid(&json!(["x"])));
+    }
+}
@@ -0,0 +1,189 @@
+// ChipSpec port dialect
+//
+// `ChipSpec.inputs` and `ChipSpec.outputs` are objects mapping a field name to
+// a descriptor. A descriptor is a JSON Schema with three extensions:
+//
+//   "type": "any"       any value; the type constraint is dropped (also when
+//                       "any" appears in a type array)
+//   "optional": true    the field may be absent; fields are required by default
+//   "encoding": "base64" the string is base64; becomes "contentEncoding"
+//
+// Every other keyword is plain JSON Schema and is copied unchanged, including
+// nested schemas (the extensions only apply at the field level). A port
+// admits an object with the declared fields and no others.
+
+use crate::cas::Cas;
+use crate::chips::normalize;
+use crate::types::Cid;
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Map, Value};
+
+/// Encodings accepted by the `encoding` extension
+pub const ENCODINGS: [&str; 1] = ["base64"];
+
+/// CAS ref prefix of the stored schema of a spec's port
+const PORT_SCHEMA_REF: &str = "chip.port";
+
+/// Translate a port declaration into a JSON Schema
+pub fn port_schema(fields: &Value) -> Result<Value> {
+    let fields = fields
+        .as_object()
+        .ok_or_else(|| dialect_error("ports must be objects of field descriptors"))?;
+
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, descriptor) in fields {
+        let (schema, optional) = field_schema(name, descriptor)?;
+        if !optional {
+            required.push(Value::String(name.clone()));
+        }
+        properties.insert(name.clone(), schema);
+    }
+
+    Ok(json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false
+    }))
+}
+
+/// Translate a port declaration and store the schema in CAS
+pub fn store_port_schema(fields: &Value, cas: &Cas) -> Result<Cid> {
+    let normalized = normalize(port_schema(fields)?)?;
+    cas.put(BASE64.decode(&normalized.bytes)?)
+}
+
+/// Schema CID of a spec's port, translated and stored on first use
+///
+/// Remembered under a CAS ref per spec CID, so each port is translated once.
+pub fn port_schema_cid(spec_cid: &Cid, port: &str, fields: &Value, cas: &Cas) -> Result<Cid> {
+    let name = format!("{}.{}:{}", PORT_SCHEMA_REF, port, spec_cid);
+    if let Some(schema_cid) = cas.get_ref(&name) {
+        return Ok(schema_cid);
+    }
+    let schema_cid = store_port_schema(fields, cas)?;
+    cas.set_ref(&name, schema_cid.clone());
+    Ok(schema_cid)
+}
+
+fn field_schema(name: &str, descriptor: &Value) -> Result<(Value, bool)> {
+    let descriptor = descriptor
+        .as_object()
+        .ok_or_else(|| dialect_error(&format!("field {} must be an object", name)))?;
+
+    let mut schema = Map::new();
+    let mut optional = false;
+    for (keyword, value) in descriptor {
+        match (keyword.as_str(), value) {
+            ("optional", Value::Bool(flag)) => optional = *flag,
+            ("optional", _) => {
+                return Err(dialect_error(&format!(
+                    "field {}: optional must be a boolean",
+                    name
+                )))
+            }
+            ("type", Value::String(kind)) if kind == "any" => {}
+            ("type", Value::Array(kinds)) if kinds.iter().any(|k| k == "any") => {}
+            ("encoding", Value::String(encoding)) if ENCODINGS.contains(&encoding.as_str()) => {
+                schema.insert("contentEncoding".to_string(), value.clone());
+            }
+            ("encoding", _) => {
+                return Err(dialect_error(&format!(
+                    "field {}: unsupported encoding {}",
+                    name, value
+                )))
+            }
+            _ => {
+                schema.insert(keyword.clone(), value.clone());
+            }
+        }
+    }
+    Ok((Value::Object(schema), optional))
+}
+
+fn dialect_error(message: &str) -> RhoError {
+    RhoError::Validate(format!("Invalid chip spec port: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chips::schema_cache;
+
+    #[test]
+    fn test_translates_extensions() {
+        let fields = json!({
+            "value": {"type": "any"},
+            "bytes": {"type": "string", "encoding": "base64"},
+            "fields": {"type": "object", "optional": true},
+            "level": {"enum": ["info", "warn"]}
+        });
+        assert_eq!(
+            port_schema(&fields).unwrap(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "value": {},
+                    "bytes": {"type": "string", "contentEncoding": "base64"},
+                    "fields": {"type": "object"},
+                    "level": {"enum": ["info", "warn"]}
+                },
+                "required": ["bytes", "level", "value"],
+                "additionalProperties": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_port_schema_enforces_declaration() {
+        let cas = Cas::new();
+        let fields = json!({
+            "message": {"type": "string"},
+            "value": {"type": ["any", "null"]},
+            "fields": {"type": "object", "optional": true}
+        });
+        let schema = schema_cache::compile(&port_schema(&fields).unwrap(), &cas).unwrap();
+
+        assert!(schema.is_valid(&json!({"message": "hi", "value": [1]})));
+        assert!(schema.is_valid(&json!({"message": "hi", "value": 1, "fields": {}})));
+        assert!(!schema.is_valid(&json!({"value": 1})));
+        assert!(!schema.is_valid(&json!({"message": 1, "value": 1})));
+        assert!(!schema.is_valid(&json!({"message": "hi", "value": 1, "extra": 1})));
+
+        let encoded = json!({"bytes": {"type": "string", "encoding": "base64"}});
+        let schema = schema_cache::compile(&port_schema(&encoded).unwrap(), &cas).unwrap();
+        assert!(schema.is_valid(&json!({"bytes": "aGk="})));
+        assert!(!schema.is_valid(&json!({"bytes": "not base64!"})));
+    }
+
+    #[test]
+    fn test_rejects_malformed_ports() {
+        assert!(port_schema(&json!([])).is_err());
+        assert!(port_schema(&json!({"x": "string"})).is_err());
+        assert!(port_schema(&json!({"x": {"optional": "yes"}})).is_err());
+        assert!(port_schema(&json!({"x": {"encoding": "hex"}})).is_err());
+        assert_eq!(port_schema(&json!({})).unwrap()["required"], json!([]));
+    }
+
+    #[test]
+    fn test_port_schema_stored_once_per_spec() {
+        let cas = Cas::new();
+        let spec_cid = cas.put(b"spec".to_vec()).unwrap();
+        let fields = json!({"value": {"type": "any"}});
+        let schema_cid = port_schema_cid(&spec_cid, "inputs", &fields, &cas).unwrap();
+        assert_eq!(schema_cid, store_port_schema(&fields, &cas).unwrap());
+
+        // Later lookups for the spec use the remembered schema
+        let other = json!({"other": {"type": "string"}});
+        assert_eq!(
+            port_schema_cid(&spec_cid, "inputs", &other, &cas).unwrap(),
+            schema_cid
+        );
+        assert_ne!(
+            port_schema_cid(&spec_cid, "outputs", &other, &cas).unwrap(),
+            schema_cid
+        );
+    }
+}
@@ -1,9 +1,24 @@
 use crate::cas::Cas;
 use crate::chips::normalize;
-use crate::types::{Cid, ValidateOutput};
+use crate::chips::schema_cache::SchemaCache;
+use crate::types::{Cid, ValidateOutput, ValidationError};
 use crate::{Result, RhoError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
-use serde_json::Value;
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::paths::PathChunk;
+use jsonschema::{CompilationOptions, JSONSchema};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Compiled JSON Schemas shared by every CAS in the process
+///
+/// A CID names the same bytes in every CAS, so a validator compiled from one
+/// store is valid for all of them.
+pub fn schema_cache() -> &'static SchemaCache {
+    static SCHEMAS: OnceLock<SchemaCache> = OnceLock::new();
+    SCHEMAS.get_or_init(SchemaCache::default)
+}
 
 /// Validate a JSON value against a JSON Schema stored in CAS
 ///
@@ -11,6 +26,8 @@ use serde_json::Value;
 /// 1. Input value → normalize() → canonical bytes → store in CAS by normalized.cid
 /// 2. Schema → fetch from CAS by schema_cid → parse → compile
 /// 3. Validate canonical value against schema
+///
+/// Compiled schemas are reused from `schema_cache()`.
 pub fn validate(value: Value, schema_cid: Cid, cas: &Cas) -> Result<ValidateOutput> {
     // Step 1: Normalize input to canonical form
     let normalized_value = normalize(value)?;
@@ -29,40 +46,246 @@ pub fn validate(value: Value, schema_cid: Cid, cas: &Cas) -> Result<ValidateOutp
         )));
     }
 
-    // Step 3: Fetch schema from CAS by schema_cid (schema must be pre-normalized and stored)
-    let schema_bytes = cas
-        .get(&schema_cid)
-        .map_err(|e| RhoError::Validate(format!("Schema not found in CAS: {}", e)))?;
-
-    let schema_json: Value = serde_json::from_slice(&schema_bytes)
-        .map_err(|e| RhoError::Validate(format!("Invalid schema JSON: {}", e)))?;
+    // Step 3: Fetch and compile the schema, or reuse the cached validator
+    let compiled = schema_cache().get_or_compile(&schema_cid, cas)?;
 
     // Step 4: Parse canonical value from CAS
     let canonical_value: Value = serde_json::from_slice(&canonical_bytes)
         .map_err(|e| RhoError::Validate(format!("Failed to parse canonical value: {}", e)))?;
 
-    // Step 5: Compile schema and validate
-    let compiled = jsonschema::JSONSchema::compile(&schema_json)
-        .map_err(|e| RhoError::Validate(format!("Failed to compile schema: {}", e)))?;
-
+    // Step 5: Validate
     if compiled.is_valid(&canonical_value) {
         Ok(ValidateOutput {
             valid: true,
             errors: None,
         })
     } else {
-        let validation_result = compiled.validate(&canonical_value);
-        let error_messages: Vec<String> = match validation_result {
-            Err(errors) => errors
-                .map(|e| format!("{}: {}", e.instance_path, e))
-                .collect(),
+        let errors = match compiled.validate(&canonical_value) {
+            Err(errors) => errors.map(validation_error).collect(),
             Ok(_) => vec![],
         };
 
         Ok(ValidateOutput {
             valid: false,
-            errors: Some(error_messages),
+            errors: Some(errors),
+        })
+    }
+}
+
+/// Custom formats registered with the schema compiler
+pub const FORMATS: [&str; 4] = ["cid", "rfc3339", "semver", "canonical-decimal"];
+
+/// Schema compiler options with the rho formats registered
+pub fn schema_options() -> CompilationOptions {
+    let mut options = JSONSchema::options();
+    options
+        .with_format("cid", is_cid)
+        .with_format("rfc3339", is_rfc3339)
+        .with_format("semver", is_semver)
+        .with_format("canonical-decimal", is_canonical_decimal);
+    options
+}
+
+/// Standard padded base64 of a 32-byte blake3 hash
+pub fn is_cid(s: &str) -> bool {
+    BASE64.decode(s).is_ok_and(|hash| hash.len() == 32)
+}
+
+/// RFC 3339 date-time in whole seconds, as accepted by `Timestamp::parse_rfc3339`
+pub fn is_rfc3339(s: &str) -> bool {
+    crate::clock::Timestamp::parse_rfc3339(s).is_ok()
+}
+
+/// Semantic Version 2.0.0, e.g. `1.2.3-rc.1+build.5`
+pub fn is_semver(s: &str) -> bool {
+    let (rest, build) = match s.split_once('+') {
+        Some((rest, build)) => (rest, Some(build)),
+        None => (s, None),
+    };
+    let (core, pre) = match rest.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (rest, None),
+    };
+
+    let numbers: Vec<&str> = core.split('.').collect();
+    numbers.len() == 3
+        && numbers.iter().all(|n| is_numeric_identifier(n))
+        && pre.is_none_or(|pre| {
+            pre.split('.').all(|id| {
+                is_alphanumeric_identifier(id)
+                    && (!id.bytes().all(|b| b.is_ascii_digit()) || is_numeric_identifier(id))
+            })
+        })
+        && build.is_none_or(|build| build.split('.').all(is_alphanumeric_identifier))
+}
+
+/// Canonical decimal string of a scaled integer, e.g. `0.15` or `-12.5`
+///
+/// No sign on positives, no leading zeros, no trailing fractional zeros and
+/// no `-0`. Bounded: the digits read as one integer must fit in i64, which is
+/// how the value is stored once scaled.
+pub fn is_canonical_decimal(s: &str) -> bool {
+    let negative = s.starts_with('-');
+    let unsigned = s.strip_prefix('-').unwrap_or(s);
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => {
+            let canonical = !fraction.is_empty()
+                && fraction.bytes().all(|b| b.is_ascii_digit())
+                && !fraction.ends_with('0');
+            if !canonical {
+                return false;
+            }
+            (integer, fraction)
+        }
+        None => (unsigned, ""),
+    };
+
+    is_numeric_identifier(integer)
+        && !(negative && integer == "0" && fraction.is_empty())
+        && format!("{}{}{}", if negative { "-" } else { "" }, integer, fraction)
+            .parse::<i64>()
+            .is_ok()
+}
+
+/// Digits without a leading zero (other than `0` itself)
+fn is_numeric_identifier(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) && (id == "0" || !id.starts_with('0'))
+}
+
+fn is_alphanumeric_identifier(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Structured form of a jsonschema error
+pub fn validation_error(error: jsonschema::ValidationError) -> ValidationError {
+    let message = error.to_string();
+    // Errors not tied to a keyword fall back to the last keyword of the path
+    let keyword = keyword(&error.kind)
+        .map(str::to_string)
+        .or_else(|| {
+            error
+                .schema_path
+                .iter()
+                .rev()
+                .find_map(|chunk| match chunk {
+                    PathChunk::Keyword(keyword) => Some(keyword.to_string()),
+                    _ => None,
+                })
         })
+        .unwrap_or_default();
+
+    ValidationError {
+        instance_path: error.instance_path.to_string(),
+        schema_path: error.schema_path.to_string(),
+        keyword,
+        message,
+        params: params(error.kind),
+    }
+}
+
+/// The schema keyword that failed
+///
+/// A `false` subschema reports `false`.
+fn keyword(kind: &ValidationErrorKind) -> Option<&'static str> {
+    use ValidationErrorKind as K;
+    Some(match kind {
+        K::AdditionalItems { .. } => "additionalItems",
+        K::AdditionalProperties { .. } => "additionalProperties",
+        K::AnyOf => "anyOf",
+        K::BacktrackLimitExceeded { .. } | K::Pattern { .. } => "pattern",
+        K::Constant { .. } => "const",
+        K::Contains => "contains",
+        K::ContentEncoding { .. } => "contentEncoding",
+        K::ContentMediaType { .. } => "contentMediaType",
+        K::Enum { .. } => "enum",
+        K::ExclusiveMaximum { .. } => "exclusiveMaximum",
+        K::ExclusiveMinimum { .. } => "exclusiveMinimum",
+        K::FalseSchema => "false",
+        K::Format { .. } => "format",
+        K::InvalidReference { .. } | K::UnknownReferenceScheme { .. } | K::Resolver { .. } => {
+            "$ref"
+        }
+        K::MaxItems { .. } => "maxItems",
+        K::Maximum { .. } => "maximum",
+        K::MaxLength { .. } => "maxLength",
+        K::MaxProperties { .. } => "maxProperties",
+        K::MinItems { .. } => "minItems",
+        K::Minimum { .. } => "minimum",
+        K::MinLength { .. } => "minLength",
+        K::MinProperties { .. } => "minProperties",
+        K::MultipleOf { .. } => "multipleOf",
+        K::Not { .. } => "not",
+        K::OneOfMultipleValid | K::OneOfNotValid => "oneOf",
+        K::PropertyNames { .. } => "propertyNames",
+        K::Required { .. } => "required",
+        K::Type { .. } => "type",
+        K::UnevaluatedProperties { .. } => "unevaluatedProperties",
+        K::UniqueItems => "uniqueItems",
+        _ => return None,
+    })
+}
+
+fn params(kind: ValidationErrorKind) -> BTreeMap<String, Value> {
+    use ValidationErrorKind as K;
+    let params = match kind {
+        K::AdditionalItems { limit } => vec![("limit", json!(limit))],
+        K::AdditionalProperties { unexpected } | K::UnevaluatedProperties { unexpected } => {
+            vec![("unexpected", json!(unexpected))]
+        }
+        K::Constant { expected_value } => vec![("expected", expected_value)],
+        K::ContentEncoding { content_encoding } => {
+            vec![("content_encoding", json!(content_encoding))]
+        }
+        K::ContentMediaType { content_media_type } => {
+            vec![("content_media_type", json!(content_media_type))]
+        }
+        K::Enum { options } => vec![("options", options)],
+        K::ExclusiveMaximum { limit }
+        | K::ExclusiveMinimum { limit }
+        | K::Maximum { limit }
+        | K::Minimum { limit } => vec![("limit", limit)],
+        K::MaxItems { limit }
+        | K::MaxLength { limit }
+        | K::MaxProperties { limit }
+        | K::MinItems { limit }
+        | K::MinLength { limit }
+        | K::MinProperties { limit } => vec![("limit", json!(limit))],
+        K::MultipleOf { multiple_of } => vec![("multiple_of", json!(multiple_of))],
+        K::Not { schema } => vec![("schema", schema)],
+        K::Pattern { pattern } => vec![("pattern", json!(pattern))],
+        K::PropertyNames { error } => vec![("error", json!(error.to_string()))],
+        K::Required { property } => vec![("property", property)],
+        K::Type { kind } => {
+            let expected: Vec<String> = match kind {
+                TypeKind::Single(primitive) => vec![primitive.to_string()],
+                TypeKind::Multiple(primitives) => {
+                    primitives.into_iter().map(|p| p.to_string()).collect()
+                }
+            };
+            vec![("expected", json!(expected))]
+        }
+        K::Format { format } => vec![("format", json!(format))],
+        K::UnknownReferenceScheme { scheme } => vec![("scheme", json!(scheme))],
+        K::Resolver { url, .. } => vec![("url", json!(url.as_str()))],
+        _ => vec![],
+    };
+    params
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), canonical_param(value)))
+        .collect()
+}
+
+/// Write numbers the canon rejects (floats, u64 above i64) as strings
+fn canonical_param(value: Value) -> Value {
+    match value {
+        Value::Number(n) if n.as_i64().is_none() => Value::String(n.to_string()),
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical_param).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, canonical_param(value)))
+                .collect(),
+        ),
+        other => other,
     }
 }
 
@@ -143,6 +366,144 @@ mod tests {
         assert!(result.errors.is_some());
     }
 
+    #[test]
+    fn test_formats() {
+        let cid = crate::chips::normalize(json!({"a": 1})).unwrap().cid;
+        assert!(is_cid(&cid));
+        assert!(!is_cid("cid_123"));
+        assert!(!is_cid(&BASE64.encode([0u8; 16])));
+        assert!(!is_cid(cid.trim_end_matches('=')));
+
+        assert!(is_rfc3339("2024-01-01T12:00:00Z"));
+        assert!(is_rfc3339("2024-01-01T12:00:00+02:00"));
+        assert!(!is_rfc3339("2024-01-01T12:00:00.5Z"));
+        assert!(!is_rfc3339("2024-01-01"));
+        assert!(!is_rfc3339("yesterday"));
+
+        for ok in [
+            "1.0.0",
+            "0.10.2",
+            "1.2.3-rc.1",
+            "1.2.3-0.alpha",
+            "1.0.0+build.5-x",
+        ] {
+            assert!(is_semver(ok), "{}", ok);
+        }
+        for bad in [
+            "1.0",
+            "v1.0.0",
+            "01.0.0",
+            "1.0.0-",
+            "1.0.0-01",
+            "1.0.0+",
+            "1.0.0-a..b",
+        ] {
+            assert!(!is_semver(bad), "{}", bad);
+        }
+
+        for ok in ["0", "3", "-12.5", "0.15", "9223372036854775807", "-0.5"] {
+            assert!(is_canonical_decimal(ok), "{}", ok);
+        }
+        for bad in [
+            "+1",
+            "01",
+            "1.50",
+            "-0",
+            "1.",
+            ".5",
+            "1e3",
+            "9223372036854775808",
+            "0.99999999999999999999",
+        ] {
+            assert!(!is_canonical_decimal(bad), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_validate_applies_formats() {
+        let cas = Cas::new();
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "cid": {"format": "cid"},
+                "at": {"format": "rfc3339"},
+                "version": {"format": "semver"},
+                "ratio": {"format": "canonical-decimal"}
+            }
+        });
+        let schema_cid = cas.put(serde_json::to_vec(&schema).unwrap()).unwrap();
+        let cid = crate::chips::normalize(json!(1)).unwrap().cid;
+
+        let ok =
+            json!({"cid": cid, "at": "2024-01-01T00:00:00Z", "version": "1.0.0", "ratio": "0.15"});
+        assert!(validate(ok, schema_cid.clone(), &cas).unwrap().valid);
+
+        let bad = json!({"cid": "x", "at": "now", "version": "1", "ratio": "0.150"});
+        let errors = validate(bad, schema_cid, &cas).unwrap().errors.unwrap();
+        assert_eq!(errors.len(), 4);
+        assert!(errors.iter().all(|e| e.keyword == "format"));
+    }
+
+    #[test]
+    fn test_keyword_of_false_schema_and_additional_properties() {
+        let cas = Cas::new();
+        let schema = json!({
+            "type": "object",
+            "properties": {"secret": false},
+            "additionalProperties": false
+        });
+        let schema_cid = cas.put(serde_json::to_vec(&schema).unwrap()).unwrap();
+
+        let value = json!({"secret": 1, "extra": 2});
+        let errors = validate(value, schema_cid, &cas).unwrap().errors.unwrap();
+        let mut keywords: Vec<&str> = errors.iter().map(|e| e.keyword.as_str()).collect();
+        keywords.sort();
+        assert_eq!(keywords, ["additionalProperties", "false"]);
+    }
+
+    #[test]
+    fn test_validate_errors_are_structured() {
+        let cas = Cas::new();
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "tags": {"type": "array", "maxItems": 1},
+                "ratio": {"multipleOf": 2.5}
+            },
+            "required": ["id"]
+        });
+        let schema_cid = cas.put(serde_json::to_vec(&schema).unwrap()).unwrap();
+
+        let value = json!({"name": 7, "tags": ["a", "b"], "ratio": 3});
+        let errors = validate(value, schema_cid, &cas).unwrap().errors.unwrap();
+        let find = |keyword: &str| errors.iter().find(|e| e.keyword == keyword).unwrap();
+
+        let required = find("required");
+        assert_eq!(required.instance_path, "");
+        assert_eq!(required.schema_path, "/required");
+        assert_eq!(required.params["property"], json!("id"));
+
+        let mistyped = find("type");
+        assert_eq!(mistyped.instance_path, "/name");
+        assert_eq!(mistyped.schema_path, "/properties/name/type");
+        assert_eq!(mistyped.params["expected"], json!(["string"]));
+        assert!(mistyped.message.contains("is not of type"));
+
+        let too_many = find("maxItems");
+        assert_eq!(too_many.instance_path, "/tags");
+        assert_eq!(too_many.params["limit"], json!(1));
+
+        // A float keyword argument is kept as a string so the error normalizes
+        let multiple = find("multipleOf");
+        assert_eq!(multiple.params["multiple_of"], json!("2.5"));
+        let body = serde_json::to_value(&errors).unwrap();
+        let normalized = crate::chips::normalize(body).unwrap();
+        let decoded: Vec<ValidationError> =
+            serde_json::from_slice(&BASE64.decode(normalized.bytes).unwrap()).unwrap();
+        assert_eq!(decoded, errors);
+    }
+
     #[test]
     fn test_validate_rejects_float_at_normalization() {
         let cas = Cas::new();
@@ -152,7 +513,7 @@ mod tests {
         let schema_cid = cas.put(BASE64.decode(&norm_schema.bytes).unwrap()).unwrap();
 
         // Float should be rejected during normalization (THE CANON: only i64)
-        let value = json!({"count": 3.14});
+        let value = json!({"count": 2.5});
         let result = validate(value, schema_cid, &cas);
 
         assert!(result.is_err());
@@ -0,0 +1,238 @@
+// Clock: injectable time source
+//
+// Base transistors never read the clock (No-IO rule). Modules and products
+// that need a time take a `&dyn Clock`, so tests and replays can use a fixed
+// or logical clock while gateways use the system clock.
+
+use crate::{Result, RhoError};
+use chrono::{DateTime, SecondsFormat, Timelike, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// A point in time with whole-second precision, always UTC
+///
+/// Serializes as canonical RFC 3339: `YYYY-MM-DDTHH:MM:SSZ`. Only years
+/// 0000 to 9999 have that form, so timestamps are kept within them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+/// 0000-01-01T00:00:00Z
+const MIN_UNIX: i64 = -62_167_219_200;
+/// 9999-12-31T23:59:59Z
+const MAX_UNIX: i64 = 253_402_300_799;
+
+impl Timestamp {
+    /// Seconds since the Unix epoch, clamped to years 0000 to 9999
+    pub fn from_unix(seconds: i64) -> Self {
+        Self(seconds.clamp(MIN_UNIX, MAX_UNIX))
+    }
+
+    pub fn unix(&self) -> i64 {
+        self.0
+    }
+
+    /// Canonical RFC 3339 form in UTC
+    pub fn to_rfc3339(&self) -> String {
+        DateTime::<Utc>::from_timestamp(self.0, 0)
+            .expect("timestamps are within years 0000 to 9999")
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// Parse any RFC 3339 timestamp, converting offsets to UTC
+    ///
+    /// Fractional seconds are rejected rather than silently truncated.
+    pub fn parse_rfc3339(s: &str) -> Result<Self> {
+        let dt = DateTime::parse_from_rfc3339(s).map_err(|e| {
+            RhoError::InvalidInput(format!("Invalid RFC 3339 timestamp {:?}: {}", s, e))
+        })?;
+        if dt.nanosecond() != 0 {
+            return Err(RhoError::InvalidInput(format!(
+                "Timestamp {:?} has sub-second precision",
+                s
+            )));
+        }
+        Ok(Self(dt.timestamp()))
+    }
+
+    /// `None` if the result falls outside years 0000 to 9999
+    pub fn add_seconds(&self, seconds: i64) -> Option<Self> {
+        self.0
+            .checked_add(seconds)
+            .filter(|t| (MIN_UNIX..=MAX_UNIX).contains(t))
+            .map(Self)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_rfc3339())
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = RhoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse_rfc3339(s)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::parse_rfc3339(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Canonicalize a caller-supplied RFC 3339 timestamp string
+///
+/// "2024-01-01T13:00:00+01:00" → "2024-01-01T12:00:00Z"
+pub fn canonicalize(s: &str) -> Result<String> {
+    Ok(Timestamp::parse_rfc3339(s)?.to_rfc3339())
+}
+
+/// Source of time for modules
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// Always returns the same instant; for deterministic tests and replay
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(Timestamp);
+
+impl FixedClock {
+    pub fn new(at: Timestamp) -> Self {
+        Self(at)
+    }
+
+    /// Fixed clock at a parsed RFC 3339 instant
+    pub fn at(s: &str) -> Result<Self> {
+        Ok(Self(Timestamp::parse_rfc3339(s)?))
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
+
+/// Starts at a given instant and advances by `step` seconds on every read
+///
+/// Gives distinct, strictly ordered times that are still reproducible.
+#[derive(Debug)]
+pub struct LogicalClock {
+    next: AtomicI64,
+    step: i64,
+}
+
+impl LogicalClock {
+    pub fn new(start: Timestamp, step: i64) -> Self {
+        Self {
+            next: AtomicI64::new(start.unix()),
+            step,
+        }
+    }
+}
+
+impl Clock for LogicalClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_unix(self.next.fetch_add(self.step, Ordering::SeqCst))
+    }
+}
+
+/// Wall-clock time, for I/O gateways
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp(Utc::now().timestamp())
+    }
+}
+
+/// Clock pinned to 2024-01-01T12:00:00Z, shared by tests
+#[cfg(test)]
+pub(crate) fn test_clock() -> FixedClock {
+    FixedClock::at("2024-01-01T12:00:00Z").unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339_round_trip() {
+        let ts = Timestamp::parse_rfc3339("2024-01-01T12:00:00Z").unwrap();
+        assert_eq!(ts.unix(), 1_704_110_400);
+        assert_eq!(ts.to_rfc3339(), "2024-01-01T12:00:00Z");
+    }
+
+    #[test]
+    fn test_canonicalize_offsets() {
+        assert_eq!(
+            canonicalize("2024-01-01T13:30:00+01:30").unwrap(),
+            "2024-01-01T12:00:00Z"
+        );
+        assert_eq!(
+            canonicalize("2024-01-01t12:00:00z").unwrap(),
+            "2024-01-01T12:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_rejects_invalid() {
+        assert!(canonicalize("deterministic_timestamp").is_err());
+        assert!(canonicalize("2024-01-01").is_err());
+        assert!(canonicalize("2024-13-01T00:00:00Z").is_err());
+        assert!(canonicalize("2024-01-01T12:00:00.5Z").is_err());
+    }
+
+    #[test]
+    fn test_fixed_clock() {
+        let clock = test_clock();
+        assert_eq!(clock.now(), clock.now());
+        assert_eq!(clock.now().to_rfc3339(), "2024-01-01T12:00:00Z");
+    }
+
+    #[test]
+    fn test_logical_clock_advances() {
+        let clock = LogicalClock::new(Timestamp::from_unix(0), 10);
+        assert_eq!(clock.now().to_rfc3339(), "1970-01-01T00:00:00Z");
+        assert_eq!(clock.now().to_rfc3339(), "1970-01-01T00:00:10Z");
+    }
+
+    #[test]
+    fn test_timestamp_serde() {
+        let ts = Timestamp::from_unix(1_704_110_400);
+        let json = serde_json::to_value(ts).unwrap();
+        assert_eq!(json, "2024-01-01T12:00:00Z");
+        let back: Timestamp = serde_json::from_value(json).unwrap();
+        assert_eq!(back, ts);
+    }
+
+    #[test]
+    fn test_timestamp_range_round_trips() {
+        let max = Timestamp::from_unix(i64::MAX);
+        assert_eq!(max.to_rfc3339(), "9999-12-31T23:59:59Z");
+        assert_eq!(Timestamp::parse_rfc3339(&max.to_rfc3339()).unwrap(), max);
+        let min = Timestamp::from_unix(i64::MIN);
+        assert_eq!(min.to_rfc3339(), "0000-01-01T00:00:00Z");
+        assert_eq!(Timestamp::parse_rfc3339(&min.to_rfc3339()).unwrap(), min);
+
+        assert_eq!(max.add_seconds(1), None);
+        assert_eq!(min.add_seconds(i64::MIN), None);
+        assert_eq!(
+            max.add_seconds(-59).unwrap().to_rfc3339(),
+            "9999-12-31T23:59:00Z"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::types::ValidationError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,6 +12,9 @@ pub enum RhoError {
     #[error("Policy evaluation error: {0}")]
     Policy(String),
 
+    #[error("Policy syntax error at column {column}: {message}")]
+    PolicySyntax { column: usize, message: String },
+
     #[error("Compilation error: {0}")]
     Compile(String),
 
@@ -20,6 +24,24 @@ pub enum RhoError {
     #[error("CAS error: {0}")]
     Cas(String),
 
+    #[error("Keystore error: {0}")]
+    Keystore(String),
+
+    #[error("Signature error: {0}")]
+    Signature(String),
+
+    #[error("Envelope error: {0}")]
+    Envelope(String),
+
+    #[error("Capability error: {0}")]
+    Capability(String),
+
+    #[error("Chip {port} does not match the spec: {}", ValidationError::join(.errors))]
+    ChipPort {
+        port: String,
+        errors: Vec<ValidationError>,
+    },
+
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
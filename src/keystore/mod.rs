@@ -0,0 +1,286 @@
+// Key management
+//
+// Keys are identified by the CID of their raw public key bytes, the same CID
+// the CAS would assign if the public key were stored in it.
+
+pub mod rotation;
+mod store;
+
+pub use rotation::{rotate, verify_rotation};
+pub use store::{KdfParams, Keystore, Rotation};
+
+use crate::types::{Cid, Proof, Signature};
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ed25519_dalek::{Signer as _, Verifier as _};
+use fips204::ml_dsa_65;
+use fips204::traits::{KeyGen, SerDes, Signer as _, Verifier as _};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Signature algorithms supported by the keystore
+///
+/// The serialized names match the atoms of the policy language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum KeyAlgorithm {
+    #[serde(rename = "ed25519")]
+    Ed25519,
+    #[serde(rename = "mldsa3")]
+    MlDsa3,
+}
+
+impl KeyAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyAlgorithm::Ed25519 => "ed25519",
+            KeyAlgorithm::MlDsa3 => "mldsa3",
+        }
+    }
+
+    /// Parse an algorithm name (case-insensitive)
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "ed25519" => Ok(KeyAlgorithm::Ed25519),
+            "mldsa3" => Ok(KeyAlgorithm::MlDsa3),
+            other => Err(RhoError::Signature(format!(
+                "Unsupported algorithm: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compute the key ID (CID) of a raw public key
+pub fn key_id(public_key: &[u8]) -> Cid {
+    BASE64.encode(blake3::hash(public_key).as_bytes())
+}
+
+/// A signing keypair
+///
+/// Both algorithms derive their keys from a 32-byte seed, which is the only
+/// secret material that gets persisted.
+#[derive(Clone)]
+pub struct KeyPair {
+    algorithm: KeyAlgorithm,
+    seed: [u8; 32],
+    public_key: Vec<u8>,
+}
+
+impl KeyPair {
+    /// Generate a fresh keypair from the OS random number generator
+    pub fn generate(algorithm: KeyAlgorithm) -> Self {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        Self::from_seed(algorithm, seed)
+    }
+
+    /// Derive a keypair deterministically from a seed
+    pub fn from_seed(algorithm: KeyAlgorithm, seed: [u8; 32]) -> Self {
+        let public_key = match algorithm {
+            KeyAlgorithm::Ed25519 => ed25519_dalek::SigningKey::from_bytes(&seed)
+                .verifying_key()
+                .to_bytes()
+                .to_vec(),
+            KeyAlgorithm::MlDsa3 => {
+                let (pk, _) = ml_dsa_65::KG::keygen_from_seed(&seed);
+                pk.into_bytes().to_vec()
+            }
+        };
+        Self {
+            algorithm,
+            seed,
+            public_key,
+        }
+    }
+
+    pub fn algorithm(&self) -> KeyAlgorithm {
+        self.algorithm
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Public key as base64, the encoding used in `Signature.public_key`
+    pub fn public_key_b64(&self) -> String {
+        BASE64.encode(&self.public_key)
+    }
+
+    /// CID of the public key
+    pub fn key_id(&self) -> Cid {
+        key_id(&self.public_key)
+    }
+
+    pub(crate) fn seed(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    /// Sign a message
+    ///
+    /// ML-DSA signatures use the deterministic variant so that signing the
+    /// same message twice yields the same signature.
+    pub fn sign(&self, message: &[u8]) -> Result<Signature> {
+        let signature = match self.algorithm {
+            KeyAlgorithm::Ed25519 => ed25519_dalek::SigningKey::from_bytes(&self.seed)
+                .sign(message)
+                .to_bytes()
+                .to_vec(),
+            KeyAlgorithm::MlDsa3 => {
+                let (_, sk) = ml_dsa_65::KG::keygen_from_seed(&self.seed);
+                sk.try_sign_with_seed(&[0u8; 32], message, &[])
+                    .map_err(|e| RhoError::Signature(format!("ML-DSA signing failed: {}", e)))?
+                    .to_vec()
+            }
+        };
+
+        Ok(Signature {
+            algorithm: self.algorithm.as_str().to_string(),
+            public_key: self.public_key_b64(),
+            signature: BASE64.encode(signature),
+        })
+    }
+
+    /// Sign a CID (the UTF-8 bytes of its string form)
+    ///
+    /// This is how Recibo Cards are signed: the signature covers `content_cid`.
+    pub fn sign_cid(&self, cid: &Cid) -> Result<Signature> {
+        self.sign(cid.as_bytes())
+    }
+
+    /// Sign a CID and package the signature as a policy `Proof`
+    pub fn prove(&self, message_cid: &Cid) -> Result<Proof> {
+        let signature = self.sign_cid(message_cid)?;
+        Ok(Proof {
+            algorithm: signature.algorithm,
+            public_key: signature.public_key,
+            signature: signature.signature,
+            message_cid: message_cid.clone(),
+        })
+    }
+}
+
+impl fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("algorithm", &self.algorithm)
+            .field("key_id", &self.key_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Verify a signature over a message
+///
+/// Returns `Ok(false)` for a well-formed signature that does not verify, and
+/// an error when the algorithm is unknown or the key/signature are malformed.
+pub fn verify(signature: &Signature, message: &[u8]) -> Result<bool> {
+    let algorithm = KeyAlgorithm::parse(&signature.algorithm)?;
+    let public_key = BASE64.decode(&signature.public_key)?;
+    let sig_bytes = BASE64.decode(&signature.signature)?;
+
+    match algorithm {
+        KeyAlgorithm::Ed25519 => {
+            let pk: [u8; 32] = public_key
+                .try_into()
+                .map_err(|_| RhoError::Signature("Invalid ed25519 public key length".into()))?;
+            let sig: [u8; 64] = sig_bytes
+                .try_into()
+                .map_err(|_| RhoError::Signature("Invalid ed25519 signature length".into()))?;
+            let vk = ed25519_dalek::VerifyingKey::from_bytes(&pk)
+                .map_err(|e| RhoError::Signature(format!("Invalid ed25519 public key: {}", e)))?;
+            Ok(vk
+                .verify(message, &ed25519_dalek::Signature::from_bytes(&sig))
+                .is_ok())
+        }
+        KeyAlgorithm::MlDsa3 => {
+            let pk: [u8; ml_dsa_65::PK_LEN] = public_key
+                .try_into()
+                .map_err(|_| RhoError::Signature("Invalid mldsa3 public key length".into()))?;
+            let sig: [u8; ml_dsa_65::SIG_LEN] = sig_bytes
+                .try_into()
+                .map_err(|_| RhoError::Signature("Invalid mldsa3 signature length".into()))?;
+            let vk = ml_dsa_65::PublicKey::try_from_bytes(pk)
+                .map_err(|e| RhoError::Signature(format!("Invalid mldsa3 public key: {}", e)))?;
+            Ok(vk.verify(message, &sig, &[]))
+        }
+    }
+}
+
+/// Verify a signature over a CID
+pub fn verify_cid(signature: &Signature, cid: &Cid) -> Result<bool> {
+    verify(signature, cid.as_bytes())
+}
+
+/// Key ID of the signer of a signature
+pub fn signer_id(signature: &Signature) -> Result<Cid> {
+    Ok(key_id(&BASE64.decode(&signature.public_key)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ed25519_sign_verify() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let sig = key.sign(b"hello").unwrap();
+        assert_eq!(sig.algorithm, "ed25519");
+        assert!(verify(&sig, b"hello").unwrap());
+        assert!(!verify(&sig, b"tampered").unwrap());
+    }
+
+    #[test]
+    fn test_mldsa3_sign_verify() {
+        let key = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let sig = key.sign(b"hello").unwrap();
+        assert_eq!(sig.algorithm, "mldsa3");
+        assert!(verify(&sig, b"hello").unwrap());
+        assert!(!verify(&sig, b"tampered").unwrap());
+    }
+
+    #[test]
+    fn test_key_id_is_cas_cid() {
+        let key = KeyPair::from_seed(KeyAlgorithm::Ed25519, [7u8; 32]);
+        let cas = crate::cas::Cas::new();
+        let cid = cas.put(key.public_key().to_vec()).unwrap();
+        assert_eq!(key.key_id(), cid);
+        assert_eq!(signer_id(&key.sign(b"m").unwrap()).unwrap(), cid);
+    }
+
+    #[test]
+    fn test_from_seed_deterministic() {
+        for algorithm in [KeyAlgorithm::Ed25519, KeyAlgorithm::MlDsa3] {
+            let k1 = KeyPair::from_seed(algorithm, [1u8; 32]);
+            let k2 = KeyPair::from_seed(algorithm, [1u8; 32]);
+            assert_eq!(k1.key_id(), k2.key_id());
+            assert_eq!(
+                k1.sign(b"msg").unwrap().signature,
+                k2.sign(b"msg").unwrap().signature
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_wrong_key() {
+        let signer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let other = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut sig = signer.sign(b"msg").unwrap();
+        sig.public_key = other.public_key_b64();
+        assert!(!verify(&sig, b"msg").unwrap());
+    }
+
+    #[test]
+    fn test_verify_unknown_algorithm() {
+        let mut sig = KeyPair::generate(KeyAlgorithm::Ed25519)
+            .sign(b"msg")
+            .unwrap();
+        sig.algorithm = "rsa".to_string();
+        assert!(verify(&sig, b"msg").is_err());
+    }
+}
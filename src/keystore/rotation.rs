@@ -0,0 +1,103 @@
+use super::{key_id, signer_id, verify_cid, KeyPair};
+use crate::chips::normalize;
+use crate::rc;
+use crate::types::ReciboCard;
+use crate::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::json;
+
+/// Build a key rotation RC
+///
+/// The body names the old and new key IDs and carries the new public key.
+/// The old key signs to attest the new key; the new key signs to prove
+/// possession.
+pub fn rotate(old: &KeyPair, new: &KeyPair) -> Result<ReciboCard> {
+    let body = json!({
+        "type": "key.rotation",
+        "old_key": old.key_id(),
+        "new_key": new.key_id(),
+        "new_algorithm": new.algorithm().as_str(),
+        "new_public_key": new.public_key_b64(),
+    });
+
+    let content_cid = normalize(body.clone())?.cid;
+    let signatures = vec![old.sign_cid(&content_cid)?, new.sign_cid(&content_cid)?];
+
+    rc::emit_with_signatures(body, signatures)
+}
+
+/// Verify a key rotation RC
+///
+/// Checks the content CID, that the embedded public key matches `new_key`,
+/// and that both the old and the new key signed the card.
+pub fn verify_rotation(card: &ReciboCard) -> Result<bool> {
+    if card.body["type"] != "key.rotation" {
+        return Ok(false);
+    }
+    if normalize(card.body.clone())?.cid != card.recibo.content_cid {
+        return Ok(false);
+    }
+
+    let (Some(old_key), Some(new_key), Some(new_public_key)) = (
+        card.body["old_key"].as_str(),
+        card.body["new_key"].as_str(),
+        card.body["new_public_key"].as_str(),
+    ) else {
+        return Ok(false);
+    };
+    if key_id(&BASE64.decode(new_public_key)?) != new_key {
+        return Ok(false);
+    }
+
+    let signed_by = |expected: &str| -> Result<bool> {
+        for sig in &card.recibo.signatures {
+            if signer_id(sig)? == expected && verify_cid(sig, &rc::signing_cid(&card.recibo)?)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+
+    Ok(signed_by(old_key)? && signed_by(new_key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::KeyAlgorithm;
+
+    #[test]
+    fn test_rotate_and_verify() {
+        let old = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let new = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let card = rotate(&old, &new).unwrap();
+
+        assert_eq!(card.recibo.signatures.len(), 2);
+        assert!(verify_rotation(&card).unwrap());
+    }
+
+    #[test]
+    fn test_rotation_rejects_substituted_key() {
+        let old = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let new = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let attacker = KeyPair::generate(KeyAlgorithm::Ed25519);
+
+        let mut card = rotate(&old, &new).unwrap();
+        card.body["new_key"] = json!(attacker.key_id());
+        card.body["new_public_key"] = json!(attacker.public_key_b64());
+        card.recibo.content_cid = normalize(card.body.clone()).unwrap().cid;
+
+        assert!(!verify_rotation(&card).unwrap());
+    }
+
+    #[test]
+    fn test_rotation_requires_old_key_signature() {
+        let old = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let new = KeyPair::generate(KeyAlgorithm::Ed25519);
+
+        let mut card = rotate(&old, &new).unwrap();
+        card.recibo.signatures.remove(0);
+
+        assert!(!verify_rotation(&card).unwrap());
+    }
+}
@@ -0,0 +1,361 @@
+use super::{key_id, rotation, KeyAlgorithm, KeyPair};
+use crate::types::{Cid, ReciboCard};
+use crate::{Result, RhoError};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const KEY_FILE_VERSION: u32 = 1;
+const KEY_EXT: &str = "key";
+const ROTATION_EXT: &str = "rotation.json";
+
+/// Argon2id cost parameters used to derive the file encryption key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// On-disk key file
+///
+/// The 32-byte key seed is encrypted with XChaCha20-Poly1305 under a key
+/// derived from the password with Argon2id. The key ID is bound as AAD so a
+/// ciphertext cannot be swapped between files.
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    key_id: Cid,
+    algorithm: KeyAlgorithm,
+    public_key: String,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Result of a key rotation
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub key: KeyPair,
+    pub card: ReciboCard,
+}
+
+/// Password-encrypted keystore backed by a directory
+pub struct Keystore {
+    dir: PathBuf,
+    kdf: KdfParams,
+}
+
+impl Keystore {
+    /// Open (and create if needed) a keystore directory
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            kdf: KdfParams::default(),
+        })
+    }
+
+    /// Override the Argon2id cost parameters for newly written key files
+    pub fn with_kdf_params(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    /// Generate a new keypair and store it encrypted under `password`
+    pub fn generate(&self, algorithm: KeyAlgorithm, password: &str) -> Result<KeyPair> {
+        let key = KeyPair::generate(algorithm);
+        self.import(&key, password)?;
+        Ok(key)
+    }
+
+    /// Store an existing keypair encrypted under `password`
+    pub fn import(&self, key: &KeyPair, password: &str) -> Result<Cid> {
+        let key_id = key.key_id();
+
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 24];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = cipher_for(password, &salt, &self.kdf)?;
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: key.seed(),
+                    aad: key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| RhoError::Keystore("Failed to encrypt key".to_string()))?;
+
+        let file = KeyFile {
+            version: KEY_FILE_VERSION,
+            key_id: key_id.clone(),
+            algorithm: key.algorithm(),
+            public_key: key.public_key_b64(),
+            kdf: self.kdf,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        fs::write(
+            self.path_for(&key_id, KEY_EXT)?,
+            serde_json::to_vec_pretty(&file)?,
+        )?;
+
+        Ok(key_id)
+    }
+
+    /// Decrypt and load a keypair
+    pub fn load(&self, key_id: &Cid, password: &str) -> Result<KeyPair> {
+        let file = self.read_key_file(key_id)?;
+        let salt = BASE64.decode(&file.salt)?;
+        let nonce = BASE64.decode(&file.nonce)?;
+        let ciphertext = BASE64.decode(&file.ciphertext)?;
+        if nonce.len() != 24 {
+            return Err(RhoError::Keystore("Invalid nonce length".to_string()));
+        }
+
+        let cipher = cipher_for(password, &salt, &file.kdf)?;
+        let seed = cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: file.key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| RhoError::Keystore("Wrong password or corrupted key file".to_string()))?;
+        let seed: [u8; 32] = seed
+            .try_into()
+            .map_err(|_| RhoError::Keystore("Invalid key seed length".to_string()))?;
+
+        let key = KeyPair::from_seed(file.algorithm, seed);
+        if key.key_id() != *key_id {
+            return Err(RhoError::CidMismatch {
+                expected: key_id.clone(),
+                actual: key.key_id(),
+            });
+        }
+        Ok(key)
+    }
+
+    /// Public key and algorithm of a stored key (no password required)
+    pub fn public_key(&self, key_id: &Cid) -> Result<(KeyAlgorithm, Vec<u8>)> {
+        let file = self.read_key_file(key_id)?;
+        let public_key = BASE64.decode(&file.public_key)?;
+        if super::key_id(&public_key) != *key_id {
+            return Err(RhoError::Keystore(format!(
+                "Key file for {} holds a different public key",
+                key_id
+            )));
+        }
+        Ok((file.algorithm, public_key))
+    }
+
+    /// List the IDs of all stored keys, sorted
+    pub fn list(&self) -> Result<Vec<Cid>> {
+        let suffix = format!(".{}", KEY_EXT);
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if let Some(stem) = name.strip_suffix(&suffix) {
+                if let Ok(hash) = URL_SAFE_NO_PAD.decode(stem) {
+                    ids.push(BASE64.encode(hash));
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Rotate a key
+    ///
+    /// Generates a new key of `algorithm`, stores it under `new_password`, and
+    /// records a rotation RC signed by both the old and the new key.
+    pub fn rotate(
+        &self,
+        old_key_id: &Cid,
+        password: &str,
+        algorithm: KeyAlgorithm,
+        new_password: &str,
+    ) -> Result<Rotation> {
+        let old = self.load(old_key_id, password)?;
+        let new = self.generate(algorithm, new_password)?;
+        let card = rotation::rotate(&old, &new)?;
+
+        fs::write(
+            self.path_for(&new.key_id(), ROTATION_EXT)?,
+            serde_json::to_vec_pretty(&card)?,
+        )?;
+
+        Ok(Rotation { key: new, card })
+    }
+
+    /// Verified rotation history leading to `key_id`, oldest first
+    ///
+    /// Each rotation RC is verified, and must name the previous entry's new
+    /// key as its old key.
+    pub fn history(&self, key_id: &Cid) -> Result<Vec<ReciboCard>> {
+        let mut chain = Vec::new();
+        let mut current = key_id.clone();
+
+        loop {
+            let path = self.path_for(&current, ROTATION_EXT)?;
+            if !path.exists() {
+                break;
+            }
+            let card: ReciboCard = serde_json::from_slice(&fs::read(path)?)?;
+            if !rotation::verify_rotation(&card)? {
+                return Err(RhoError::Keystore(format!(
+                    "Invalid rotation record for {}",
+                    current
+                )));
+            }
+            if card.body["new_key"] != current.as_str() {
+                return Err(RhoError::Keystore(format!(
+                    "Rotation record for {} names a different key",
+                    current
+                )));
+            }
+            current = card.body["old_key"]
+                .as_str()
+                .ok_or_else(|| RhoError::Keystore("Rotation record missing old_key".into()))?
+                .to_string();
+            if chain.len() > 1024 || current == *key_id {
+                return Err(RhoError::Keystore("Rotation history has a cycle".into()));
+            }
+            chain.push(card);
+        }
+
+        chain.reverse();
+        Ok(chain)
+    }
+
+    fn read_key_file(&self, key_id: &Cid) -> Result<KeyFile> {
+        let path = self.path_for(key_id, KEY_EXT)?;
+        let bytes = fs::read(&path)
+            .map_err(|_| RhoError::Keystore(format!("Key not found: {}", key_id)))?;
+        let file: KeyFile = serde_json::from_slice(&bytes)?;
+        if file.version != KEY_FILE_VERSION {
+            return Err(RhoError::Keystore(format!(
+                "Unsupported key file version: {}",
+                file.version
+            )));
+        }
+        if file.key_id != *key_id || key_id_of(&file)? != *key_id {
+            return Err(RhoError::Keystore(format!(
+                "Key file for {} is inconsistent",
+                key_id
+            )));
+        }
+        Ok(file)
+    }
+
+    /// Key IDs are standard base64 and may contain '/', so file names use
+    /// the URL-safe encoding of the same hash.
+    fn path_for(&self, key_id: &Cid, ext: &str) -> Result<PathBuf> {
+        let hash = BASE64
+            .decode(key_id)
+            .map_err(|_| RhoError::Keystore(format!("Invalid key ID: {}", key_id)))?;
+        Ok(self
+            .dir
+            .join(format!("{}.{}", URL_SAFE_NO_PAD.encode(hash), ext)))
+    }
+}
+
+fn key_id_of(file: &KeyFile) -> Result<Cid> {
+    Ok(key_id(&BASE64.decode(&file.public_key)?))
+}
+
+fn cipher_for(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<XChaCha20Poly1305> {
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| RhoError::Keystore(format!("Invalid KDF parameters: {}", e)))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| RhoError::Keystore(format!("Key derivation failed: {}", e)))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST_KDF: KdfParams = KdfParams {
+        m_cost: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    fn temp_keystore(name: &str) -> Keystore {
+        let dir =
+            std::env::temp_dir().join(format!("rho-keystore-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Keystore::open(dir).unwrap().with_kdf_params(FAST_KDF)
+    }
+
+    #[test]
+    fn test_generate_and_load() {
+        let ks = temp_keystore("load");
+        for algorithm in [KeyAlgorithm::Ed25519, KeyAlgorithm::MlDsa3] {
+            let key = ks.generate(algorithm, "secret").unwrap();
+            let loaded = ks.load(&key.key_id(), "secret").unwrap();
+            assert_eq!(loaded.key_id(), key.key_id());
+            assert_eq!(loaded.algorithm(), algorithm);
+        }
+        assert_eq!(ks.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let ks = temp_keystore("password");
+        let key = ks.generate(KeyAlgorithm::Ed25519, "secret").unwrap();
+        assert!(ks.load(&key.key_id(), "wrong").is_err());
+    }
+
+    #[test]
+    fn test_public_key_without_password() {
+        let ks = temp_keystore("public");
+        let key = ks.generate(KeyAlgorithm::Ed25519, "secret").unwrap();
+        let (algorithm, public_key) = ks.public_key(&key.key_id()).unwrap();
+        assert_eq!(algorithm, KeyAlgorithm::Ed25519);
+        assert_eq!(public_key, key.public_key());
+    }
+
+    #[test]
+    fn test_rotation_history() {
+        let ks = temp_keystore("rotate");
+        let k1 = ks.generate(KeyAlgorithm::Ed25519, "p1").unwrap();
+        let r2 = ks
+            .rotate(&k1.key_id(), "p1", KeyAlgorithm::MlDsa3, "p2")
+            .unwrap();
+        let r3 = ks
+            .rotate(&r2.key.key_id(), "p2", KeyAlgorithm::Ed25519, "p3")
+            .unwrap();
+
+        let history = ks.history(&r3.key.key_id()).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].body["old_key"], k1.key_id());
+        assert_eq!(history[1].body["new_key"], r3.key.key_id());
+        assert!(ks.history(&k1.key_id()).unwrap().is_empty());
+    }
+}
@@ -1,9 +1,12 @@
 pub mod cas;
 pub mod chips;
+pub mod clock;
 pub mod errors;
+pub mod keystore;
 pub mod modules;
 pub mod products;
 pub mod rc;
+pub mod tsa;
 pub mod types;
 
 pub use errors::{Result, RhoError};
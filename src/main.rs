@@ -1,5 +1,6 @@
 use rho_circles::cas::Cas;
 use rho_circles::chips::normalize;
+use rho_circles::clock::SystemClock;
 use rho_circles::modules;
 use rho_circles::products;
 use serde_json::json;
@@ -59,7 +60,7 @@ fn main() {
         "inputs": {"value": {"type": "string"}},
         "outputs": {"result": {"type": "string"}}
     });
-    let publish_result = modules::publish(chip_spec, "owner_demo".to_string(), &cas);
+    let publish_result = modules::publish(chip_spec, "owner_demo".to_string(), &cas, &SystemClock);
     match publish_result {
         Ok(rc) => {
             println!("   ✓ Chip published!");
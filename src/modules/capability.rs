@@ -0,0 +1,422 @@
+// Capability delegation
+//
+// A capability is an RC, signed by its issuer, granting an audience key some
+// actions on some resources, optionally until a deadline. A delegated
+// capability names its parent token in its signed body, which must match
+// `recibo.parents`, and may only narrow it. A chain is valid when it leads back to a root token issued by a trusted
+// authority and no token in it is revoked or expired.
+
+use crate::cas::Cas;
+use crate::clock::{Clock, Timestamp};
+use crate::keystore::{self, KeyPair};
+use crate::modules::glob::{glob_covers, glob_match};
+use crate::rc::{self, StatusList};
+use crate::types::{Cid, ReciboCard};
+use crate::{Result, RhoError};
+use serde::{Deserialize, Serialize};
+
+/// Body type of a capability RC
+pub const CAPABILITY_TYPE: &str = "rho.capability";
+
+/// Longest delegation chain `verify_chain` will follow
+pub const MAX_CHAIN_DEPTH: usize = 16;
+
+/// Body of a capability RC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capability {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Key ID of the signer
+    pub issuer: Cid,
+    /// Key ID the capability is granted to
+    pub audience: Cid,
+    /// Action globs
+    pub actions: Vec<String>,
+    /// Resource globs
+    pub resources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires_at: Option<Timestamp>,
+    /// Card CID of the parent token (UCAN `prf`); none for a root token
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent: Option<Cid>,
+}
+
+impl Capability {
+    /// True if the capability names this action on this resource
+    pub fn allows(&self, action: &str, resource: &str) -> bool {
+        self.actions.iter().any(|a| glob_match(a, action))
+            && self.resources.iter().any(|r| glob_match(r, resource))
+    }
+}
+
+/// What `delegate` grants
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    pub audience: Cid,
+    pub actions: Vec<String>,
+    pub resources: Vec<String>,
+    pub expires_at: Option<Timestamp>,
+}
+
+/// What a chain is checked against
+#[derive(Clone, Copy)]
+pub struct ChainCheck<'a> {
+    /// Key IDs allowed to issue root capabilities
+    pub authorities: &'a [Cid],
+    /// Revocations of any token in the chain
+    pub status_list: Option<&'a StatusList>,
+    /// Expiry is checked against this clock's current time
+    pub clock: &'a dyn Clock,
+}
+
+/// Issue a capability, signed by `issuer`, and store it in CAS
+///
+/// Without a parent this is a root capability. With one, `issuer` must be the
+/// parent's audience and the grant must attenuate the parent. Returns the
+/// card CID of the stored token.
+pub fn delegate(issuer: &KeyPair, grant: Grant, parent: Option<&Cid>, cas: &Cas) -> Result<Cid> {
+    let capability = Capability {
+        kind: CAPABILITY_TYPE.to_string(),
+        issuer: issuer.key_id(),
+        audience: grant.audience,
+        actions: grant.actions,
+        resources: grant.resources,
+        expires_at: grant.expires_at,
+        parent: parent.cloned(),
+    };
+    if let Some(parent_cid) = parent {
+        let parent = decode(&rc::load(parent_cid, cas)?)?;
+        check_attenuation(&parent, &capability)?;
+    }
+
+    let parents = parent.into_iter().cloned().collect();
+    let mut card = rc::emit_with_parents(serde_json::to_value(&capability)?, parents)?;
+    rc::sign(&mut card, issuer)?;
+    rc::store(&card, cas)
+}
+
+/// Verify the chain ending at `capability_cid` and return its leaf
+///
+/// Every token must be signed by its issuer, unrevoked and unexpired, each
+/// delegation must name its parent in its body and attenuate it, and the
+/// root must be issued by one of `check.authorities`.
+pub fn verify_chain(capability_cid: &Cid, check: &ChainCheck, cas: &Cas) -> Result<Capability> {
+    let now = check.clock.now();
+    let mut card = rc::load(capability_cid, cas)?;
+    let leaf = check_token(&card, check, now)?;
+    let mut child = leaf.clone();
+
+    for _ in 0..MAX_CHAIN_DEPTH {
+        let parent_cid = match card.recibo.parents.as_slice() {
+            [] => {
+                if !check.authorities.contains(&child.issuer) {
+                    return Err(RhoError::Capability(format!(
+                        "Root issuer {} is not an authority",
+                        child.issuer
+                    )));
+                }
+                return Ok(leaf);
+            }
+            [parent] => parent.clone(),
+            _ => {
+                return Err(RhoError::Capability(
+                    "Capability has more than one parent".to_string(),
+                ))
+            }
+        };
+        card = rc::load(&parent_cid, cas)?;
+        let parent = check_token(&card, check, now)?;
+        check_attenuation(&parent, &child)?;
+        child = parent;
+    }
+    Err(RhoError::Capability(format!(
+        "Delegation chain is longer than {}",
+        MAX_CHAIN_DEPTH
+    )))
+}
+
+/// Body of a capability card
+fn decode(card: &ReciboCard) -> Result<Capability> {
+    let capability: Capability = serde_json::from_value(card.body.clone())
+        .map_err(|e| RhoError::Capability(format!("Not a capability: {}", e)))?;
+    if capability.kind != CAPABILITY_TYPE {
+        return Err(RhoError::Capability(format!(
+            "Not a capability: type {}",
+            capability.kind
+        )));
+    }
+    Ok(capability)
+}
+
+/// Signature, parent link, revocation and expiry of one token
+fn check_token(card: &ReciboCard, check: &ChainCheck, now: Timestamp) -> Result<Capability> {
+    let capability = decode(card)?;
+    let content_cid = &card.recibo.content_cid;
+
+    if card.recibo.parents.as_slice() != capability.parent.as_slice() {
+        return Err(RhoError::Capability(format!(
+            "Capability {} names a different parent than its card",
+            content_cid
+        )));
+    }
+
+    let signed_by_issuer = rc::verify_card(card, "true")?.valid
+        && card
+            .recibo
+            .signatures
+            .iter()
+            .any(|sig| keystore::signer_id(sig).ok().as_ref() == Some(&capability.issuer));
+    if !signed_by_issuer {
+        return Err(RhoError::Capability(format!(
+            "Capability {} is not signed by its issuer",
+            content_cid
+        )));
+    }
+    if let Some(entry) = check.status_list.and_then(|list| list.status(content_cid)) {
+        return Err(RhoError::Capability(format!(
+            "Capability {} was revoked: {}",
+            content_cid, entry.reason
+        )));
+    }
+    if matches!(capability.expires_at, Some(expires_at) if now >= expires_at) {
+        return Err(RhoError::Capability(format!(
+            "Capability {} has expired",
+            content_cid
+        )));
+    }
+    Ok(capability)
+}
+
+/// `child` may only be issued by `parent`'s audience and only narrow it
+fn check_attenuation(parent: &Capability, child: &Capability) -> Result<()> {
+    if child.issuer != parent.audience {
+        return Err(RhoError::Capability(format!(
+            "{} cannot delegate a capability granted to {}",
+            child.issuer, parent.audience
+        )));
+    }
+    let covered = |parent: &[String], child: &[String]| {
+        child
+            .iter()
+            .all(|c| parent.iter().any(|p| glob_covers(p, c)))
+    };
+    if !covered(&parent.actions, &child.actions) {
+        return Err(RhoError::Capability(
+            "Delegated actions exceed the parent's".to_string(),
+        ));
+    }
+    if !covered(&parent.resources, &child.resources) {
+        return Err(RhoError::Capability(
+            "Delegated resources exceed the parent's".to_string(),
+        ));
+    }
+    match (parent.expires_at, child.expires_at) {
+        (Some(parent_expiry), Some(child_expiry)) if child_expiry > parent_expiry => Err(
+            RhoError::Capability("Delegation outlives its parent".to_string()),
+        ),
+        (Some(_), None) => Err(RhoError::Capability(
+            "Delegation outlives its parent".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::keystore::KeyAlgorithm;
+
+    fn at(s: &str) -> Timestamp {
+        Timestamp::parse_rfc3339(s).unwrap()
+    }
+
+    fn grant(audience: &KeyPair, actions: &[&str], resources: &[&str], until: &str) -> Grant {
+        Grant {
+            audience: audience.key_id(),
+            actions: actions.iter().map(|s| s.to_string()).collect(),
+            resources: resources.iter().map(|s| s.to_string()).collect(),
+            expires_at: Some(at(until)),
+        }
+    }
+
+    struct Chain {
+        cas: Cas,
+        root: KeyPair,
+        a: KeyPair,
+        b: KeyPair,
+        root_cap: Cid,
+        leaf: Cid,
+    }
+
+    /// root → A (judge/read on models/**) → B (judge on models/x)
+    fn chain() -> Chain {
+        let cas = Cas::new();
+        let root = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let a = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let b = KeyPair::generate(KeyAlgorithm::MlDsa3);
+
+        let root_cap = delegate(
+            &root,
+            grant(
+                &a,
+                &["judge", "read"],
+                &["models/**"],
+                "2025-01-01T00:00:00Z",
+            ),
+            None,
+            &cas,
+        )
+        .unwrap();
+        let leaf = delegate(
+            &a,
+            grant(&b, &["judge"], &["models/x"], "2024-07-01T00:00:00Z"),
+            Some(&root_cap),
+            &cas,
+        )
+        .unwrap();
+        Chain {
+            cas,
+            root,
+            a,
+            b,
+            root_cap,
+            leaf,
+        }
+    }
+
+    fn check<'a>(authorities: &'a [Cid], clock: &'a FixedClock) -> ChainCheck<'a> {
+        ChainCheck {
+            authorities,
+            status_list: None,
+            clock,
+        }
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let c = chain();
+        let authorities = [c.root.key_id()];
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let leaf = verify_chain(&c.leaf, &check(&authorities, &clock), &c.cas).unwrap();
+        assert_eq!(leaf.audience, c.b.key_id());
+        assert!(leaf.allows("judge", "models/x"));
+        assert!(!leaf.allows("read", "models/x"));
+
+        // Unknown root authority
+        let others = [c.a.key_id()];
+        assert!(verify_chain(&c.leaf, &check(&others, &clock), &c.cas).is_err());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let c = chain();
+        let authorities = [c.root.key_id()];
+        let clock = FixedClock::at("2024-08-01T00:00:00Z").unwrap();
+        // The leaf expires before the root does
+        assert!(verify_chain(&c.leaf, &check(&authorities, &clock), &c.cas).is_err());
+        assert!(verify_chain(&c.root_cap, &check(&authorities, &clock), &c.cas).is_ok());
+    }
+
+    #[test]
+    fn test_revoking_any_link_breaks_the_chain() {
+        let c = chain();
+        let authorities = [c.root.key_id()];
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+
+        let root_card = rc::load(&c.root_cap, &c.cas).unwrap();
+        let mut list = StatusList::new(vec![c.root.key_id()]);
+        list.add(&rc::revoke(&root_card.recibo.content_cid, "rotated", &c.root, &clock).unwrap())
+            .unwrap();
+
+        let check = ChainCheck {
+            status_list: Some(&list),
+            ..check(&authorities, &clock)
+        };
+        let err = verify_chain(&c.leaf, &check, &c.cas).unwrap_err();
+        assert!(err.to_string().contains("revoked"));
+    }
+
+    #[test]
+    fn test_delegation_must_attenuate() {
+        let c = chain();
+        // Wider resources
+        assert!(delegate(
+            &c.a,
+            grant(&c.b, &["judge"], &["**"], "2024-07-01T00:00:00Z"),
+            Some(&c.root_cap),
+            &c.cas
+        )
+        .is_err());
+        // New action
+        assert!(delegate(
+            &c.a,
+            grant(&c.b, &["delete"], &["models/x"], "2024-07-01T00:00:00Z"),
+            Some(&c.root_cap),
+            &c.cas
+        )
+        .is_err());
+        // Outlives the parent
+        assert!(delegate(
+            &c.a,
+            grant(&c.b, &["judge"], &["models/x"], "2026-01-01T00:00:00Z"),
+            Some(&c.root_cap),
+            &c.cas
+        )
+        .is_err());
+        // Only the audience can delegate
+        assert!(delegate(
+            &c.b,
+            grant(&c.b, &["judge"], &["models/x"], "2024-07-01T00:00:00Z"),
+            Some(&c.root_cap),
+            &c.cas
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_forged_link_is_rejected() {
+        let c = chain();
+        let authorities = [c.root.key_id()];
+
+        // B widens its own token by hand and re-signs it as A would
+        let mut card = rc::load(&c.leaf, &c.cas).unwrap();
+        let mut body: Capability = serde_json::from_value(card.body.clone()).unwrap();
+        body.resources = vec!["**".to_string()];
+        card.body = serde_json::to_value(&body).unwrap();
+        card.recibo.content_cid = rc::content_cid(&card.body).unwrap();
+        card.recibo.signatures.clear();
+        rc::sign(&mut card, &c.b).unwrap();
+        let forged = rc::store(&card, &c.cas).unwrap();
+
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        assert!(verify_chain(&forged, &check(&authorities, &clock), &c.cas).is_err());
+    }
+
+    #[test]
+    fn test_parent_must_match_signed_body() {
+        let c = chain();
+        let authorities = [c.root.key_id()];
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+
+        // A signs a token whose body claims to be a root but whose card
+        // hangs it under the root capability
+        let mut body: Capability =
+            serde_json::from_value(rc::load(&c.leaf, &c.cas).unwrap().body).unwrap();
+        body.parent = None;
+        let mut card = rc::emit_with_parents(
+            serde_json::to_value(&body).unwrap(),
+            vec![c.root_cap.clone()],
+        )
+        .unwrap();
+        rc::sign(&mut card, &c.a).unwrap();
+        let unlinked = rc::store(&card, &c.cas).unwrap();
+
+        let err = verify_chain(&unlinked, &check(&authorities, &clock), &c.cas).unwrap_err();
+        assert!(err.to_string().contains("different parent"));
+
+        let leaf: Capability =
+            serde_json::from_value(rc::load(&c.leaf, &c.cas).unwrap().body).unwrap();
+        assert_eq!(leaf.parent, Some(c.root_cap));
+    }
+}
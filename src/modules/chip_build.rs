@@ -1,24 +1,65 @@
 use crate::cas::Cas;
-use crate::chips::compile;
-use crate::rc;
+use crate::chips::{compile, normalize};
+use crate::clock::{Clock, Timestamp};
+use crate::rc::{self, Kind};
 use crate::types::{ChipSpec, Cid, ReciboCard};
 use crate::Result;
 use base64::Engine;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Body of a `rc.chip.build.v1` receipt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildReceipt {
+    pub spec_cid: Cid,
+    pub rb_cid: Cid,
+    pub compiled_at: Timestamp,
+}
+
+impl Kind for BuildReceipt {
+    const KIND: &'static str = "rc.chip.build.v1";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "spec_cid": {"type": "string"},
+                "rb_cid": {"type": "string"},
+                "compiled_at": {"type": "string"}
+            },
+            "required": ["spec_cid", "rb_cid", "compiled_at"],
+            "additionalProperties": false
+        })
+    }
+}
 
 /// mod.chip.build: Compile chip to bytecode
 ///
-/// Pipeline: cas.get(spec_cid) → compile → cas.put(rb_cid) → rc.emit
+/// Pipeline: cas.get(spec_cid) → compile → cas.put(rb_cid) → rc.emit → rc.store
 ///
 /// Inputs:
 /// - spec_cid: CID of the chip spec in CAS
+/// - clock: time source for `compiled_at`
 ///
-/// Output: RC containing the compiled bytecode CID
-pub fn build(spec_cid: Cid, cas: &Cas) -> Result<ReciboCard> {
+/// Output: RC of kind `rc.chip.build.v1` containing the compiled bytecode
+/// CID. Its parent is the publish RC of the spec, if the spec was published
+/// through `mod.chip.publish`.
+pub fn build(spec_cid: Cid, cas: &Cas, clock: &dyn Clock) -> Result<ReciboCard> {
     // Fetch chip spec from CAS
     let spec_bytes = cas.get(&spec_cid)?;
     let chip_spec: ChipSpec = serde_json::from_slice(&spec_bytes)?;
 
+    // Keep the canonical spec under the CID the bytecode embeds, so that eval
+    // can find the declared ports even if the published spec had extra keys
+    let canonical = normalize(serde_json::to_value(&chip_spec)?)?;
+    cas.put(base64::engine::general_purpose::STANDARD.decode(&canonical.bytes)?)?;
+
+    // Store each wiring op under the CID the bytecode refers to it by
+    for op in chip_spec.wiring.iter().flatten() {
+        let op = normalize(op.clone())?;
+        cas.put(base64::engine::general_purpose::STANDARD.decode(&op.bytes)?)?;
+    }
+
     // Compile the chip spec to bytecode
     let compiled = compile(chip_spec, None)?;
 
@@ -35,19 +76,25 @@ pub fn build(spec_cid: Cid, cas: &Cas) -> Result<ReciboCard> {
     }
 
     // Build result
-    let result = json!({
-        "spec_cid": spec_cid,
-        "rb_cid": rb_cid,
-        "compiled_at": "deterministic_timestamp",
-    });
-
-    // Emit as RC
-    rc::emit(result)
+    let result = BuildReceipt {
+        spec_cid: spec_cid.clone(),
+        rb_cid: rb_cid.clone(),
+        compiled_at: clock.now(),
+    };
+
+    // Emit as RC linked to the publish receipt, and record it as the origin of rb_cid
+    let mut card = rc::emit_typed(&result, vec![])?;
+    card.recibo.parents = rc::producer(&spec_cid, cas).into_iter().collect();
+    let card_cid = rc::store(&card, cas)?;
+    rc::record_producer(&rb_cid, &card_cid, cas);
+
+    Ok(card)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::test_clock;
     use base64::Engine;
     use serde_json::json;
 
@@ -75,20 +122,45 @@ mod tests {
         let spec_cid = cas.put(spec_bytes).unwrap();
 
         // Build the chip
-        let result = build(spec_cid, &cas);
+        let result = build(spec_cid, &cas, &test_clock());
         assert!(result.is_ok());
         let rc = result.unwrap();
         assert!(rc.body["rb_cid"].is_string());
         assert_eq!(rc.body["spec_cid"], normalized.cid);
+        assert_eq!(rc.body["compiled_at"], "2024-01-01T12:00:00Z");
+
+        let receipt: BuildReceipt = rc::decode(&rc).unwrap();
+        assert_eq!(receipt.spec_cid, normalized.cid);
     }
 
     #[test]
     fn test_build_missing_spec() {
         let cas = Cas::new();
-        let result = build("nonexistent_cid".to_string(), &cas);
+        let result = build("nonexistent_cid".to_string(), &cas, &test_clock());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_build_links_to_publish_receipt() {
+        let cas = Cas::new();
+        let spec = json!({
+            "chip": "test.chip",
+            "version": "1.0.0",
+            "type": "module",
+            "inputs": {},
+            "outputs": {}
+        });
+        let published =
+            crate::modules::publish(spec, "owner".to_string(), &cas, &test_clock()).unwrap();
+        let chip_cid = published.body["chip_cid"].as_str().unwrap().to_string();
+
+        let built = build(chip_cid, &cas, &test_clock()).unwrap();
+        assert_eq!(
+            built.recibo.parents,
+            vec![rc::card_cid(&published).unwrap()]
+        );
+    }
+
     #[test]
     fn test_build_deterministic() {
         let cas = Cas::new();
@@ -113,8 +185,8 @@ mod tests {
         let spec_cid = cas.put(spec_bytes).unwrap();
 
         // Build twice
-        let rc1 = build(spec_cid.clone(), &cas).unwrap();
-        let rc2 = build(spec_cid, &cas).unwrap();
+        let rc1 = build(spec_cid.clone(), &cas, &test_clock()).unwrap();
+        let rc2 = build(spec_cid, &cas, &test_clock()).unwrap();
         assert_eq!(rc1.body["rb_cid"], rc2.body["rb_cid"]);
     }
 }
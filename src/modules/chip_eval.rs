@@ -1,45 +1,116 @@
 use crate::cas::Cas;
-use crate::chips::{exec, normalize};
+use crate::chips::bytecode::{self, Bytecode};
+use crate::chips::{exec, spec_schema, validate};
 use crate::rc;
-use crate::types::{Cid, ReciboCard};
-use crate::Result;
+use crate::types::{ChipSpec, Cid, ReciboCard};
+use crate::{Result, RhoError};
 use serde_json::{json, Value};
 
 /// mod.chip.eval: Execute chip
 ///
-/// Pipeline: cas.get(rb_cid) → normalize(inputs) → exec → rc.emit
+/// Pipeline: cas.get(rb_cid) → cas.get(spec_cid) → validate(inputs) → exec →
+/// validate(outputs) → rc.emit → rc.store
 ///
 /// Inputs:
 /// - rb_cid: CID of the bytecode in CAS
 /// - chip_inputs: Object with input values
 ///
-/// Output: RC containing execution results
+/// The spec embedded in the bytecode must be in CAS. Inputs are checked
+/// against its declared ports (see `chips::spec_schema`), and so are outputs
+/// for opcodes that compute them (`exec::computes_outputs`); a mismatch fails
+/// with `RhoError::ChipPort` carrying the validation errors.
+///
+/// Output: RC containing execution results. Its parent is the build RC of
+/// `rb_cid`, if the bytecode was built through `mod.chip.build`.
 pub fn eval(rb_cid: Cid, chip_inputs: Value, cas: &Cas) -> Result<ReciboCard> {
-    // Normalize inputs
-    let _normalized_inputs = normalize(chip_inputs.clone())?;
+    // Resolve the spec behind the bytecode
+    let (program, spec) = load_spec(&rb_cid, cas)?;
 
-    // Execute the chip
+    // Execute the chip on checked inputs, and check what it produced
+    check_port(&program.spec_cid, "inputs", &spec.inputs, &chip_inputs, cas)?;
     let exec_output = exec(rb_cid.clone(), chip_inputs, cas)?;
+    if exec::computes_outputs(program.opcode) {
+        check_port(
+            &program.spec_cid,
+            "outputs",
+            &spec.outputs,
+            &exec_output.body,
+            cas,
+        )?;
+    }
 
     // Build result
     let result = json!({
         "rb_cid": rb_cid,
         "body": exec_output.body,
-        "content_cid": exec_output.content_cid,
+        "content_cid": &exec_output.content_cid,
     });
 
-    // Emit as RC
-    rc::emit(result)
+    // Emit as RC linked to the build receipt, and record it as the origin of the output
+    let parents = rc::producer(&rb_cid, cas).into_iter().collect();
+    let card = rc::emit_with_parents(result, parents)?;
+    let card_cid = rc::store(&card, cas)?;
+    rc::record_producer(&exec_output.content_cid, &card_cid, cas);
+
+    Ok(card)
+}
+
+fn load_spec(rb_cid: &Cid, cas: &Cas) -> Result<(Bytecode, ChipSpec)> {
+    let bytecode = cas
+        .get(rb_cid)
+        .map_err(|e| RhoError::Exec(format!("Bytecode not found in CAS: {}", e)))?;
+    let program = bytecode::decode(&bytecode)?;
+    let spec_bytes = cas
+        .get(&program.spec_cid)
+        .map_err(|e| RhoError::Exec(format!("Chip spec of {} not found in CAS: {}", rb_cid, e)))?;
+    let spec = serde_json::from_slice(&spec_bytes)?;
+    Ok((program, spec))
+}
+
+fn check_port(spec_cid: &Cid, port: &str, fields: &Value, value: &Value, cas: &Cas) -> Result<()> {
+    let schema_cid = spec_schema::port_schema_cid(spec_cid, port, fields, cas)?;
+    let validation = validate(value.clone(), schema_cid, cas)?;
+    if !validation.valid {
+        return Err(RhoError::ChipPort {
+            port: port.to_string(),
+            errors: validation.errors.unwrap_or_default(),
+        });
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::chips::compile;
+    use crate::chips::normalize;
+    use crate::clock::test_clock;
     use crate::types::ChipSpec;
     use base64::Engine;
     use serde_json::json;
 
+    /// Compile `chip_spec` and store its bytecode and canonical spec
+    fn store_chip(chip_spec: ChipSpec, cas: &Cas) -> Cid {
+        let canonical = normalize(serde_json::to_value(&chip_spec).unwrap()).unwrap();
+        let engine = base64::engine::general_purpose::STANDARD;
+        cas.put(engine.decode(&canonical.bytes).unwrap()).unwrap();
+        let compiled = compile(chip_spec, None).unwrap();
+        cas.put(engine.decode(&compiled.rb_bytes).unwrap()).unwrap()
+    }
+
+    fn echo_chip(inputs: Value, outputs: Value) -> ChipSpec {
+        ChipSpec {
+            chip: "test.echo".to_string(),
+            version: "1.0.0".to_string(),
+            chip_type: crate::types::ChipType::Module,
+            inputs,
+            outputs,
+            determinism: None,
+            opcode: Some(10),
+            wiring: None,
+        }
+    }
+
     #[test]
     fn test_eval_valid_chip() {
         let cas = Cas::new();
@@ -56,11 +127,7 @@ mod tests {
             wiring: None,
         };
 
-        let compiled = compile(chip_spec, None).unwrap();
-        let rb_bytes = base64::engine::general_purpose::STANDARD
-            .decode(&compiled.rb_bytes)
-            .unwrap();
-        let rb_cid = cas.put(rb_bytes).unwrap();
+        let rb_cid = store_chip(chip_spec, &cas);
 
         // Evaluate the chip
         let inputs = json!({});
@@ -77,6 +144,34 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_eval_lineage_reaches_published_spec() {
+        let cas = Cas::new();
+        let clock = test_clock();
+        let spec = json!({
+            "chip": "test.chip",
+            "version": "1.0.0",
+            "type": "module",
+            "inputs": {},
+            "outputs": {}
+        });
+        let published = crate::modules::publish(spec, "owner".to_string(), &cas, &clock).unwrap();
+        let chip_cid = published.body["chip_cid"].as_str().unwrap().to_string();
+        let built = crate::modules::build(chip_cid.clone(), &cas, &clock).unwrap();
+        let rb_cid = built.body["rb_cid"].as_str().unwrap().to_string();
+
+        let evaluated = eval(rb_cid, json!({}), &cas).unwrap();
+        let eval_cid = rc::card_cid(&evaluated).unwrap();
+
+        // "Which published spec produced this output?"
+        let origins = rc::origins(&eval_cid, &cas).unwrap();
+        assert_eq!(origins, vec![rc::card_cid(&published).unwrap()]);
+        let origin = rc::load(&origins[0], &cas).unwrap();
+        assert_eq!(origin.body["chip_cid"], chip_cid);
+
+        assert_eq!(rc::lineage(&eval_cid, &cas).unwrap().len(), 3);
+    }
+
     #[test]
     fn test_eval_deterministic() {
         let cas = Cas::new();
@@ -93,11 +188,7 @@ mod tests {
             wiring: None,
         };
 
-        let compiled = compile(chip_spec, None).unwrap();
-        let rb_bytes = base64::engine::general_purpose::STANDARD
-            .decode(&compiled.rb_bytes)
-            .unwrap();
-        let rb_cid = cas.put(rb_bytes).unwrap();
+        let rb_cid = store_chip(chip_spec, &cas);
 
         // Evaluate twice
         let inputs = json!({});
@@ -105,4 +196,78 @@ mod tests {
         let rc2 = eval(rb_cid, inputs, &cas).unwrap();
         assert_eq!(rc1.body["content_cid"], rc2.body["content_cid"]);
     }
+
+    #[test]
+    fn test_eval_requires_spec_in_cas() {
+        let cas = Cas::new();
+        let compiled = compile(echo_chip(json!({}), json!({})), None).unwrap();
+        let rb_bytes = base64::engine::general_purpose::STANDARD
+            .decode(&compiled.rb_bytes)
+            .unwrap();
+        let rb_cid = cas.put(rb_bytes).unwrap();
+
+        let err = eval(rb_cid, json!({}), &cas).unwrap_err();
+        assert!(err.to_string().contains("Chip spec"));
+    }
+
+    #[test]
+    fn test_eval_checks_inputs() {
+        let cas = Cas::new();
+        let ports = json!({
+            "message": {"type": "string"},
+            "fields": {"type": "object", "optional": true}
+        });
+        let rb_cid = store_chip(echo_chip(ports.clone(), ports), &cas);
+
+        assert!(eval(rb_cid.clone(), json!({"message": "hi"}), &cas).is_ok());
+
+        let inputs = json!({"message": 7, "extra": true});
+        match eval(rb_cid, inputs, &cas).unwrap_err() {
+            RhoError::ChipPort { port, errors } => {
+                assert_eq!(port, "inputs");
+                let mut keywords: Vec<_> = errors.iter().map(|e| e.keyword.as_str()).collect();
+                keywords.sort();
+                assert_eq!(keywords, vec!["additionalProperties", "type"]);
+                assert!(errors.iter().any(|e| e.instance_path == "/message"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_eval_checks_only_computed_outputs() {
+        let cas = Cas::new();
+        let inputs = json!({"value": {"type": "any"}, "schema_cid": {"type": "string"}});
+        let schema_cid = cas.put(br#"{"type":"integer"}"#.to_vec()).unwrap();
+        let request = json!({"value": 1, "schema_cid": schema_cid});
+
+        // An echo module's declared outputs need not match its inputs
+        let outputs = json!({"result": {"type": "string"}});
+        let echo = store_chip(echo_chip(inputs.clone(), outputs.clone()), &cas);
+        assert!(eval(echo, request.clone(), &cas).is_ok());
+
+        // rho.validate computes its outputs, so they are checked
+        let validator = |outputs: Value| ChipSpec {
+            opcode: Some(3),
+            ..echo_chip(inputs.clone(), outputs)
+        };
+        let declared = json!({
+            "valid": {"type": "boolean"},
+            "errors": {"type": "array", "optional": true}
+        });
+        let rb_cid = store_chip(validator(declared), &cas);
+        assert_eq!(
+            eval(rb_cid, request.clone(), &cas).unwrap().body["body"]["valid"],
+            true
+        );
+
+        let rb_cid = store_chip(validator(outputs), &cas);
+        match eval(rb_cid, request, &cas).unwrap_err() {
+            RhoError::ChipPort { port, errors } => {
+                assert_eq!(port, "outputs");
+                assert!(errors.iter().any(|e| e.keyword == "required"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
 }
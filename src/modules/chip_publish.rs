@@ -1,52 +1,92 @@
 use crate::cas::Cas;
-use crate::chips::{normalize, validate};
+use crate::chips::policy::{self, is_satisfiable};
+use crate::chips::{normalize, schema_cache, spec_schema, validate};
+use crate::clock::Clock;
 use crate::rc;
-use crate::types::{Cid, ReciboCard};
+use crate::types::{Cid, ReciboCard, ValidationError};
 use crate::{Result, RhoError};
 use base64::Engine;
 use serde_json::{json, Value};
 
+/// CAS ref to the schema chip specs are validated against
+pub const CHIP_SPEC_SCHEMA_REF: &str = "schema:mod.chip.spec";
+
+/// Basic chip spec schema
+pub fn chip_spec_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "chip": {"type": "string"},
+            "version": {"type": "string", "format": "semver"},
+            "type": {"type": "string", "enum": ["base", "module", "product"]},
+            "inputs": {"type": "object"},
+            "outputs": {"type": "object"},
+            "policy": {"type": "string"}
+        },
+        "required": ["chip", "version", "type", "inputs", "outputs"]
+    })
+}
+
+/// Store the chip spec schema in CAS once and return its CID
+///
+/// Later calls reuse the stored schema and its compiled validator.
+pub fn register_chip_spec_schema(cas: &Cas) -> Result<Cid> {
+    if let Some(schema_cid) = cas.get_ref(CHIP_SPEC_SCHEMA_REF) {
+        return Ok(schema_cid);
+    }
+    schema_cache::register(chip_spec_schema(), CHIP_SPEC_SCHEMA_REF, cas)
+}
+
 /// mod.chip.publish: Publish chip definition to registry
 ///
-/// Pipeline: normalize(chip_spec) → validate → cas.put → rc.emit
+/// Pipeline: normalize(chip_spec) → validate → cas.put → rc.emit → rc.store
+///
+/// The `inputs` and `outputs` ports must follow the dialect of
+/// `chips::spec_schema`. A spec may carry a signature `policy` expression;
+/// it must parse and be satisfiable, so that a chip cannot be published with
+/// a policy that can never pass.
 ///
 /// Inputs:
 /// - chip_spec: ChipSpec object
 /// - owner_cid: CID of the owner's public key
+/// - clock: time source for `published_at`
 ///
-/// Output: RC containing the published chip's CID
-pub fn publish(chip_spec: Value, owner_cid: Cid, cas: &Cas) -> Result<ReciboCard> {
+/// Output: RC containing the published chip's CID. The RC is stored in CAS and
+/// recorded as the producer of `chip_cid`, so builds of the chip link to it.
+pub fn publish(
+    chip_spec: Value,
+    owner_cid: Cid,
+    cas: &Cas,
+    clock: &dyn Clock,
+) -> Result<ReciboCard> {
     // Normalize the chip spec
     let normalized = normalize(chip_spec.clone())?;
 
-    // Define basic chip spec schema
-    let schema = json!({
-        "type": "object",
-        "properties": {
-            "chip": {"type": "string"},
-            "version": {"type": "string"},
-            "type": {"type": "string", "enum": ["base", "module", "product"]},
-            "inputs": {"type": "object"},
-            "outputs": {"type": "object"}
-        },
-        "required": ["chip", "version", "type", "inputs", "outputs"]
-    });
-
-    // Store schema in CAS
-    let schema_normalized = normalize(schema)?;
-    let schema_bytes =
-        base64::engine::general_purpose::STANDARD.decode(&schema_normalized.bytes)?;
-    let schema_cid = cas.put(schema_bytes)?;
+    let schema_cid = register_chip_spec_schema(cas)?;
 
     // Validate the chip spec
     let validation = validate(chip_spec.clone(), schema_cid, cas)?;
     if !validation.valid {
         return Err(RhoError::ValidationFailed(format!(
-            "Chip spec validation failed: {:?}",
-            validation.errors
+            "Chip spec validation failed: {}",
+            ValidationError::join(validation.errors.as_deref().unwrap_or_default())
         )));
     }
 
+    // The declared ports must translate to JSON Schemas that compile
+    for port in ["inputs", "outputs"] {
+        schema_cache::compile(&spec_schema::port_schema(&chip_spec[port])?, cas)?;
+    }
+
+    if let Some(policy_expr) = chip_spec.get("policy").and_then(Value::as_str) {
+        if !is_satisfiable(&policy::parse(policy_expr)?)? {
+            return Err(RhoError::Policy(format!(
+                "Policy can never be satisfied: {}",
+                policy_expr
+            )));
+        }
+    }
+
     // Store the chip spec in CAS
     let chip_bytes = base64::engine::general_purpose::STANDARD.decode(&normalized.bytes)?;
     let chip_cid = cas.put(chip_bytes)?;
@@ -55,16 +95,21 @@ pub fn publish(chip_spec: Value, owner_cid: Cid, cas: &Cas) -> Result<ReciboCard
     let result = json!({
         "chip_cid": chip_cid,
         "owner_cid": owner_cid,
-        "published_at": "deterministic_timestamp",  // In real impl, this would be from ledger
+        "published_at": clock.now().to_rfc3339(),
     });
 
-    // Emit as RC
-    rc::emit(result)
+    // Emit as RC and record it as the origin of the chip spec
+    let card = rc::emit(result)?;
+    let card_cid = rc::store(&card, cas)?;
+    rc::record_producer(&chip_cid, &card_cid, cas);
+
+    Ok(card)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::test_clock;
 
     #[test]
     fn test_publish_valid_chip() {
@@ -77,10 +122,74 @@ mod tests {
             "outputs": {"result": {"type": "string"}}
         });
         let owner_cid = "owner_123".to_string();
-        let result = publish(chip_spec, owner_cid, &cas);
+        let result = publish(chip_spec, owner_cid, &cas, &test_clock());
         assert!(result.is_ok());
         let rc = result.unwrap();
         assert!(rc.body["chip_cid"].is_string());
+        assert_eq!(rc.body["published_at"], "2024-01-01T12:00:00Z");
+    }
+
+    #[test]
+    fn test_publish_rejects_unsatisfiable_policy() {
+        let cas = Cas::new();
+        let spec = |policy: &str| {
+            json!({
+                "chip": "test.chip",
+                "version": "1.0.0",
+                "type": "module",
+                "inputs": {},
+                "outputs": {},
+                "policy": policy
+            })
+        };
+
+        let result = publish(
+            spec("hybrid-and(ed25519,false)"),
+            "owner".to_string(),
+            &cas,
+            &test_clock(),
+        );
+        assert!(matches!(result, Err(RhoError::Policy(_))));
+        assert!(publish(
+            spec("hybrid-and(ed25519:k,mldsa3:k)"),
+            "owner".to_string(),
+            &cas,
+            &test_clock()
+        )
+        .is_err());
+        assert!(publish(
+            spec("hybrid-or(ed25519"),
+            "owner".to_string(),
+            &cas,
+            &test_clock()
+        )
+        .is_err());
+        assert!(publish(
+            spec("threshold(1,ed25519,false)"),
+            "owner".to_string(),
+            &cas,
+            &test_clock()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_publish_compiles_schema_once() {
+        let cas = Cas::new();
+        for version in ["1.0.0", "1.0.1", "1.0.2"] {
+            let spec = json!({
+                "chip": "test.chip",
+                "version": version,
+                "type": "module",
+                "inputs": {},
+                "outputs": {}
+            });
+            publish(spec, "owner".to_string(), &cas, &test_clock()).unwrap();
+        }
+        // The cache is shared with concurrently running tests, so check the
+        // entry rather than the counters
+        let schema_cid = cas.get_ref(CHIP_SPEC_SCHEMA_REF).unwrap();
+        assert!(validate::schema_cache().contains(&schema_cid));
     }
 
     #[test]
@@ -91,10 +200,42 @@ mod tests {
             // Missing required fields
         });
         let owner_cid = "owner_123".to_string();
-        let result = publish(chip_spec, owner_cid, &cas);
+        let result = publish(chip_spec, owner_cid, &cas, &test_clock());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_publish_requires_semver() {
+        let cas = Cas::new();
+        let spec = json!({
+            "chip": "test.chip",
+            "version": "1.0",
+            "type": "module",
+            "inputs": {},
+            "outputs": {}
+        });
+        let err = publish(spec, "owner".to_string(), &cas, &test_clock()).unwrap_err();
+        assert!(err.to_string().contains("semver"));
+    }
+
+    #[test]
+    fn test_publish_rejects_malformed_ports() {
+        let cas = Cas::new();
+        for inputs in [
+            json!({"value": {"optional": "sometimes"}}),
+            json!({"value": {"type": "strnig"}}),
+        ] {
+            let spec = json!({
+                "chip": "test.chip",
+                "version": "1.0.0",
+                "type": "module",
+                "inputs": inputs,
+                "outputs": {}
+            });
+            assert!(publish(spec, "owner".to_string(), &cas, &test_clock()).is_err());
+        }
+    }
+
     #[test]
     fn test_publish_deterministic() {
         let cas = Cas::new();
@@ -106,8 +247,8 @@ mod tests {
             "outputs": {"result": {"type": "string"}}
         });
         let owner_cid = "owner_123".to_string();
-        let rc1 = publish(chip_spec.clone(), owner_cid.clone(), &cas).unwrap();
-        let rc2 = publish(chip_spec, owner_cid, &cas).unwrap();
+        let rc1 = publish(chip_spec.clone(), owner_cid.clone(), &cas, &test_clock()).unwrap();
+        let rc2 = publish(chip_spec, owner_cid, &cas, &test_clock()).unwrap();
         assert_eq!(rc1.body["chip_cid"], rc2.body["chip_cid"]);
     }
 }
@@ -0,0 +1,130 @@
+// Glob patterns for actions and resources
+//
+// `?` is one character and `*` any run of characters other than `/`; `**`
+// also crosses `/`. Patterns are matched against concrete names, and
+// compared with each other when a capability is attenuated.
+
+/// Match `text` against a glob
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    // row[j]: the tokens from the current one on match text[j..]
+    let mut row: Vec<bool> = (0..=text.len()).map(|j| j == text.len()).collect();
+    for token in tokens(pattern).iter().rev() {
+        let next = row;
+        row = vec![false; text.len() + 1];
+        for j in (0..=text.len()).rev() {
+            row[j] = match *token {
+                Token::AnyPath => next[j] || (j < text.len() && row[j + 1]),
+                Token::AnySegment => next[j] || (j < text.len() && text[j] != '/' && row[j + 1]),
+                Token::One => j < text.len() && text[j] != '/' && next[j + 1],
+                Token::Char(c) => j < text.len() && text[j] == c && next[j + 1],
+            };
+        }
+    }
+    row[0]
+}
+
+/// True if every name matched by `child` is also matched by `parent`
+///
+/// Conservative: may reject some patterns that are in fact narrower, but
+/// never accepts a wider one.
+pub(crate) fn glob_covers(parent: &str, child: &str) -> bool {
+    let child: Vec<char> = child.chars().collect();
+    let double_star = |j: usize| child[j] == '*' && child.get(j + 1) == Some(&'*');
+    // row[j]: the parent tokens from the current one cover child[j..]
+    let mut row: Vec<bool> = (0..=child.len()).map(|j| j == child.len()).collect();
+    for token in tokens(parent).iter().rev() {
+        let next = row;
+        row = vec![false; child.len() + 1];
+        for j in (0..=child.len()).rev() {
+            let more = j < child.len();
+            row[j] = match *token {
+                // `**` absorbs anything, wildcards included
+                Token::AnyPath => next[j] || (more && row[j + 1]),
+                // `*` absorbs a run without `/` and without a child `**`
+                Token::AnySegment => {
+                    next[j] || (more && child[j] != '/' && !double_star(j) && row[j + 1])
+                }
+                Token::One => more && child[j] != '/' && child[j] != '*' && next[j + 1],
+                Token::Char(c) => more && child[j] == c && next[j + 1],
+            };
+        }
+    }
+    row[0]
+}
+
+/// One element of a glob
+#[derive(Clone, Copy)]
+enum Token {
+    /// `**`
+    AnyPath,
+    /// `*`
+    AnySegment,
+    /// `?`
+    One,
+    Char(char),
+}
+
+fn tokens(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' if chars.next_if_eq(&'*').is_some() => Token::AnyPath,
+            '*' => Token::AnySegment,
+            '?' => Token::One,
+            c => Token::Char(c),
+        });
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("reports/*", "reports/q1"));
+        assert!(!glob_match("reports/*", "reports/2024/q1"));
+        assert!(glob_match("reports/**", "reports/2024/q1"));
+        assert!(glob_match("**", ""));
+        assert!(glob_match("q?", "q1"));
+        assert!(!glob_match("q?", "q12"));
+        assert!(glob_match("*.pdf", "report.pdf"));
+        assert!(!glob_match("read", "reader"));
+    }
+
+    #[test]
+    fn test_glob_covers() {
+        assert!(glob_covers("reports/*", "reports/q1"));
+        assert!(glob_covers("reports/*", "reports/*"));
+        assert!(glob_covers("reports/*", "reports/q?"));
+        assert!(glob_covers("reports/**", "reports/2024/*"));
+        assert!(glob_covers("**", "anything/**"));
+        assert!(glob_covers("*", "read"));
+
+        // Wider children are rejected
+        assert!(!glob_covers("reports/*", "reports/**"));
+        assert!(!glob_covers("reports/*", "reports/2024/q1"));
+        assert!(!glob_covers("reports/q1", "reports/*"));
+        assert!(!glob_covers("q?", "q*"));
+        assert!(!glob_covers("read", "*"));
+    }
+
+    #[test]
+    fn test_backtracking_patterns_are_fast() {
+        let pattern = format!("{}b", "*a".repeat(6));
+        let text = "a".repeat(40);
+        let wide = format!("{}b", "**a".repeat(30));
+        let long = "a".repeat(2000);
+
+        let start = std::time::Instant::now();
+        assert!(!glob_match(&pattern, &text));
+        assert!(!glob_match(&wide, &long));
+        assert!(!glob_covers(&pattern, &text));
+        assert!(!glob_covers(&wide, &format!("{}c", "*a".repeat(1000))));
+        assert!(glob_match(&pattern, &format!("{}b", text)));
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+}
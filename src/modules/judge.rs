@@ -1,4 +1,5 @@
 use crate::cas::Cas;
+use crate::clock::Clock;
 use crate::rc;
 use crate::types::{Cid, ReciboCard};
 use crate::Result;
@@ -12,9 +13,11 @@ use serde_json::json;
 /// Inputs:
 /// - prompt_cid: CID of the prompt in CAS
 /// - policy_cid: CID of the trust policy in CAS
+/// - clock: time source for the response timestamp (a gateway passes `SystemClock`)
 ///
-/// Output: RC containing LLM response
-pub fn judge(prompt_cid: Cid, policy_cid: Cid, cas: &Cas) -> Result<ReciboCard> {
+/// Output: RC containing LLM response, linked to the RCs that produced the
+/// prompt and policy when those are known
+pub fn judge(prompt_cid: Cid, policy_cid: Cid, cas: &Cas, clock: &dyn Clock) -> Result<ReciboCard> {
     // Fetch prompt from CAS
     let prompt_bytes = cas.get(&prompt_cid)?;
     let _prompt: serde_json::Value = serde_json::from_slice(&prompt_bytes)?;
@@ -36,18 +39,26 @@ pub fn judge(prompt_cid: Cid, policy_cid: Cid, cas: &Cas) -> Result<ReciboCard>
         "policy_cid": policy_cid,
         "response": "Mock LLM response",
         "model": "mock-v1",
-        "timestamp": "deterministic_timestamp",
+        "timestamp": clock.now().to_rfc3339(),
         "tokens_used": 42,
     });
 
-    // Emit as RC
-    rc::emit(mock_response)
+    // Emit as RC linked to whatever produced the prompt and policy
+    let parents = [&prompt_cid, &policy_cid]
+        .into_iter()
+        .filter_map(|cid| rc::producer(cid, cas))
+        .collect();
+    let card = rc::emit_with_parents(mock_response, parents)?;
+    rc::store(&card, cas)?;
+
+    Ok(card)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::chips::normalize;
+    use crate::clock::test_clock;
     use base64::Engine;
     use serde_json::json;
 
@@ -72,7 +83,7 @@ mod tests {
         let policy_cid = cas.put(policy_bytes).unwrap();
 
         // Call judge
-        let result = judge(prompt_cid, policy_cid, &cas);
+        let result = judge(prompt_cid, policy_cid, &cas, &test_clock());
         assert!(result.is_ok());
         let rc = result.unwrap();
         assert_eq!(rc.body["response"], "Mock LLM response");
@@ -90,7 +101,12 @@ mod tests {
             .unwrap();
         let policy_cid = cas.put(policy_bytes).unwrap();
 
-        let result = judge("nonexistent_cid".to_string(), policy_cid, &cas);
+        let result = judge(
+            "nonexistent_cid".to_string(),
+            policy_cid,
+            &cas,
+            &test_clock(),
+        );
         assert!(result.is_err());
     }
 
@@ -115,11 +131,10 @@ mod tests {
         let policy_cid = cas.put(policy_bytes).unwrap();
 
         // Call twice
-        let rc1 = judge(prompt_cid.clone(), policy_cid.clone(), &cas).unwrap();
-        let rc2 = judge(prompt_cid, policy_cid, &cas).unwrap();
+        let rc1 = judge(prompt_cid.clone(), policy_cid.clone(), &cas, &test_clock()).unwrap();
+        let rc2 = judge(prompt_cid, policy_cid, &cas, &test_clock()).unwrap();
         // Note: In a real implementation with actual LLM calls, this might not be deterministic
-        // But the CID generation from normalized output should still be deterministic
-        assert!(!rc1.recibo.content_cid.is_empty());
-        assert!(!rc2.recibo.content_cid.is_empty());
+        // With a fixed clock the CID of the normalized output is deterministic
+        assert_eq!(rc1.recibo.content_cid, rc2.recibo.content_cid);
     }
 }
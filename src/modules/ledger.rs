@@ -1,8 +1,7 @@
 use crate::cas::Cas;
-use crate::chips::normalize;
+use crate::rc;
 use crate::types::ReciboCard;
 use crate::Result;
-use base64::Engine;
 
 /// mod.ledger.append: Append-only ledger for audit
 ///
@@ -13,27 +12,22 @@ use base64::Engine;
 ///
 /// Output: Success boolean
 pub fn append(rc: ReciboCard, cas: &Cas) -> Result<bool> {
-    // Normalize the RC for storage
-    let rc_value = serde_json::to_value(&rc)?;
-    let normalized = normalize(rc_value)?;
-
-    // Store in CAS
-    let rc_bytes = base64::engine::general_purpose::STANDARD.decode(&normalized.bytes)?;
-    let stored_cid = cas.put(rc_bytes)?;
+    // Normalize the RC and store it in CAS; `store` checks the CID
+    rc::store(&rc, cas)?;
 
     // In a real implementation, this would:
     // 1. Append to a Merkle tree or blockchain
     // 2. Update the ledger head pointer
     // 3. Emit a ledger event
     //
-    // For now, we just verify storage succeeded
-    Ok(stored_cid == normalized.cid)
+    // For now, storing is the append
+    Ok(true)
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
-    use crate::rc;
     use serde_json::json;
 
     #[test]
@@ -1,21 +1,53 @@
 use crate::cas::Cas;
-use crate::chips::{normalize, validate};
-use crate::rc;
+use crate::rc::{self, Kind};
 use crate::types::ReciboCard;
 use crate::{Result, RhoError};
-use base64::Engine;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+/// Body of a `rc.log.v1` receipt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub level: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fields: Option<Value>,
+}
+
+impl Kind for LogEntry {
+    const KIND: &'static str = "rc.log.v1";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "level": {
+                    "type": "string",
+                    "enum": ["info", "warn", "error"]
+                },
+                "message": {
+                    "type": "string"
+                },
+                "fields": {
+                    "type": "object"
+                }
+            },
+            "required": ["level", "message"],
+            "additionalProperties": false
+        })
+    }
+}
+
 /// mod.log: Structured logging with validation
 ///
-/// Pipeline: normalize → validate(schema) → rc.emit
+/// Pipeline: normalize → validate(schema:rc.log.v1) → rc.emit
 ///
 /// Inputs:
 /// - level: "info" | "warn" | "error"
 /// - message: string
 /// - fields: object (optional)
 ///
-/// Output: RC (Recibo Card)
+/// Output: RC (Recibo Card) of kind `rc.log.v1`
 pub fn log(level: String, message: String, fields: Option<Value>, cas: &Cas) -> Result<ReciboCard> {
     // Validate level
     if !["info", "warn", "error"].contains(&level.as_str()) {
@@ -26,54 +58,15 @@ pub fn log(level: String, message: String, fields: Option<Value>, cas: &Cas) ->
     }
 
     // Build log entry
-    let mut log_entry = json!({
-        "level": level,
-        "message": message,
-    });
-
-    if let Some(f) = fields {
-        log_entry["fields"] = f;
-    }
-
-    // Normalize the log entry
-    let _normalized = normalize(log_entry.clone())?;
-
-    // Define schema for log entry validation
-    let schema = json!({
-        "type": "object",
-        "properties": {
-            "level": {
-                "type": "string",
-                "enum": ["info", "warn", "error"]
-            },
-            "message": {
-                "type": "string"
-            },
-            "fields": {
-                "type": "object"
-            }
-        },
-        "required": ["level", "message"],
-        "additionalProperties": false
-    });
-
-    // Store schema in CAS
-    let schema_normalized = normalize(schema)?;
-    let schema_bytes =
-        base64::engine::general_purpose::STANDARD.decode(&schema_normalized.bytes)?;
-    let schema_cid = cas.put(schema_bytes)?;
-
-    // Validate the log entry
-    let validation = validate(log_entry.clone(), schema_cid, cas)?;
-    if !validation.valid {
-        return Err(RhoError::ValidationFailed(format!(
-            "Log entry validation failed: {:?}",
-            validation.errors
-        )));
-    }
+    let log_entry = LogEntry {
+        level,
+        message,
+        fields,
+    };
 
-    // Emit as RC
-    rc::emit(log_entry)
+    // Make sure schema:rc.log.v1 is in CAS, then validate and emit against it
+    rc::kind::ensure_registered::<LogEntry>(cas)?;
+    rc::emit_kind(LogEntry::KIND, serde_json::to_value(&log_entry)?, cas)
 }
 
 #[cfg(test)]
@@ -105,6 +98,17 @@ mod tests {
         assert_eq!(rc.body["fields"], fields);
     }
 
+    #[test]
+    fn test_log_is_typed() {
+        let cas = Cas::new();
+        let card = log("error".to_string(), "Disk full".to_string(), None, &cas).unwrap();
+        assert_eq!(card.recibo.kind.as_deref(), Some("rc.log.v1"));
+        assert!(rc::kind::kind_schema(LogEntry::KIND, &cas).is_some());
+
+        let entry: LogEntry = rc::decode(&card).unwrap();
+        assert_eq!(entry.message, "Disk full");
+    }
+
     #[test]
     fn test_log_invalid_level() {
         let cas = Cas::new();
@@ -2,9 +2,11 @@
 //
 // Modules compose base transistors via wiring specifications
 
+pub mod capability;
 pub mod chip_build;
 pub mod chip_eval;
 pub mod chip_publish;
+mod glob;
 pub mod judge;
 pub mod ledger;
 pub mod log;
@@ -17,3 +19,22 @@ pub use judge::judge;
 pub use ledger::append;
 pub use log::log;
 pub use permit::permit;
+
+use crate::cas::Cas;
+use crate::rc;
+use crate::Result;
+
+/// Register every schema modules validate against; call once at startup
+pub fn register_schemas(cas: &Cas) -> Result<()> {
+    register_kinds(cas)?;
+    chip_publish::register_chip_spec_schema(cas)?;
+    Ok(())
+}
+
+/// Register the schemas of the receipt kinds emitted by modules
+pub fn register_kinds(cas: &Cas) -> Result<()> {
+    rc::kind::register::<log::LogEntry>(cas)?;
+    rc::kind::register::<chip_build::BuildReceipt>(cas)?;
+    rc::kind::register::<permit::DecisionReceipt>(cas)?;
+    Ok(())
+}
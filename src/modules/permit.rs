@@ -1,123 +1,920 @@
 use crate::cas::Cas;
-use crate::chips::policy_eval;
-use crate::types::{Cid, Proof};
-use crate::Result;
+use crate::chips::policy::verified_key_ids;
+use crate::chips::{normalize, policy_eval_with, policy_trace, store_trace};
+use crate::clock::{Clock, Timestamp};
+use crate::keystore::KeyPair;
+use crate::modules::capability::{verify_chain, ChainCheck};
+use crate::modules::glob::glob_match;
+use crate::modules::ledger;
+use crate::rc::{self, Kind, StatusList};
+use crate::types::{Cid, PolicyTrace, Proof, ReciboCard};
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Document type of a permit policy stored in CAS
+pub const PERMIT_POLICY_TYPE: &str = "rho.permit.policy";
+
+/// CAS ref to the card CID of the latest decision receipt
+pub const DECISION_HEAD_REF: &str = "mod.permit.head";
+
+/// Access-control policy document
+///
+/// Principals are key IDs. Rules are matched in order; any matching `deny`
+/// rule overrides every `allow` rule, and a request no rule allows is denied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermitPolicy {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Role name → member key IDs
+    #[serde(default)]
+    pub roles: BTreeMap<String, Vec<Cid>>,
+    /// Key ID → attributes, for `when` conditions
+    #[serde(default)]
+    pub attributes: BTreeMap<Cid, BTreeMap<String, String>>,
+    pub rules: Vec<Rule>,
+    /// Key IDs that may issue root capabilities
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorities: Vec<Cid>,
+}
+
+/// One RBAC/ABAC rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    pub effect: Effect,
+    /// Key IDs the rule applies to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub principals: Vec<Cid>,
+    /// Roles the rule applies to; `*` is any authenticated principal
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Action globs
+    pub actions: Vec<String>,
+    /// Resource globs: `*` within a path segment, `**` across segments
+    pub resources: Vec<String>,
+    /// Principal attributes that must all be equal
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub when: BTreeMap<String, String>,
+    /// Signature policy the request's proofs must also satisfy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Effect {
+    Allow,
+    Deny,
+}
+
+/// Result of `permit`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decision {
+    pub allowed: bool,
+    /// ID of the rule that decided, if any rule matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// Card CID of the capability that allowed the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capability: Option<Cid>,
+    pub reason: String,
+}
+
+impl Decision {
+    fn deny(reason: impl Into<String>) -> Self {
+        Self {
+            allowed: false,
+            rule: None,
+            capability: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A capability presented with a request, for `permit_delegated`
+#[derive(Clone)]
+pub struct Delegation<'a> {
+    /// Card CID of the principal's capability token
+    pub capability: Cid,
+    /// Revocations of tokens in the chain
+    pub status_list: Option<&'a StatusList>,
+    /// Expiry is checked against this clock's current time
+    pub clock: &'a dyn Clock,
+}
+
+/// A request to decide and record with `permit_receipt`
+#[derive(Debug, Clone)]
+pub struct PermitRequest {
+    pub principal: String,
+    pub action: String,
+    pub resource: String,
+    pub policy_cid: Cid,
+    pub proofs: Vec<Proof>,
+}
+
+/// Body of a `mod.permit.decision.v1` receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecisionReceipt {
+    pub principal: String,
+    pub action: String,
+    pub resource: String,
+    pub policy_cid: Cid,
+    /// CIDs of the presented proofs, each stored in CAS
+    pub proof_cids: Vec<Cid>,
+    pub allowed: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub capability: Option<Cid>,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trace_cid: Option<Cid>,
+    pub decided_at: Timestamp,
+}
+
+impl Kind for DecisionReceipt {
+    const KIND: &'static str = "mod.permit.decision.v1";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "principal": {"type": "string"},
+                "action": {"type": "string"},
+                "resource": {"type": "string"},
+                "policy_cid": {"type": "string"},
+                "proof_cids": {"type": "array", "items": {"type": "string"}},
+                "allowed": {"type": "boolean"},
+                "rule": {"type": "string"},
+                "capability": {"type": "string"},
+                "reason": {"type": "string"},
+                "trace_cid": {"type": "string"},
+                "decided_at": {"type": "string"}
+            },
+            "required": [
+                "principal", "action", "resource", "policy_cid", "proof_cids",
+                "allowed", "reason", "decided_at"
+            ],
+            "additionalProperties": false
+        })
+    }
+}
+
+/// The request being decided
+struct Request<'a> {
+    principal: &'a str,
+    action: &'a str,
+    resource: &'a str,
+    proofs: &'a [Proof],
+}
+
+/// Result of `permit_traced`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PermitOutcome {
+    pub decision: Decision,
+    /// Trace of the deciding rule's `require` policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<PolicyTrace>,
+    /// CID of `trace` in CAS, for attaching to permit and denial receipts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_cid: Option<Cid>,
+}
+
+/// CID of a permit request, the message that proofs must sign
+///
+/// It carries no nonce or expiry, so a proof authorizes the same request
+/// every time it is presented: replay is accepted. Decisions are pure
+/// checks of who may do what; callers that need one-time use must bind
+/// freshness into the resource or track consumed proofs themselves.
+pub fn request_cid(principal: &str, action: &str, resource: &str) -> Result<Cid> {
+    Ok(normalize(json!({
+        "principal": principal,
+        "action": action,
+        "resource": resource,
+    }))?
+    .cid)
+}
+
+/// Store a permit policy document in CAS
+pub fn store_permit_policy(policy: &PermitPolicy, cas: &Cas) -> Result<Cid> {
+    let normalized = normalize(serde_json::to_value(policy)?)?;
+    cas.put(BASE64.decode(&normalized.bytes)?)
+}
+
+/// Load a permit policy document from CAS
+pub fn load_permit_policy(policy_cid: &Cid, cas: &Cas) -> Result<PermitPolicy> {
+    let policy: PermitPolicy = serde_json::from_slice(&cas.get(policy_cid)?)
+        .map_err(|e| RhoError::Policy(format!("Invalid permit policy {}: {}", policy_cid, e)))?;
+    if policy.kind != PERMIT_POLICY_TYPE {
+        return Err(RhoError::Policy(format!(
+            "{} is not a permit policy",
+            policy_cid
+        )));
+    }
+    Ok(policy)
+}
 
 /// mod.permit: Access control policy evaluation
 ///
 /// Evaluates whether a principal is allowed to perform an action on a resource
 ///
 /// Inputs:
-/// - principal: Principal identifier (e.g., user ID, public key)
+/// - principal: key ID of the requesting key
 /// - action: Action to perform (e.g., "read", "write", "delete")
-/// - resource: Resource identifier
-/// - policy_cid: CID of the policy in CAS
+/// - resource: Resource identifier (e.g., "reports/2024/q1")
+/// - policy_cid: CID of a `PermitPolicy` in CAS
+/// - proofs: signatures over `request_cid(principal, action, resource)`
 ///
-/// Output: Boolean indicating if access is allowed
+/// Output: the decision and the rule that made it. The principal must have
+/// signed the request; proofs by other keys only count towards `require`.
 pub fn permit(
-    _principal: String,
-    _action: String,
-    _resource: String,
-    _policy_cid: Cid,
+    principal: String,
+    action: String,
+    resource: String,
+    policy_cid: Cid,
     proofs: Vec<Proof>,
-    _cas: &Cas,
-) -> Result<bool> {
-    // Fetch policy from CAS
-    // In a real implementation, this would:
-    // 1. Fetch the policy document from CAS
-    // 2. Parse it into a policy expression
-    // 3. Substitute principal/action/resource into the policy
-    // 4. Evaluate the resulting policy expression
-    //
-    // For now, we use a simple policy expression based on the proofs
-
-    // Build a simple policy: require at least one valid signature
-    let policy_expr = if proofs.is_empty() {
-        "false".to_string()
-    } else {
-        // For demonstration, we accept if any ed25519 or mldsa3 proof is present
-        "hybrid-or(ed25519,mldsa3)".to_string()
+    cas: &Cas,
+) -> Result<Decision> {
+    let request = Request {
+        principal: &principal,
+        action: &action,
+        resource: &resource,
+        proofs: &proofs,
     };
+    let (decision, _) = decide(&request, &policy_cid, None, cas, false)?;
+    Ok(decision)
+}
 
-    // Evaluate the policy
-    let result = policy_eval(policy_expr, proofs)?;
+/// Like `permit`, but a request no rule allows may be allowed by a capability
+///
+/// The capability chain must lead back to one of the policy's `authorities`,
+/// every token in it must be unrevoked and unexpired at `delegation.clock`,
+/// and the leaf must be granted to the principal and cover the request.
+/// Deny rules still override capabilities.
+pub fn permit_delegated(
+    principal: String,
+    action: String,
+    resource: String,
+    policy_cid: Cid,
+    proofs: Vec<Proof>,
+    delegation: &Delegation,
+    cas: &Cas,
+) -> Result<Decision> {
+    let request = Request {
+        principal: &principal,
+        action: &action,
+        resource: &resource,
+        proofs: &proofs,
+    };
+    let (decision, _) = decide(&request, &policy_cid, Some(delegation), cas, false)?;
+    Ok(decision)
+}
+
+/// Like `permit`, but also explains the decision
+///
+/// When the deciding rule has a `require` policy, its trace is stored in
+/// `cas` so that receipts can reference it.
+pub fn permit_traced(
+    principal: String,
+    action: String,
+    resource: String,
+    policy_cid: Cid,
+    proofs: Vec<Proof>,
+    cas: &Cas,
+) -> Result<PermitOutcome> {
+    let request = Request {
+        principal: &principal,
+        action: &action,
+        resource: &resource,
+        proofs: &proofs,
+    };
+    let (decision, trace) = decide(&request, &policy_cid, None, cas, true)?;
+    let trace_cid = trace.as_ref().map(|t| store_trace(t, cas)).transpose()?;
+    Ok(PermitOutcome {
+        decision,
+        trace,
+        trace_cid,
+    })
+}
+
+/// Decide a request and append a receipt of the decision to the ledger
+///
+/// Allowed and denied requests both produce a `mod.permit.decision.v1` RC,
+/// signed by `service`. Each receipt's parent is the previous decision
+/// receipt (see `DECISION_HEAD_REF`), so editing an entry breaks the links
+/// after it. Whoever holds `service` and the CAS refs can still rewrite the
+/// log. The proofs and the policy trace are stored in CAS alongside it.
+pub fn permit_receipt(
+    request: PermitRequest,
+    delegation: Option<&Delegation>,
+    service: &KeyPair,
+    cas: &Cas,
+    clock: &dyn Clock,
+) -> Result<(Decision, ReciboCard)> {
+    let req = Request {
+        principal: &request.principal,
+        action: &request.action,
+        resource: &request.resource,
+        proofs: &request.proofs,
+    };
+    let (decision, trace) = decide(&req, &request.policy_cid, delegation, cas, true)?;
+    let trace_cid = trace.as_ref().map(|t| store_trace(t, cas)).transpose()?;
+
+    let mut proof_cids = Vec::with_capacity(request.proofs.len());
+    for proof in &request.proofs {
+        let normalized = normalize(serde_json::to_value(proof)?)?;
+        proof_cids.push(cas.put(BASE64.decode(&normalized.bytes)?)?);
+    }
+
+    let receipt = DecisionReceipt {
+        principal: request.principal,
+        action: request.action,
+        resource: request.resource,
+        policy_cid: request.policy_cid,
+        proof_cids,
+        allowed: decision.allowed,
+        rule: decision.rule.clone(),
+        capability: decision.capability.clone(),
+        reason: decision.reason.clone(),
+        trace_cid,
+        decided_at: clock.now(),
+    };
 
-    // In a real system, we would also check:
-    // - Principal matches the proof's public key
-    // - Action is in the allowed set for this principal
-    // - Resource is accessible by this principal
-    //
-    // For now, we just return the policy evaluation result
-    Ok(result.result)
+    // Retry against the new head if another decision was chained meanwhile
+    loop {
+        let head = cas.get_ref(DECISION_HEAD_REF);
+        let mut card = rc::emit_typed(&receipt, vec![])?;
+        card.recibo.parents = head.iter().cloned().collect();
+        rc::sign(&mut card, service)?;
+
+        let card_cid = rc::card_cid(&card)?;
+        if !cas.compare_and_set_ref(DECISION_HEAD_REF, head.as_ref(), Some(card_cid.clone())) {
+            continue;
+        }
+        // Only the winner of the head reaches the ledger; on failure the
+        // head is handed back so it never names a missing receipt
+        let appended = ledger::append(card.clone(), cas);
+        if !matches!(appended, Ok(true)) {
+            cas.compare_and_set_ref(DECISION_HEAD_REF, Some(&card_cid), head);
+            appended?;
+            return Err(RhoError::Cas(
+                "Ledger did not accept the decision receipt".to_string(),
+            ));
+        }
+        return Ok((decision, card));
+    }
+}
+
+fn decide(
+    request: &Request,
+    policy_cid: &Cid,
+    delegation: Option<&Delegation>,
+    cas: &Cas,
+    traced: bool,
+) -> Result<(Decision, Option<PolicyTrace>)> {
+    let policy = load_permit_policy(policy_cid, cas)?;
+    let principal = request.principal;
+    let message_cid = request_cid(principal, request.action, request.resource)?;
+
+    if !verified_key_ids(request.proofs, &message_cid)
+        .iter()
+        .any(|id| id == principal)
+    {
+        return Ok((
+            Decision::deny("Principal has no valid proof over the request"),
+            None,
+        ));
+    }
+
+    let roles: Vec<&str> = policy
+        .roles
+        .iter()
+        .filter(|(_, members)| members.iter().any(|m| m == principal))
+        .map(|(role, _)| role.as_str())
+        .collect();
+    let attributes = policy.attributes.get(principal);
+
+    // Deny overrides: look at every deny rule before any allow rule
+    for effect in [Effect::Deny, Effect::Allow] {
+        for rule in policy.rules.iter().filter(|r| r.effect == effect) {
+            if !applies(rule, request, &roles, attributes) {
+                continue;
+            }
+            let trace = match &rule.require {
+                Some(expr) => {
+                    let proofs = request.proofs.to_vec();
+                    let output = if traced {
+                        policy_trace(expr.clone(), proofs, &message_cid, Some(cas))?
+                    } else {
+                        policy_eval_with(expr.clone(), proofs, &message_cid, cas)?
+                    };
+                    if !output.result {
+                        continue;
+                    }
+                    output.trace
+                }
+                None => None,
+            };
+            let allowed = effect == Effect::Allow;
+            let reason = format!(
+                "{} by rule {}",
+                if allowed { "Allowed" } else { "Denied" },
+                rule.id
+            );
+            return Ok((
+                Decision {
+                    allowed,
+                    rule: Some(rule.id.clone()),
+                    capability: None,
+                    reason,
+                },
+                trace,
+            ));
+        }
+    }
+
+    let Some(delegation) = delegation else {
+        return Ok((Decision::deny("No rule allows the request"), None));
+    };
+    let check = ChainCheck {
+        authorities: &policy.authorities,
+        status_list: delegation.status_list,
+        clock: delegation.clock,
+    };
+    let decision = match verify_chain(&delegation.capability, &check, cas) {
+        Ok(leaf) if leaf.audience != principal => {
+            Decision::deny("Capability is granted to another key")
+        }
+        Ok(leaf) if !leaf.allows(request.action, request.resource) => {
+            Decision::deny("Capability does not cover the request")
+        }
+        Ok(_) => Decision {
+            allowed: true,
+            rule: None,
+            capability: Some(delegation.capability.clone()),
+            reason: "Allowed by capability".to_string(),
+        },
+        Err(e) => Decision::deny(e.to_string()),
+    };
+    Ok((decision, None))
+}
+
+/// Subject, action, resource and attribute match, before `require`
+fn applies(
+    rule: &Rule,
+    request: &Request,
+    roles: &[&str],
+    attributes: Option<&BTreeMap<String, String>>,
+) -> bool {
+    let subject = rule.principals.iter().any(|p| p == request.principal)
+        || rule
+            .roles
+            .iter()
+            .any(|r| r == "*" || roles.contains(&r.as_str()));
+    let conditions = rule
+        .when
+        .iter()
+        .all(|(key, value)| attributes.and_then(|a| a.get(key)) == Some(value));
+
+    subject
+        && conditions
+        && rule.actions.iter().any(|a| glob_match(a, request.action))
+        && rule
+            .resources
+            .iter()
+            .any(|r| glob_match(r, request.resource))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keystore::{KeyAlgorithm, KeyPair};
 
-    #[test]
-    fn test_permit_with_proof() {
+    struct Fixture {
+        cas: Cas,
+        policy_cid: Cid,
+        admin: KeyPair,
+        analyst: KeyPair,
+        intern: KeyPair,
+        auditor: KeyPair,
+    }
+
+    fn fixture() -> Fixture {
         let cas = Cas::new();
-        let proof = Proof {
-            algorithm: "ed25519".to_string(),
-            public_key: "test_key".to_string(),
-            signature: "test_sig".to_string(),
-            message_cid: "test_msg".to_string(),
-        };
-        let result = permit(
-            "user123".to_string(),
-            "read".to_string(),
-            "resource456".to_string(),
-            "policy_cid".to_string(),
-            vec![proof],
-            &cas,
-        );
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), true);
+        let admin = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let analyst = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let intern = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let auditor = KeyPair::generate(KeyAlgorithm::MlDsa3);
+
+        let policy: PermitPolicy = serde_json::from_value(json!({
+            "type": PERMIT_POLICY_TYPE,
+            "roles": {
+                "admin": [admin.key_id()],
+                "analyst": [analyst.key_id(), intern.key_id()]
+            },
+            "attributes": {
+                analyst.key_id(): {"department": "finance"},
+                intern.key_id(): {"department": "marketing"}
+            },
+            "rules": [
+                {
+                    "id": "admins-everything",
+                    "effect": "allow",
+                    "roles": ["admin"],
+                    "actions": ["*"],
+                    "resources": ["**"]
+                },
+                {
+                    "id": "finance-reads-reports",
+                    "effect": "allow",
+                    "roles": ["analyst"],
+                    "actions": ["read"],
+                    "resources": ["reports/*"],
+                    "when": {"department": "finance"}
+                },
+                {
+                    "id": "no-deleting-audit-logs",
+                    "effect": "deny",
+                    "roles": ["*"],
+                    "actions": ["delete"],
+                    "resources": ["audit/**"]
+                },
+                {
+                    "id": "analyst-export-with-auditor",
+                    "effect": "allow",
+                    "principals": [analyst.key_id()],
+                    "actions": ["export"],
+                    "resources": ["reports/**"],
+                    "require": format!("mldsa3:{}", auditor.key_id())
+                }
+            ],
+            "authorities": [admin.key_id()]
+        }))
+        .unwrap();
+        let policy_cid = store_permit_policy(&policy, &cas).unwrap();
+
+        Fixture {
+            cas,
+            policy_cid,
+            admin,
+            analyst,
+            intern,
+            auditor,
+        }
+    }
+
+    fn request(f: &Fixture, signers: &[&KeyPair], action: &str, resource: &str) -> Decision {
+        let principal = signers[0].key_id();
+        let message = request_cid(&principal, action, resource).unwrap();
+        let proofs = signers.iter().map(|k| k.prove(&message).unwrap()).collect();
+        permit(
+            principal,
+            action.to_string(),
+            resource.to_string(),
+            f.policy_cid.clone(),
+            proofs,
+            &f.cas,
+        )
+        .unwrap()
     }
 
     #[test]
-    fn test_permit_without_proof() {
-        let cas = Cas::new();
-        let result = permit(
-            "user123".to_string(),
-            "read".to_string(),
-            "resource456".to_string(),
-            "policy_cid".to_string(),
-            vec![],
-            &cas,
+    fn test_rbac_and_attributes() {
+        let f = fixture();
+
+        let decision = request(&f, &[&f.analyst], "read", "reports/q1");
+        assert!(decision.allowed);
+        assert_eq!(decision.rule.as_deref(), Some("finance-reads-reports"));
+
+        // Same role, wrong department
+        let decision = request(&f, &[&f.intern], "read", "reports/q1");
+        assert!(!decision.allowed);
+        assert_eq!(decision.rule, None);
+
+        // `*` does not cross path segments
+        assert!(!request(&f, &[&f.analyst], "read", "reports/2024/q1").allowed);
+        assert!(!request(&f, &[&f.analyst], "write", "reports/q1").allowed);
+        assert!(request(&f, &[&f.admin], "write", "reports/2024/q1").allowed);
+    }
+
+    #[test]
+    fn test_deny_overrides_allow() {
+        let f = fixture();
+        let decision = request(&f, &[&f.admin], "delete", "audit/2024/log");
+        assert!(!decision.allowed);
+        assert_eq!(decision.rule.as_deref(), Some("no-deleting-audit-logs"));
+    }
+
+    #[test]
+    fn test_require_policy() {
+        let f = fixture();
+        assert!(!request(&f, &[&f.analyst], "export", "reports/q1").allowed);
+
+        let message = request_cid(&f.analyst.key_id(), "export", "reports/q1").unwrap();
+        let outcome = permit_traced(
+            f.analyst.key_id(),
+            "export".to_string(),
+            "reports/q1".to_string(),
+            f.policy_cid.clone(),
+            vec![
+                f.analyst.prove(&message).unwrap(),
+                f.auditor.prove(&message).unwrap(),
+            ],
+            &f.cas,
+        )
+        .unwrap();
+        assert!(outcome.decision.allowed);
+        assert_eq!(
+            outcome.decision.rule.as_deref(),
+            Some("analyst-export-with-auditor")
         );
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), false);
+
+        let trace = outcome.trace.unwrap();
+        assert_eq!(trace.satisfied_by, vec![f.auditor.key_id()]);
+        let stored: PolicyTrace =
+            serde_json::from_slice(&f.cas.get(&outcome.trace_cid.unwrap()).unwrap()).unwrap();
+        assert_eq!(stored, trace);
     }
 
     #[test]
-    fn test_permit_deterministic() {
-        let cas = Cas::new();
-        let proof = Proof {
-            algorithm: "ed25519".to_string(),
-            public_key: "test_key".to_string(),
-            signature: "test_sig".to_string(),
-            message_cid: "test_msg".to_string(),
-        };
-        let result1 = permit(
-            "user123".to_string(),
+    fn test_principal_must_sign_request() {
+        let f = fixture();
+
+        // No proofs at all
+        let decision = permit(
+            f.admin.key_id(),
+            "read".to_string(),
+            "reports/q1".to_string(),
+            f.policy_cid.clone(),
+            vec![],
+            &f.cas,
+        )
+        .unwrap();
+        assert!(!decision.allowed);
+
+        // Someone else's proof, claiming to be the admin
+        let message = request_cid(&f.admin.key_id(), "read", "reports/q1").unwrap();
+        let decision = permit(
+            f.admin.key_id(),
             "read".to_string(),
-            "resource456".to_string(),
-            "policy_cid".to_string(),
-            vec![proof.clone()],
-            &cas,
+            "reports/q1".to_string(),
+            f.policy_cid.clone(),
+            vec![f.intern.prove(&message).unwrap()],
+            &f.cas,
         )
         .unwrap();
-        let result2 = permit(
-            "user123".to_string(),
+        assert!(!decision.allowed);
+
+        // The admin's proof over a different request
+        let other = request_cid(&f.admin.key_id(), "read", "reports/q2").unwrap();
+        let decision = permit(
+            f.admin.key_id(),
             "read".to_string(),
-            "resource456".to_string(),
-            "policy_cid".to_string(),
-            vec![proof],
-            &cas,
+            "reports/q1".to_string(),
+            f.policy_cid.clone(),
+            vec![f.admin.prove(&other).unwrap()],
+            &f.cas,
+        )
+        .unwrap();
+        assert!(!decision.allowed);
+    }
+
+    #[test]
+    fn test_rejects_non_policy_document() {
+        let f = fixture();
+        let cid = f.cas.put(b"{\"rules\": []}".to_vec()).unwrap();
+        let message = request_cid(&f.admin.key_id(), "read", "x").unwrap();
+        let result = permit(
+            f.admin.key_id(),
+            "read".to_string(),
+            "x".to_string(),
+            cid,
+            vec![f.admin.prove(&message).unwrap()],
+            &f.cas,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_capability_delegation() {
+        use crate::clock::FixedClock;
+        use crate::modules::capability::{delegate, Grant};
+
+        let f = fixture();
+        let grant = |actions: &[&str], resources: &[&str]| Grant {
+            audience: f.intern.key_id(),
+            actions: actions.iter().map(|s| s.to_string()).collect(),
+            resources: resources.iter().map(|s| s.to_string()).collect(),
+            expires_at: Some(Timestamp::parse_rfc3339("2024-07-01T00:00:00Z").unwrap()),
+        };
+        let capability =
+            delegate(&f.admin, grant(&["judge"], &["models/x"]), None, &f.cas).unwrap();
+
+        let ask = |action: &str, resource: &str, now: &str, status_list: Option<&StatusList>| {
+            let message = request_cid(&f.intern.key_id(), action, resource).unwrap();
+            let clock = FixedClock::at(now).unwrap();
+            let delegation = Delegation {
+                capability: capability.clone(),
+                status_list,
+                clock: &clock,
+            };
+            permit_delegated(
+                f.intern.key_id(),
+                action.to_string(),
+                resource.to_string(),
+                f.policy_cid.clone(),
+                vec![f.intern.prove(&message).unwrap()],
+                &delegation,
+                &f.cas,
+            )
+            .unwrap()
+        };
+
+        let decision = ask("judge", "models/x", "2024-06-01T00:00:00Z", None);
+        assert!(decision.allowed);
+        assert_eq!(decision.capability, Some(capability.clone()));
+        assert!(!ask("judge", "models/y", "2024-06-01T00:00:00Z", None).allowed);
+        assert!(!ask("judge", "models/x", "2024-07-01T00:00:00Z", None).allowed);
+
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let card = crate::rc::load(&capability, &f.cas).unwrap();
+        let mut list = StatusList::new(vec![f.admin.key_id()]);
+        list.add(&crate::rc::revoke(&card.recibo.content_cid, "left", &f.admin, &clock).unwrap())
+            .unwrap();
+        let decision = ask("judge", "models/x", "2024-06-01T00:00:00Z", Some(&list));
+        assert!(!decision.allowed);
+        assert!(decision.reason.contains("revoked"));
+
+        // Without the capability the intern cannot judge
+        let message = request_cid(&f.intern.key_id(), "judge", "models/x").unwrap();
+        let decision = permit(
+            f.intern.key_id(),
+            "judge".to_string(),
+            "models/x".to_string(),
+            f.policy_cid.clone(),
+            vec![f.intern.prove(&message).unwrap()],
+            &f.cas,
         )
         .unwrap();
-        assert_eq!(result1, result2);
+        assert!(!decision.allowed);
+    }
+
+    #[test]
+    fn test_capability_needs_policy_authority_and_yields_to_deny() {
+        use crate::clock::FixedClock;
+        use crate::modules::capability::{delegate, Grant};
+
+        let f = fixture();
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let grant = Grant {
+            audience: f.intern.key_id(),
+            actions: vec!["*".to_string()],
+            resources: vec!["**".to_string()],
+            expires_at: None,
+        };
+        let ask = |capability: Cid, action: &str, resource: &str| {
+            let message = request_cid(&f.intern.key_id(), action, resource).unwrap();
+            permit_delegated(
+                f.intern.key_id(),
+                action.to_string(),
+                resource.to_string(),
+                f.policy_cid.clone(),
+                vec![f.intern.prove(&message).unwrap()],
+                &Delegation {
+                    capability,
+                    status_list: None,
+                    clock: &clock,
+                },
+                &f.cas,
+            )
+            .unwrap()
+        };
+
+        // Issued by someone who is not an authority of the policy
+        let rogue = delegate(&f.analyst, grant.clone(), None, &f.cas).unwrap();
+        assert!(!ask(rogue, "judge", "models/x").allowed);
+
+        let root = delegate(&f.admin, grant, None, &f.cas).unwrap();
+        assert!(ask(root.clone(), "judge", "models/x").allowed);
+        let decision = ask(root, "delete", "audit/2024");
+        assert!(!decision.allowed);
+        assert_eq!(decision.rule.as_deref(), Some("no-deleting-audit-logs"));
+    }
+
+    #[test]
+    fn test_decision_receipts_form_a_chain() {
+        use crate::clock::FixedClock;
+
+        let f = fixture();
+        let service = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let make = |key: &KeyPair, action: &str, resource: &str| {
+            let message = request_cid(&key.key_id(), action, resource).unwrap();
+            PermitRequest {
+                principal: key.key_id(),
+                action: action.to_string(),
+                resource: resource.to_string(),
+                policy_cid: f.policy_cid.clone(),
+                proofs: vec![key.prove(&message).unwrap()],
+            }
+        };
+
+        let receipt = |request| permit_receipt(request, None, &service, &f.cas, &clock).unwrap();
+
+        let (allowed, first) = receipt(make(&f.analyst, "read", "reports/q1"));
+        assert!(allowed.allowed);
+        assert!(first.recibo.parents.is_empty());
+
+        let (denied, second) = receipt(make(&f.intern, "read", "reports/q1"));
+        assert!(!denied.allowed);
+        let signed_by_service = format!("key:{}", service.key_id());
+        assert!(rc::verify_card(&second, &signed_by_service).unwrap().valid);
+
+        // Both receipts are in the ledger, chained newest to oldest
+        let first_cid = rc::card_cid(&first).unwrap();
+        let second_cid = rc::card_cid(&second).unwrap();
+        assert_eq!(second.recibo.parents, vec![first_cid.clone()]);
+        assert_eq!(f.cas.get_ref(DECISION_HEAD_REF), Some(second_cid.clone()));
+        assert_eq!(rc::load(&first_cid, &f.cas).unwrap().body, first.body);
+
+        let body: DecisionReceipt = rc::decode(&second).unwrap();
+        assert_eq!(body.principal, f.intern.key_id());
+        assert_eq!(body.reason, "No rule allows the request");
+        assert_eq!(body.decided_at.to_rfc3339(), "2024-06-01T00:00:00Z");
+        let proof: Proof =
+            serde_json::from_slice(&f.cas.get(&body.proof_cids[0]).unwrap()).unwrap();
+        assert_eq!(proof.public_key, f.intern.public_key_b64());
+
+        let first_body: DecisionReceipt = rc::decode(&first).unwrap();
+        assert_eq!(first_body.rule.as_deref(), Some("finance-reads-reports"));
+    }
+
+    #[test]
+    fn test_concurrent_decisions_do_not_fork_the_chain() {
+        let f = fixture();
+        let service = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let clock = crate::clock::test_clock();
+        let message = request_cid(&f.analyst.key_id(), "read", "reports/q1").unwrap();
+        let request = PermitRequest {
+            principal: f.analyst.key_id(),
+            action: "read".to_string(),
+            resource: "reports/q1".to_string(),
+            policy_cid: f.policy_cid.clone(),
+            proofs: vec![f.analyst.prove(&message).unwrap()],
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..3 {
+                        permit_receipt(request.clone(), None, &service, &f.cas, &clock).unwrap();
+                    }
+                });
+            }
+        });
+
+        let mut length = 0;
+        let mut head = f.cas.get_ref(DECISION_HEAD_REF);
+        while let Some(cid) = head {
+            length += 1;
+            head = rc::load(&cid, &f.cas)
+                .unwrap()
+                .recibo
+                .parents
+                .first()
+                .cloned();
+        }
+        assert_eq!(length, 12);
+    }
+
+    #[test]
+    fn test_decision_receipt_references_trace() {
+        use crate::clock::FixedClock;
+
+        let f = fixture();
+        let clock = FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let message = request_cid(&f.analyst.key_id(), "export", "reports/q1").unwrap();
+        let request = PermitRequest {
+            principal: f.analyst.key_id(),
+            action: "export".to_string(),
+            resource: "reports/q1".to_string(),
+            policy_cid: f.policy_cid.clone(),
+            proofs: vec![
+                f.analyst.prove(&message).unwrap(),
+                f.auditor.prove(&message).unwrap(),
+            ],
+        };
+        let service = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let (decision, card) = permit_receipt(request, None, &service, &f.cas, &clock).unwrap();
+        assert!(decision.allowed);
+
+        let body: DecisionReceipt = rc::decode(&card).unwrap();
+        assert_eq!(body.proof_cids.len(), 2);
+        let trace: PolicyTrace =
+            serde_json::from_slice(&f.cas.get(&body.trace_cid.unwrap()).unwrap()).unwrap();
+        assert!(trace.result);
     }
 }
@@ -5,13 +5,13 @@
 ///
 /// Use case: AI model passport, governance, regulatory compliance
 use crate::cas::Cas;
-use crate::chips::normalize;
-use crate::rc;
+use crate::clock::{self, Timestamp};
+use crate::rc::{self, Kind, VerifyOptions};
 use crate::types::{ReciboCard, Signature};
-use crate::Result;
+use crate::{Result, RhoError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 /// AI Model information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +55,72 @@ pub struct AiPassport {
     pub registration_timestamp: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub additional_metadata: Option<Value>,
+    /// RFC 3339, canonical UTC; checked by `verify_passport_with`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expires_at: Option<String>,
+}
+
+impl Kind for AiPassport {
+    const KIND: &'static str = "product.ai-passport.v1";
+
+    fn schema() -> Value {
+        // Bias metrics are scaled by 10000
+        let metric = json!({"type": "integer", "minimum": 0, "maximum": 10000});
+        json!({
+            "type": "object",
+            "properties": {
+                "model_info": {
+                    "type": "object",
+                    "properties": {
+                        "version": {"type": "string", "format": "semver"}
+                    },
+                    "required": [
+                        "model_name",
+                        "version",
+                        "architecture",
+                        "parameters",
+                        "training_data_description"
+                    ]
+                },
+                "model_weights_cid": {"type": "string", "format": "cid"},
+                "compliance": {
+                    "type": "object",
+                    "properties": {
+                        "certification_date": {"type": "string", "format": "rfc3339"},
+                        "document_cid": {"type": "string", "format": "cid"}
+                    },
+                    "required": [
+                        "framework",
+                        "risk_level",
+                        "certification_date",
+                        "auditor",
+                        "document_cid"
+                    ]
+                },
+                "bias_metrics": {
+                    "type": "object",
+                    "properties": {
+                        "demographic_parity": metric,
+                        "equal_opportunity": metric,
+                        "fairness_score": metric,
+                        "toxicity_score": metric
+                    },
+                    "required": ["demographic_parity", "equal_opportunity", "fairness_score"]
+                },
+                "registration_timestamp": {"type": "string", "format": "rfc3339"},
+                "additional_metadata": {},
+                "expires_at": {"type": "string", "format": "rfc3339"}
+            },
+            "required": [
+                "model_info",
+                "model_weights_cid",
+                "compliance",
+                "bias_metrics",
+                "registration_timestamp"
+            ],
+            "additionalProperties": false
+        })
+    }
 }
 
 /// Passport registration result
@@ -68,6 +134,7 @@ pub struct PassportReceipt {
 ///
 /// Creates an immutable record proving the model's compliance status,
 /// bias metrics, and training data characteristics.
+/// The timestamp must be RFC 3339 and is stored in canonical UTC form.
 #[allow(clippy::too_many_arguments)]
 pub fn register_model(
     model_info: ModelInfo,
@@ -82,6 +149,8 @@ pub fn register_model(
     signatures: Vec<Signature>,
     cas: &Cas,
 ) -> Result<PassportReceipt> {
+    let registration_timestamp = clock::canonicalize(&registration_timestamp)?;
+
     // Hash and store model weights
     let weights_hash = blake3::hash(&model_weights);
     let model_weights_cid = BASE64.encode(weights_hash.as_bytes());
@@ -109,11 +178,11 @@ pub fn register_model(
         bias_metrics,
         registration_timestamp,
         additional_metadata,
+        expires_at: None,
     };
 
-    // Emit receipt card (normalization happens inside emit_with_signatures)
-    let passport_value = serde_json::to_value(&passport)?;
-    let receipt_card = rc::emit_with_signatures(passport_value, signatures)?;
+    // Emit a typed receipt card (normalization happens inside emit)
+    let receipt_card = rc::emit_typed(&passport, signatures)?;
 
     Ok(PassportReceipt {
         passport,
@@ -133,6 +202,61 @@ pub fn register_with_hash(
     registration_timestamp: String,
     signatures: Vec<Signature>,
 ) -> Result<PassportReceipt> {
+    hash_only_passport(
+        model_info,
+        model_weights_cid,
+        compliance,
+        bias_metrics,
+        registration_timestamp,
+        None,
+        signatures,
+    )
+}
+
+/// Register a hash-only passport that expires at `expires_at`
+///
+/// Like `register_with_hash`; `expires_at` must be RFC 3339 and later than
+/// the registration time.
+pub fn register_with_expiry(
+    model_info: ModelInfo,
+    model_weights_cid: String,
+    compliance: ComplianceDoc,
+    bias_metrics: BiasMetrics,
+    registration_timestamp: String,
+    expires_at: String,
+    signatures: Vec<Signature>,
+) -> Result<PassportReceipt> {
+    let expires = Timestamp::parse_rfc3339(&expires_at)?;
+    if expires <= Timestamp::parse_rfc3339(&registration_timestamp)? {
+        return Err(RhoError::InvalidInput(
+            "Passport must expire after its registration".to_string(),
+        ));
+    }
+
+    hash_only_passport(
+        model_info,
+        model_weights_cid,
+        compliance,
+        bias_metrics,
+        registration_timestamp,
+        Some(expires),
+        signatures,
+    )
+}
+
+/// Build and emit a hash-only passport
+fn hash_only_passport(
+    model_info: ModelInfo,
+    model_weights_cid: String,
+    mut compliance: ComplianceDoc,
+    bias_metrics: BiasMetrics,
+    registration_timestamp: String,
+    expires_at: Option<Timestamp>,
+    signatures: Vec<Signature>,
+) -> Result<PassportReceipt> {
+    let registration_timestamp = clock::canonicalize(&registration_timestamp)?;
+    compliance.certification_date = clock::canonicalize(&compliance.certification_date)?;
+
     let passport = AiPassport {
         model_info,
         model_weights_cid,
@@ -140,11 +264,11 @@ pub fn register_with_hash(
         bias_metrics,
         registration_timestamp,
         additional_metadata: None,
+        expires_at: expires_at.map(|t| t.to_rfc3339()),
     };
 
-    // Emit receipt card (normalization happens inside emit_with_signatures)
-    let passport_value = serde_json::to_value(&passport)?;
-    let receipt_card = rc::emit_with_signatures(passport_value, signatures)?;
+    // Emit a typed receipt card (normalization happens inside emit)
+    let receipt_card = rc::emit_typed(&passport, signatures)?;
 
     Ok(PassportReceipt {
         passport,
@@ -154,15 +278,25 @@ pub fn register_with_hash(
 
 /// Verify a passport's integrity
 ///
-/// Checks if the receipt's CID matches the passport data.
-/// In production, would also verify cryptographic signatures and audit trail.
+/// Checks the receipt against the passport data and verifies every
+/// signature via `rc::verify_typed`.
 pub fn verify_passport(receipt: &PassportReceipt) -> Result<bool> {
-    // Re-normalize the passport
-    let passport_value = serde_json::to_value(&receipt.passport)?;
-    let normalized = normalize(passport_value)?;
+    let card = ReciboCard {
+        body: serde_json::to_value(&receipt.passport)?,
+        recibo: receipt.receipt_card.recibo.clone(),
+    };
+    Ok(rc::verify_typed::<AiPassport>(&card, "true", &VerifyOptions::default())?.valid)
+}
 
-    // Check if CID matches
-    Ok(normalized.cid == receipt.receipt_card.recibo.content_cid)
+/// Verify a passport against a status list and the current time
+///
+/// Fails verification if the passport has been revoked or has expired.
+pub fn verify_passport_with(receipt: &PassportReceipt, options: &VerifyOptions) -> Result<bool> {
+    let card = ReciboCard {
+        body: serde_json::to_value(&receipt.passport)?,
+        recibo: receipt.receipt_card.recibo.clone(),
+    };
+    Ok(rc::verify_typed::<AiPassport>(&card, "true", options)?.valid)
 }
 
 /// Check if model passes compliance requirements
@@ -199,6 +333,8 @@ pub fn validate_compliance(passport: &AiPassport) -> Result<bool> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cas::test_support::test_cid;
+    use crate::keystore::{KeyAlgorithm, KeyPair};
     use serde_json::json;
 
     #[test]
@@ -265,7 +401,7 @@ mod tests {
             risk_level: "limited".to_string(),
             certification_date: "2024-01-01T12:00:00Z".to_string(),
             auditor: "AI Safety Lab".to_string(),
-            document_cid: "mock_pdf_cid".to_string(),
+            document_cid: test_cid("mock_pdf"),
         };
 
         let bias_metrics = BiasMetrics {
@@ -277,7 +413,7 @@ mod tests {
 
         let result = register_with_hash(
             model_info,
-            "mock_weights_cid".to_string(),
+            test_cid("mock_weights"),
             compliance,
             bias_metrics,
             "2024-01-01T12:00:00Z".to_string(),
@@ -286,7 +422,7 @@ mod tests {
 
         assert!(result.is_ok());
         let receipt = result.unwrap();
-        assert_eq!(receipt.passport.model_weights_cid, "mock_weights_cid");
+        assert_eq!(receipt.passport.model_weights_cid, test_cid("mock_weights"));
     }
 
     #[test]
@@ -304,7 +440,7 @@ mod tests {
             risk_level: "minimal".to_string(),
             certification_date: "2024-01-01T12:00:00Z".to_string(),
             auditor: "Test Auditor".to_string(),
-            document_cid: "test_cid".to_string(),
+            document_cid: test_cid("test"),
         };
 
         let bias_metrics = BiasMetrics {
@@ -316,7 +452,7 @@ mod tests {
 
         let receipt = register_with_hash(
             model_info,
-            "test_weights_cid".to_string(),
+            test_cid("test_weights"),
             compliance,
             bias_metrics,
             "2024-01-01T12:00:00Z".to_string(),
@@ -328,6 +464,186 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_register_rejects_malformed_fields() {
+        let register = |version: &str, weights_cid: String, fairness_score: i64| {
+            register_with_hash(
+                ModelInfo {
+                    model_name: "Model".to_string(),
+                    version: version.to_string(),
+                    architecture: "transformer".to_string(),
+                    parameters: 1,
+                    training_data_description: "Test data".to_string(),
+                },
+                weights_cid,
+                ComplianceDoc {
+                    framework: "EU AI Act".to_string(),
+                    risk_level: "minimal".to_string(),
+                    certification_date: "2024-01-01T12:00:00Z".to_string(),
+                    auditor: "Auditor".to_string(),
+                    document_cid: test_cid("doc"),
+                },
+                BiasMetrics {
+                    demographic_parity: 0,
+                    equal_opportunity: 0,
+                    fairness_score,
+                    toxicity_score: None,
+                },
+                "2024-01-01T12:00:00Z".to_string(),
+                vec![],
+            )
+        };
+
+        assert!(register("1.0.0", test_cid("weights"), 8200).is_ok());
+        assert!(register("v1", test_cid("weights"), 8200).is_err());
+        assert!(register("1.0.0", "weights".to_string(), 8200).is_err());
+        assert!(register("1.0.0", test_cid("weights"), 12000).is_err());
+    }
+
+    #[test]
+    fn test_verify_passport_signed_by_auditor() {
+        let auditor = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let mut receipt = register_with_hash(
+            ModelInfo {
+                model_name: "SignedModel".to_string(),
+                version: "1.0.0".to_string(),
+                architecture: "transformer".to_string(),
+                parameters: 1_000_000,
+                training_data_description: "Test data".to_string(),
+            },
+            test_cid("weights"),
+            ComplianceDoc {
+                framework: "EU AI Act".to_string(),
+                risk_level: "minimal".to_string(),
+                certification_date: "2024-01-01T12:00:00Z".to_string(),
+                auditor: "Test Auditor".to_string(),
+                document_cid: test_cid("doc"),
+            },
+            BiasMetrics {
+                demographic_parity: 500,
+                equal_opportunity: 9500,
+                fairness_score: 9200,
+                toxicity_score: None,
+            },
+            "2024-01-01T12:00:00Z".to_string(),
+            vec![],
+        )
+        .unwrap();
+        rc::sign(&mut receipt.receipt_card, &auditor).unwrap();
+        assert!(verify_passport(&receipt).unwrap());
+
+        // Tampering with the passport after signing is detected
+        receipt.passport.bias_metrics.fairness_score = 9900;
+        assert!(!verify_passport(&receipt).unwrap());
+    }
+
+    fn expiring_passport(key: &KeyPair) -> PassportReceipt {
+        let mut receipt = register_with_expiry(
+            ModelInfo {
+                model_name: "Expiring".to_string(),
+                version: "1.0.0".to_string(),
+                architecture: "transformer".to_string(),
+                parameters: 1_000_000,
+                training_data_description: "Test".to_string(),
+            },
+            test_cid("weights"),
+            ComplianceDoc {
+                framework: "EU AI Act".to_string(),
+                risk_level: "limited".to_string(),
+                certification_date: "2024-01-01T12:00:00Z".to_string(),
+                auditor: "Auditor".to_string(),
+                document_cid: test_cid("doc"),
+            },
+            BiasMetrics {
+                demographic_parity: 1000,
+                equal_opportunity: 9000,
+                fairness_score: 8800,
+                toxicity_score: None,
+            },
+            "2024-01-01T12:00:00Z".to_string(),
+            "2025-01-01T01:00:00+01:00".to_string(),
+            vec![],
+        )
+        .unwrap();
+        rc::sign(&mut receipt.receipt_card, key).unwrap();
+        receipt
+    }
+
+    #[test]
+    fn test_passport_expiry() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let receipt = expiring_passport(&key);
+        assert_eq!(
+            receipt.passport.expires_at.as_deref(),
+            Some("2025-01-01T00:00:00Z")
+        );
+
+        let at = |s: &str| VerifyOptions {
+            now: Some(Timestamp::parse_rfc3339(s).unwrap()),
+            ..Default::default()
+        };
+        assert!(verify_passport_with(&receipt, &at("2024-06-01T00:00:00Z")).unwrap());
+        assert!(!verify_passport_with(&receipt, &at("2025-06-01T00:00:00Z")).unwrap());
+    }
+
+    #[test]
+    fn test_passport_revocation() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let clock = crate::clock::FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let receipt = expiring_passport(&key);
+
+        let mut list = rc::StatusList::new(vec![authority.key_id()]);
+        list.add(
+            &rc::revoke(
+                &receipt.receipt_card.recibo.content_cid,
+                "non-compliant",
+                &authority,
+                &clock,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let options = VerifyOptions {
+            status_list: Some(&list),
+            ..Default::default()
+        };
+        assert!(verify_passport(&receipt).unwrap());
+        assert!(!verify_passport_with(&receipt, &options).unwrap());
+    }
+
+    #[test]
+    fn test_register_with_expiry_rejects_past_expiry() {
+        let result = register_with_expiry(
+            ModelInfo {
+                model_name: "Bad".to_string(),
+                version: "1.0.0".to_string(),
+                architecture: "cnn".to_string(),
+                parameters: 1,
+                training_data_description: "Test".to_string(),
+            },
+            test_cid("weights"),
+            ComplianceDoc {
+                framework: "EU AI Act".to_string(),
+                risk_level: "limited".to_string(),
+                certification_date: "2024-01-01T12:00:00Z".to_string(),
+                auditor: "Auditor".to_string(),
+                document_cid: test_cid("doc"),
+            },
+            BiasMetrics {
+                demographic_parity: 1000,
+                equal_opportunity: 9000,
+                fairness_score: 8800,
+                toxicity_score: None,
+            },
+            "2024-01-01T12:00:00Z".to_string(),
+            "2023-01-01T00:00:00Z".to_string(),
+            vec![],
+        );
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_validate_compliance() {
         // Test passing compliance
@@ -345,7 +661,7 @@ mod tests {
                 risk_level: "minimal".to_string(),
                 certification_date: "2024-01-01T12:00:00Z".to_string(),
                 auditor: "Auditor".to_string(),
-                document_cid: "doc_cid".to_string(),
+                document_cid: test_cid("doc"),
             },
             bias_metrics: BiasMetrics {
                 demographic_parity: 1500,   // 0.15
@@ -355,6 +671,7 @@ mod tests {
             },
             registration_timestamp: "2024-01-01T12:00:00Z".to_string(),
             additional_metadata: None,
+            expires_at: None,
         };
 
         assert!(validate_compliance(&good_passport).unwrap());
@@ -374,7 +691,7 @@ mod tests {
                 risk_level: "high".to_string(),
                 certification_date: "2024-01-01T12:00:00Z".to_string(),
                 auditor: "Auditor".to_string(),
-                document_cid: "doc_cid".to_string(),
+                document_cid: test_cid("doc"),
             },
             bias_metrics: BiasMetrics {
                 demographic_parity: 1500,   // 0.15
@@ -384,6 +701,7 @@ mod tests {
             },
             registration_timestamp: "2024-01-01T12:00:00Z".to_string(),
             additional_metadata: None,
+            expires_at: None,
         };
 
         assert!(!validate_compliance(&bad_passport).unwrap());
@@ -404,7 +722,7 @@ mod tests {
             risk_level: "minimal".to_string(),
             certification_date: "2024-01-01T12:00:00Z".to_string(),
             auditor: "Test Auditor".to_string(),
-            document_cid: "test_cid".to_string(),
+            document_cid: test_cid("test"),
         };
 
         let bias_metrics = BiasMetrics {
@@ -416,7 +734,7 @@ mod tests {
 
         let receipt1 = register_with_hash(
             model_info.clone(),
-            "test_cid".to_string(),
+            test_cid("test"),
             compliance.clone(),
             bias_metrics.clone(),
             "2024-01-01T12:00:00Z".to_string(),
@@ -426,7 +744,7 @@ mod tests {
 
         let receipt2 = register_with_hash(
             model_info,
-            "test_cid".to_string(),
+            test_cid("test"),
             compliance,
             bias_metrics,
             "2024-01-01T12:00:00Z".to_string(),
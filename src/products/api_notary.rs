@@ -4,24 +4,42 @@
 /// for B2B API data exchanges. Eliminates disputes with cryptographic proof.
 ///
 /// Use case: Sidecar for APIs that need to prove "I sent this" or "I received this"
-use crate::chips::normalize;
-use crate::rc;
+use crate::clock;
+use crate::rc::{self, Kind, VerifyOptions};
+use crate::tsa::TimestampAuthority;
 use crate::types::{ReciboCard, Signature};
 use crate::Result;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
 /// API Request/Response pair for notarization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiTransaction {
     pub method: String,
     pub path: String,
-    pub timestamp: String, // ISO 8601 format
+    pub timestamp: String, // RFC 3339, canonicalized to UTC on notarization
     pub request_body: Option<Value>,
     pub response_body: Option<Value>,
     pub status_code: u16,
 }
 
+impl Kind for ApiTransaction {
+    const KIND: &'static str = "product.api-notary.v1";
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "method": {"type": "string"},
+                "path": {"type": "string"},
+                "timestamp": {"type": "string", "format": "rfc3339"},
+                "status_code": {"type": "integer", "minimum": 100, "maximum": 599}
+            },
+            "required": ["method", "path", "timestamp", "status_code"]
+        })
+    }
+}
+
 /// Notarization result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotaryReceipt {
@@ -32,12 +50,17 @@ pub struct NotaryReceipt {
 /// Notarize an API transaction
 ///
 /// Creates a cryptographic receipt for an API request/response pair.
-/// Both parties can sign to create proof of agreement on what was exchanged.
-pub fn notarize(transaction: ApiTransaction, signatures: Vec<Signature>) -> Result<NotaryReceipt> {
-    // Convert transaction to value and emit receipt card
-    // Note: emit_with_signatures will normalize internally
-    let transaction_value = serde_json::to_value(&transaction)?;
-    let receipt_card = rc::emit_with_signatures(transaction_value, signatures)?;
+/// Both parties can sign to create proof of agreement on what was exchanged;
+/// they sign `rc::typed_signing_cid` of the transaction.
+/// The timestamp must be RFC 3339 and is stored in canonical UTC form.
+pub fn notarize(
+    mut transaction: ApiTransaction,
+    signatures: Vec<Signature>,
+) -> Result<NotaryReceipt> {
+    transaction.timestamp = clock::canonicalize(&transaction.timestamp)?;
+
+    // Emit a typed receipt card (normalization happens inside emit)
+    let receipt_card = rc::emit_typed(&transaction, signatures)?;
 
     Ok(NotaryReceipt {
         transaction,
@@ -45,22 +68,37 @@ pub fn notarize(transaction: ApiTransaction, signatures: Vec<Signature>) -> Resu
     })
 }
 
+/// Notarize an API transaction and have a TSA timestamp the receipt
+///
+/// The token proves to third parties that the transaction existed no later
+/// than the TSA's time, provided they trust the TSA's key (see
+/// `VerifyOptions.trusted_tsas`). `verify` only rejects malformed tokens.
+pub fn notarize_with_timestamp(
+    transaction: ApiTransaction,
+    signatures: Vec<Signature>,
+    tsa: &dyn TimestampAuthority,
+) -> Result<NotaryReceipt> {
+    let mut receipt = notarize(transaction, signatures)?;
+    rc::timestamp(&mut receipt.receipt_card, tsa)?;
+    Ok(receipt)
+}
+
 /// Verify a notary receipt
 ///
-/// Verifies that the receipt's CID matches the transaction content.
-/// In a real implementation, this would also verify signatures.
+/// Checks the receipt against the transaction it claims to cover and
+/// verifies every signature via `rc::verify_typed`.
 pub fn verify(receipt: &NotaryReceipt) -> Result<bool> {
-    // Re-normalize the transaction
-    let transaction_value = serde_json::to_value(&receipt.transaction)?;
-    let normalized = normalize(transaction_value)?;
-
-    // Check if CID matches
-    Ok(normalized.cid == receipt.receipt_card.recibo.content_cid)
+    let card = ReciboCard {
+        body: serde_json::to_value(&receipt.transaction)?,
+        recibo: receipt.receipt_card.recibo.clone(),
+    };
+    Ok(rc::verify_typed::<ApiTransaction>(&card, "true", &VerifyOptions::default())?.valid)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keystore::{KeyAlgorithm, KeyPair};
     use serde_json::json;
 
     #[test]
@@ -128,6 +166,113 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_verify_checks_signatures() {
+        let transaction = ApiTransaction {
+            method: "POST".to_string(),
+            path: "/api/v1/payment".to_string(),
+            timestamp: "2024-01-01T12:00:00Z".to_string(),
+            request_body: Some(json!({"amount": 100})),
+            response_body: None,
+            status_code: 201,
+        };
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let cid = rc::typed_signing_cid(&transaction).unwrap();
+
+        let receipt = notarize(transaction.clone(), vec![key.sign_cid(&cid).unwrap()]).unwrap();
+        assert!(verify(&receipt).unwrap());
+
+        let forged = Signature {
+            algorithm: "ed25519".to_string(),
+            public_key: key.public_key_b64(),
+            signature: key.sign(b"something else").unwrap().signature,
+        };
+        let receipt = notarize(transaction, vec![forged]).unwrap();
+        assert!(!verify(&receipt).unwrap());
+    }
+
+    #[test]
+    fn test_verify_detects_tampered_transaction() {
+        let transaction = ApiTransaction {
+            method: "GET".to_string(),
+            path: "/api/v1/data".to_string(),
+            timestamp: "2024-01-01T12:00:00Z".to_string(),
+            request_body: None,
+            response_body: Some(json!({"balance": 10})),
+            status_code: 200,
+        };
+        let mut receipt = notarize(transaction, vec![]).unwrap();
+        receipt.transaction.response_body = Some(json!({"balance": 1000}));
+        assert!(!verify(&receipt).unwrap());
+    }
+
+    #[test]
+    fn test_notary_receipt_is_typed() {
+        let cas = crate::cas::Cas::new();
+        crate::products::register_kinds(&cas).unwrap();
+        let transaction = ApiTransaction {
+            method: "GET".to_string(),
+            path: "/api/v1/status".to_string(),
+            timestamp: "2024-01-01T12:00:00Z".to_string(),
+            request_body: None,
+            response_body: Some(json!({"ok": true})),
+            status_code: 200,
+        };
+
+        let receipt = notarize(transaction, vec![]).unwrap();
+        assert_eq!(
+            receipt.receipt_card.recibo.kind.as_deref(),
+            Some(ApiTransaction::KIND)
+        );
+        assert!(rc::kind::validate_card(&receipt.receipt_card, &cas).is_ok());
+        let decoded: ApiTransaction = rc::decode(&receipt.receipt_card).unwrap();
+        assert_eq!(decoded.path, "/api/v1/status");
+    }
+
+    #[test]
+    fn test_notarize_with_timestamp() {
+        let tsa = crate::tsa::LocalTsa::new(
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            Box::new(crate::clock::FixedClock::at("2024-01-01T12:00:05Z").unwrap()),
+        );
+        let transaction = ApiTransaction {
+            method: "POST".to_string(),
+            path: "/api/v1/orders".to_string(),
+            timestamp: "2024-01-01T12:00:00Z".to_string(),
+            request_body: Some(json!({"item": "widget"})),
+            response_body: None,
+            status_code: 201,
+        };
+
+        let receipt = notarize_with_timestamp(transaction, vec![], &tsa).unwrap();
+        let token = receipt.receipt_card.recibo.timestamp.as_ref().unwrap();
+        assert_eq!(token.info.time.to_rfc3339(), "2024-01-01T12:00:05Z");
+        assert!(crate::tsa::verify_token(
+            token,
+            &receipt.receipt_card.recibo.content_cid,
+            Some(&tsa.key_id())
+        )
+        .unwrap());
+        assert!(verify(&receipt).unwrap());
+    }
+
+    #[test]
+    fn test_notarize_canonicalizes_timestamp() {
+        let mut transaction = ApiTransaction {
+            method: "GET".to_string(),
+            path: "/api/v1/time".to_string(),
+            timestamp: "2024-01-01T14:00:00+02:00".to_string(),
+            request_body: None,
+            response_body: None,
+            status_code: 204,
+        };
+        let receipt = notarize(transaction.clone(), vec![]).unwrap();
+        assert_eq!(receipt.transaction.timestamp, "2024-01-01T12:00:00Z");
+
+        transaction.timestamp = "yesterday".to_string();
+        assert!(notarize(transaction, vec![]).is_err());
+    }
+
     #[test]
     fn test_notarize_deterministic() {
         let transaction = ApiTransaction {
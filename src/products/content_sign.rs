@@ -6,7 +6,9 @@
 /// Use case: Newsrooms, content creators, anti-fake news verification
 use crate::cas::Cas;
 use crate::chips::normalize;
-use crate::rc;
+use crate::clock;
+use crate::rc::{self, Kind, VerifyOptions};
+use crate::tsa::TimestampAuthority;
 use crate::types::{ReciboCard, Signature};
 use crate::{Result, RhoError};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
@@ -19,12 +21,32 @@ pub struct SignedContent {
     pub content_type: String, // "article", "image", "video", etc.
     pub title: String,
     pub author: String,
-    pub timestamp: String,   // ISO 8601 format
+    pub timestamp: String,   // RFC 3339, canonical UTC
     pub content_cid: String, // CID of the actual content
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Value>,
 }
 
+impl Kind for SignedContent {
+    const KIND: &'static str = "product.content-sign.v1";
+
+    fn schema() -> Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "content_type": {"type": "string"},
+                "title": {"type": "string"},
+                "author": {"type": "string"},
+                "timestamp": {"type": "string", "format": "rfc3339"},
+                "content_cid": {"type": "string", "format": "cid"},
+                "metadata": {}
+            },
+            "required": ["content_type", "title", "author", "timestamp", "content_cid"],
+            "additionalProperties": false
+        })
+    }
+}
+
 /// Content signing result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignedReceipt {
@@ -36,6 +58,7 @@ pub struct SignedReceipt {
 ///
 /// Creates a Recibo Card that proves the content came from a specific author/organization.
 /// The receipt can be verified offline by checking signatures and CID.
+/// The timestamp must be RFC 3339 and is stored in canonical UTC form.
 #[allow(clippy::too_many_arguments)]
 pub fn sign_content(
     content_type: String,
@@ -47,6 +70,8 @@ pub fn sign_content(
     metadata: Option<Value>,
     cas: &Cas,
 ) -> Result<SignedReceipt> {
+    let timestamp = clock::canonicalize(&timestamp)?;
+
     // Hash the actual content to get its CID
     let content_hash = blake3::hash(&content);
     let content_cid = BASE64.encode(content_hash.as_bytes());
@@ -64,9 +89,8 @@ pub fn sign_content(
         metadata,
     };
 
-    // Emit receipt card (normalization happens inside emit_with_signatures)
-    let content_value = serde_json::to_value(&signed_content)?;
-    let receipt_card = rc::emit_with_signatures(content_value, signatures)?;
+    // Emit a typed receipt card (normalization happens inside emit)
+    let receipt_card = rc::emit_typed(&signed_content, signatures)?;
 
     Ok(SignedReceipt {
         signed_content,
@@ -78,12 +102,15 @@ pub fn sign_content(
 ///
 /// Simpler version for pure JSON content (not binary files).
 /// Useful for APIs, structured data, configuration files.
+/// The timestamp must be RFC 3339 and is stored in canonical UTC form.
 pub fn sign_json(
     content: Value,
     author: String,
     timestamp: String,
     signatures: Vec<Signature>,
 ) -> Result<ReciboCard> {
+    let timestamp = clock::canonicalize(&timestamp)?;
+
     // Normalize the content first to ensure it's valid
     let normalized = normalize(content.clone())?;
 
@@ -99,17 +126,40 @@ pub fn sign_json(
     rc::emit_with_signatures(signed_doc, signatures)
 }
 
+/// Sign a JSON document and have a TSA timestamp the receipt
+///
+/// Unlike the author-supplied `timestamp`, the TSA token is third-party
+/// evidence that the document existed at the TSA's time, for verifiers that
+/// trust the TSA's key (see `VerifyOptions.trusted_tsas`).
+pub fn sign_json_with_timestamp(
+    content: Value,
+    author: String,
+    timestamp: String,
+    signatures: Vec<Signature>,
+    tsa: &dyn TimestampAuthority,
+) -> Result<ReciboCard> {
+    let mut receipt = sign_json(content, author, timestamp, signatures)?;
+    rc::timestamp(&mut receipt, tsa)?;
+    Ok(receipt)
+}
+
 /// Verify a signed receipt
 ///
-/// Checks if the content CID in the receipt matches the actual content.
-/// In a real implementation, this would also verify cryptographic signatures.
+/// Checks that the content hashes to the CID in the receipt, then verifies
+/// the receipt and its signatures via `rc::verify_typed`.
 pub fn verify(receipt: &SignedReceipt, content: Vec<u8>) -> Result<bool> {
     // Hash the content
     let content_hash = blake3::hash(&content);
     let content_cid = BASE64.encode(content_hash.as_bytes());
+    if content_cid != receipt.signed_content.content_cid {
+        return Ok(false);
+    }
 
-    // Check if CID matches
-    Ok(content_cid == receipt.signed_content.content_cid)
+    let card = ReciboCard {
+        body: serde_json::to_value(&receipt.signed_content)?,
+        recibo: receipt.receipt_card.recibo.clone(),
+    };
+    Ok(rc::verify_typed::<SignedContent>(&card, "true", &VerifyOptions::default())?.valid)
 }
 
 /// Verify a signed JSON document
@@ -130,13 +180,17 @@ pub fn verify_json(receipt: &ReciboCard) -> Result<bool> {
         .and_then(|v| v.as_str())
         .ok_or_else(|| RhoError::Validate("Missing content_cid field".to_string()))?;
 
-    // Compare CIDs
-    Ok(normalized.cid == stored_cid)
+    if normalized.cid != stored_cid {
+        return Ok(false);
+    }
+
+    Ok(rc::verify_card(receipt, "true")?.valid)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keystore::{KeyAlgorithm, KeyPair};
     use serde_json::json;
 
     #[test]
@@ -235,6 +289,70 @@ mod tests {
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_verify_json_checks_signatures() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut receipt = sign_json(
+            json!({"data": "test"}),
+            "Author".to_string(),
+            "2024-01-01T12:00:00Z".to_string(),
+            vec![],
+        )
+        .unwrap();
+        rc::sign(&mut receipt, &key).unwrap();
+        assert!(verify_json(&receipt).unwrap());
+
+        // A signature from another card does not carry over
+        let other = sign_json(
+            json!({"data": "other"}),
+            "Author".to_string(),
+            "2024-01-01T12:00:00Z".to_string(),
+            vec![],
+        )
+        .unwrap();
+        receipt
+            .recibo
+            .signatures
+            .push(key.sign_cid(&other.recibo.content_cid).unwrap());
+        assert!(!verify_json(&receipt).unwrap());
+    }
+
+    #[test]
+    fn test_sign_json_with_timestamp() {
+        let tsa = crate::tsa::LocalTsa::new(
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            Box::new(crate::clock::FixedClock::at("2024-01-02T00:00:00Z").unwrap()),
+        );
+        let mut receipt = sign_json_with_timestamp(
+            json!({"headline": "Timestamped"}),
+            "Author".to_string(),
+            "2024-01-01T12:00:00Z".to_string(),
+            vec![],
+            &tsa,
+        )
+        .unwrap();
+        assert!(receipt.recibo.timestamp.is_some());
+        assert!(verify_json(&receipt).unwrap());
+
+        // Moving the token to different content fails verification
+        receipt.body["content"] = json!({"headline": "Altered"});
+        receipt.body["content_cid"] =
+            json!(normalize(receipt.body["content"].clone()).unwrap().cid);
+        receipt.recibo.content_cid = rc::content_cid(&receipt.body).unwrap();
+        assert!(!verify_json(&receipt).unwrap());
+    }
+
+    #[test]
+    fn test_sign_json_rejects_invalid_timestamp() {
+        let result = sign_json(
+            json!({"data": "test"}),
+            "Author".to_string(),
+            "not a timestamp".to_string(),
+            vec![],
+        );
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_sign_content_deterministic() {
         let cas = Cas::new();
@@ -0,0 +1,419 @@
+/// Product layer: W3C Verifiable Credentials
+///
+/// Exports AI passports and content receipts as VC Data Model 2.0
+/// credentials secured with a Data Integrity proof (cryptosuite
+/// `eddsa-jcs-2022`), so wallets and standard verifiers can consume them.
+/// Issuers are identified by `did:key` DIDs derived from Ed25519 keys.
+///
+/// The credential subject carries the card body and its native Recibo, so an
+/// imported credential turns back into the exact RC it was issued from.
+use super::ai_passport::{AiPassport, PassportReceipt};
+use super::content_sign::{SignedContent, SignedReceipt};
+use crate::chips::normalize;
+use crate::clock::Clock;
+use crate::keystore::{self, KeyAlgorithm, KeyPair};
+use crate::types::{Recibo, ReciboCard, Signature};
+use crate::{Result, RhoError};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Base context of VC Data Model 2.0
+pub const VC_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+
+/// Cryptosuite used for every proof we issue
+pub const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+
+/// Multicodec prefix of an Ed25519 public key
+const ED25519_MULTICODEC: [u8; 2] = [0xed, 0x01];
+
+/// `did:key` identifier of an Ed25519 key
+pub fn did_key(key: &KeyPair) -> Result<String> {
+    if key.algorithm() != KeyAlgorithm::Ed25519 {
+        return Err(RhoError::Signature(format!(
+            "did:key issuers must be ed25519, got {}",
+            key.algorithm()
+        )));
+    }
+    let mut bytes = ED25519_MULTICODEC.to_vec();
+    bytes.extend_from_slice(key.public_key());
+    Ok(format!("did:key:z{}", bs58::encode(bytes).into_string()))
+}
+
+/// Ed25519 public key named by a `did:key` DID or DID URL
+pub fn did_key_public_key(did: &str) -> Result<Vec<u8>> {
+    let invalid = || RhoError::InvalidInput(format!("Not an ed25519 did:key: {}", did));
+    let multibase = did
+        .split('#')
+        .next()
+        .and_then(|d| d.strip_prefix("did:key:z"))
+        .ok_or_else(invalid)?;
+    let bytes = bs58::decode(multibase).into_vec().map_err(|_| invalid())?;
+    match bytes.strip_prefix(&ED25519_MULTICODEC) {
+        Some(public_key) if public_key.len() == 32 => Ok(public_key.to_vec()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Issue a credential for any RC
+///
+/// `credential_type` is added after `VerifiableCredential` in `type`.
+pub fn issue_credential(
+    card: &ReciboCard,
+    credential_type: &str,
+    issuer: &KeyPair,
+    clock: &dyn Clock,
+) -> Result<Value> {
+    let content_cid = normalize(card.body.clone())?.cid;
+    if content_cid != card.recibo.content_cid {
+        return Err(RhoError::CidMismatch {
+            expected: card.recibo.content_cid.clone(),
+            actual: content_cid,
+        });
+    }
+
+    let did = did_key(issuer)?;
+    let now = clock.now().to_rfc3339();
+    let mut credential = json!({
+        "@context": [VC_CONTEXT],
+        "type": ["VerifiableCredential", credential_type],
+        "issuer": did,
+        "validFrom": now,
+        "credentialSubject": {
+            "body": card.body,
+            "recibo": card.recibo,
+        },
+    });
+
+    let mut proof = json!({
+        "type": "DataIntegrityProof",
+        "cryptosuite": CRYPTOSUITE,
+        "created": now,
+        "verificationMethod": verification_method(&did),
+        "proofPurpose": "assertionMethod",
+        "@context": [VC_CONTEXT],
+    });
+    let signature = issuer.sign(&hash_data(&credential, &proof)?)?;
+    proof["proofValue"] = json!(format!(
+        "z{}",
+        bs58::encode(BASE64.decode(&signature.signature)?).into_string()
+    ));
+    credential["proof"] = proof;
+
+    Ok(credential)
+}
+
+/// Issue an `AiPassportCredential` for a registered model
+pub fn passport_credential(
+    receipt: &PassportReceipt,
+    issuer: &KeyPair,
+    clock: &dyn Clock,
+) -> Result<Value> {
+    issue_credential(&receipt.receipt_card, "AiPassportCredential", issuer, clock)
+}
+
+/// Issue a `SignedContentCredential` for signed content
+pub fn content_credential(
+    receipt: &SignedReceipt,
+    issuer: &KeyPair,
+    clock: &dyn Clock,
+) -> Result<Value> {
+    issue_credential(
+        &receipt.receipt_card,
+        "SignedContentCredential",
+        issuer,
+        clock,
+    )
+}
+
+/// Verify the Data Integrity proof of a credential
+///
+/// Only `eddsa-jcs-2022` proofs by a `did:key` matching the issuer are
+/// accepted. This says nothing about the native signatures in the subject;
+/// import the credential and run `rc::verify_card` for those.
+pub fn verify_credential(credential: &Value) -> Result<bool> {
+    let proof = credential
+        .get("proof")
+        .ok_or_else(|| RhoError::InvalidInput("Credential has no proof".to_string()))?;
+    if proof["type"] != "DataIntegrityProof" || proof["cryptosuite"] != CRYPTOSUITE {
+        return Ok(false);
+    }
+
+    let issuer = credential["issuer"]
+        .as_str()
+        .ok_or_else(|| RhoError::InvalidInput("Credential has no issuer".to_string()))?;
+    let method = proof["verificationMethod"].as_str().unwrap_or_default();
+    if method != verification_method(issuer) {
+        return Ok(false);
+    }
+
+    let proof_value = proof["proofValue"]
+        .as_str()
+        .and_then(|v| v.strip_prefix('z'))
+        .ok_or_else(|| RhoError::InvalidInput("Missing multibase proofValue".to_string()))?;
+    let signature = Signature {
+        algorithm: KeyAlgorithm::Ed25519.as_str().to_string(),
+        public_key: BASE64.encode(did_key_public_key(issuer)?),
+        signature: BASE64.encode(
+            bs58::decode(proof_value)
+                .into_vec()
+                .map_err(|e| RhoError::InvalidInput(format!("Invalid proofValue: {}", e)))?,
+        ),
+    };
+
+    let mut unsecured = credential.clone();
+    if let Some(map) = unsecured.as_object_mut() {
+        map.remove("proof");
+    }
+    let mut options = proof.clone();
+    if let Some(map) = options.as_object_mut() {
+        map.remove("proofValue");
+    }
+
+    keystore::verify(&signature, &hash_data(&unsecured, &options)?)
+}
+
+/// Import a credential back into the RC it was issued from
+///
+/// Fails unless the proof verifies and the subject's body matches its
+/// content_cid.
+pub fn import_credential(credential: &Value) -> Result<ReciboCard> {
+    if !verify_credential(credential)? {
+        return Err(RhoError::Signature(
+            "Credential proof does not verify".to_string(),
+        ));
+    }
+
+    let subject = &credential["credentialSubject"];
+    let body = subject["body"].clone();
+    let recibo: Recibo = serde_json::from_value(subject["recibo"].clone())?;
+    let content_cid = normalize(body.clone())?.cid;
+    if content_cid != recibo.content_cid {
+        return Err(RhoError::CidMismatch {
+            expected: recibo.content_cid,
+            actual: content_cid,
+        });
+    }
+
+    Ok(ReciboCard { body, recibo })
+}
+
+/// Import an `AiPassportCredential`
+pub fn import_passport(credential: &Value) -> Result<PassportReceipt> {
+    let receipt_card = import_typed(credential, "AiPassportCredential")?;
+    let passport: AiPassport = serde_json::from_value(receipt_card.body.clone())?;
+    Ok(PassportReceipt {
+        passport,
+        receipt_card,
+    })
+}
+
+/// Import a `SignedContentCredential`
+pub fn import_signed_content(credential: &Value) -> Result<SignedReceipt> {
+    let receipt_card = import_typed(credential, "SignedContentCredential")?;
+    let signed_content: SignedContent = serde_json::from_value(receipt_card.body.clone())?;
+    Ok(SignedReceipt {
+        signed_content,
+        receipt_card,
+    })
+}
+
+/// `import_credential`, requiring `credential_type` in `type`
+fn import_typed(credential: &Value, credential_type: &str) -> Result<ReciboCard> {
+    let typed = credential["type"]
+        .as_array()
+        .is_some_and(|types| types.iter().any(|t| t == credential_type));
+    if !typed {
+        return Err(RhoError::InvalidInput(format!(
+            "Credential is not of type {}",
+            credential_type
+        )));
+    }
+    import_credential(credential)
+}
+
+fn verification_method(did: &str) -> String {
+    let fragment = did.strip_prefix("did:key:").unwrap_or(did);
+    format!("{}#{}", did, fragment)
+}
+
+/// eddsa-jcs-2022 hashData: SHA-256(JCS(proof options)) || SHA-256(JCS(document))
+fn hash_data(document: &Value, proof_options: &Value) -> Result<Vec<u8>> {
+    let mut data = Sha256::digest(jcs(proof_options)?.as_bytes()).to_vec();
+    data.extend_from_slice(&Sha256::digest(jcs(document)?.as_bytes()));
+    Ok(data)
+}
+
+/// JSON Canonicalization Scheme (RFC 8785)
+///
+/// Like RC bodies, credentials are integer-only, so the ECMAScript number
+/// serialization reduces to plain integers.
+fn jcs(value: &Value) -> Result<String> {
+    Ok(match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => serde_json::to_string(value)?,
+        Value::Number(n) => {
+            if !(n.is_i64() || n.is_u64()) {
+                return Err(RhoError::InvalidInput(format!(
+                    "Credentials support integers only, got {}",
+                    n
+                )));
+            }
+            n.to_string()
+        }
+        Value::Array(items) => {
+            let items = items.iter().map(jcs).collect::<Result<Vec<_>>>()?;
+            format!("[{}]", items.join(","))
+        }
+        Value::Object(map) => {
+            // RFC 8785 orders members by their UTF-16 code units
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            let members = entries
+                .into_iter()
+                .map(|(k, v)| Ok(format!("{}:{}", serde_json::to_string(k)?, jcs(v)?)))
+                .collect::<Result<Vec<_>>>()?;
+            format!("{{{}}}", members.join(","))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cas::test_support::test_cid;
+    use crate::clock::test_clock;
+    use crate::products::ai_passport::{register_with_hash, BiasMetrics, ComplianceDoc, ModelInfo};
+    use crate::products::content_sign::sign_content;
+    use crate::rc;
+
+    fn passport(signer: &KeyPair) -> PassportReceipt {
+        let mut receipt = register_with_hash(
+            ModelInfo {
+                model_name: "CredModel".to_string(),
+                version: "1.0.0".to_string(),
+                architecture: "transformer".to_string(),
+                parameters: 7_000_000_000,
+                training_data_description: "Public web text".to_string(),
+            },
+            test_cid("weights"),
+            ComplianceDoc {
+                framework: "EU AI Act".to_string(),
+                risk_level: "limited".to_string(),
+                certification_date: "2024-01-01T12:00:00Z".to_string(),
+                auditor: "Audit Co".to_string(),
+                document_cid: test_cid("pdf"),
+            },
+            BiasMetrics {
+                demographic_parity: 1000,
+                equal_opportunity: 9000,
+                fairness_score: 8800,
+                toxicity_score: None,
+            },
+            "2024-01-01T12:00:00Z".to_string(),
+            vec![],
+        )
+        .unwrap();
+        rc::sign(&mut receipt.receipt_card, signer).unwrap();
+        receipt
+    }
+
+    #[test]
+    fn test_did_key_round_trip() {
+        let key = KeyPair::from_seed(KeyAlgorithm::Ed25519, [7u8; 32]);
+        let did = did_key(&key).unwrap();
+        assert!(did.starts_with("did:key:z6Mk"));
+        assert_eq!(did_key_public_key(&did).unwrap(), key.public_key());
+
+        let pq = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        assert!(did_key(&pq).is_err());
+    }
+
+    #[test]
+    fn test_passport_credential_round_trip() {
+        let issuer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let receipt = passport(&issuer);
+
+        let vc = passport_credential(&receipt, &issuer, &test_clock()).unwrap();
+        assert_eq!(vc["type"][1], "AiPassportCredential");
+        assert_eq!(vc["issuer"], did_key(&issuer).unwrap());
+        assert_eq!(vc["proof"]["cryptosuite"], CRYPTOSUITE);
+        assert_eq!(vc["validFrom"], "2024-01-01T12:00:00Z");
+        assert!(verify_credential(&vc).unwrap());
+
+        let imported = import_passport(&vc).unwrap();
+        assert_eq!(imported.passport.model_info.model_name, "CredModel");
+        assert!(
+            rc::verify_card(&imported.receipt_card, "ed25519")
+                .unwrap()
+                .valid
+        );
+    }
+
+    #[test]
+    fn test_content_credential_round_trip() {
+        let cas = crate::cas::Cas::new();
+        let issuer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let receipt = sign_content(
+            "article".to_string(),
+            "Credentialed".to_string(),
+            "Reporter".to_string(),
+            "2024-01-01T12:00:00Z".to_string(),
+            b"story".to_vec(),
+            vec![],
+            None,
+            &cas,
+        )
+        .unwrap();
+
+        let vc = content_credential(&receipt, &issuer, &test_clock()).unwrap();
+        let imported = import_signed_content(&vc).unwrap();
+        assert_eq!(imported.signed_content.title, "Credentialed");
+        assert_eq!(
+            imported.receipt_card.recibo.content_cid,
+            receipt.receipt_card.recibo.content_cid
+        );
+    }
+
+    #[test]
+    fn test_import_requires_credential_type() {
+        let issuer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let receipt = passport(&issuer);
+        let vc = issue_credential(
+            &receipt.receipt_card,
+            "OtherCredential",
+            &issuer,
+            &test_clock(),
+        )
+        .unwrap();
+
+        assert!(import_credential(&vc).is_ok());
+        let err = import_passport(&vc).unwrap_err();
+        assert!(err.to_string().contains("AiPassportCredential"));
+    }
+
+    #[test]
+    fn test_tampered_credential_fails() {
+        let issuer = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let vc = passport_credential(&passport(&issuer), &issuer, &test_clock()).unwrap();
+
+        let mut tampered = vc.clone();
+        tampered["credentialSubject"]["body"]["model_weights_cid"] = json!("other");
+        assert!(!verify_credential(&tampered).unwrap());
+        assert!(import_credential(&tampered).is_err());
+
+        let mut reissued = vc;
+        let other = KeyPair::generate(KeyAlgorithm::Ed25519);
+        reissued["issuer"] = json!(did_key(&other).unwrap());
+        assert!(!verify_credential(&reissued).unwrap());
+    }
+
+    #[test]
+    fn test_jcs_orders_by_utf16() {
+        // U+10000 sorts before U+E000 in UTF-16 but after it in UTF-8
+        let value = json!({"\u{10000}": 1, "\u{e000}": 2, "a": [true, null]});
+        assert_eq!(
+            jcs(&value).unwrap(),
+            "{\"a\":[true,null],\"\u{10000}\":1,\"\u{e000}\":2}"
+        );
+    }
+}
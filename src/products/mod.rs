@@ -5,13 +5,33 @@
 pub mod ai_passport;
 pub mod api_notary;
 pub mod content_sign;
+pub mod credential;
 
 // Re-export for convenience
 pub use ai_passport::{
-    register_model, register_with_hash, validate_compliance, verify_passport, AiPassport,
-    PassportReceipt,
+    register_model, register_with_expiry, register_with_hash, validate_compliance, verify_passport,
+    verify_passport_with, AiPassport, PassportReceipt,
+};
+pub use api_notary::{
+    notarize, notarize_with_timestamp, verify as verify_notary, ApiTransaction, NotaryReceipt,
 };
-pub use api_notary::{notarize, verify as verify_notary, ApiTransaction, NotaryReceipt};
 pub use content_sign::{
-    sign_content, sign_json, verify as verify_content, verify_json, SignedContent, SignedReceipt,
+    sign_content, sign_json, sign_json_with_timestamp, verify as verify_content, verify_json,
+    SignedContent, SignedReceipt,
+};
+pub use credential::{
+    content_credential, did_key, import_credential, import_passport, import_signed_content,
+    issue_credential, passport_credential, verify_credential,
 };
+
+use crate::cas::Cas;
+use crate::rc;
+use crate::Result;
+
+/// Register the schemas of the receipt kinds emitted by products
+pub fn register_kinds(cas: &Cas) -> Result<()> {
+    rc::kind::register::<AiPassport>(cas)?;
+    rc::kind::register::<ApiTransaction>(cas)?;
+    rc::kind::register::<SignedContent>(cas)?;
+    Ok(())
+}
@@ -0,0 +1,312 @@
+// Threshold co-signing
+//
+// A draft card is handed to several parties. Each signs the same signing CID
+// (see `rc::signing_cid`) on its own copy, and the partial cards are merged
+// in any order into one card whose signatures are deduplicated and
+// canonically ordered. A `Threshold` (k of n named keys) decides when the
+// card is complete.
+
+use crate::keystore::{self, KeyPair};
+use crate::rc;
+use crate::tsa;
+use crate::types::{Cid, ReciboCard, Signature};
+use crate::{Result, RhoError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// k-of-n signing requirement over key IDs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub k: usize,
+    /// Key IDs allowed to count towards `k`, sorted and deduplicated
+    pub signers: Vec<Cid>,
+}
+
+impl Threshold {
+    pub fn new(k: usize, signers: Vec<Cid>) -> Result<Self> {
+        let signers: Vec<Cid> = signers
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if k == 0 || k > signers.len() {
+            return Err(RhoError::InvalidInput(format!(
+                "Threshold {} is out of range for {} signers",
+                k,
+                signers.len()
+            )));
+        }
+        Ok(Self { k, signers })
+    }
+}
+
+/// Progress of a card towards a `Threshold`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosignStatus {
+    pub k: usize,
+    /// Listed signers with a valid signature on the card
+    pub signed: Vec<Cid>,
+    /// Listed signers that have not signed yet
+    pub missing: Vec<Cid>,
+    pub complete: bool,
+}
+
+/// Sign a copy of a draft card, producing a partial card for `merge`
+pub fn cosign(draft: &ReciboCard, key: &KeyPair) -> Result<ReciboCard> {
+    let mut partial = draft.clone();
+    partial
+        .recibo
+        .signatures
+        .push(key.sign_cid(&rc::signing_cid(&draft.recibo)?)?);
+    canonicalize_signatures(&mut partial.recibo.signatures);
+    Ok(partial)
+}
+
+/// Merge partial cards of the same draft
+///
+/// All cards must share `content_cid`, `kind` and `parents`. Every signature
+/// must verify over their signing CID; one signature per signer is kept and
+/// the result is sorted, so the merge is independent of input order. Of the
+/// timestamp tokens that verify against `content_cid`, the earliest is kept;
+/// tokens that do not verify are dropped.
+pub fn merge(cards: &[ReciboCard]) -> Result<ReciboCard> {
+    let (first, rest) = cards
+        .split_first()
+        .ok_or_else(|| RhoError::InvalidInput("Nothing to merge".to_string()))?;
+
+    let mut merged = first.clone();
+    merged.recibo.timestamp = None;
+    for card in rest {
+        if card.recibo.content_cid != merged.recibo.content_cid {
+            return Err(RhoError::CidMismatch {
+                expected: merged.recibo.content_cid.clone(),
+                actual: card.recibo.content_cid.clone(),
+            });
+        }
+        if card.recibo.kind != merged.recibo.kind {
+            return Err(RhoError::InvalidInput(
+                "Cannot merge cards of different kinds".to_string(),
+            ));
+        }
+        if card.recibo.parents != merged.recibo.parents {
+            return Err(RhoError::InvalidInput(
+                "Cannot merge cards with different parents".to_string(),
+            ));
+        }
+        merged
+            .recibo
+            .signatures
+            .extend(card.recibo.signatures.iter().cloned());
+    }
+
+    for token in cards
+        .iter()
+        .filter_map(|card| card.recibo.timestamp.as_ref())
+    {
+        if !tsa::verify_token(token, &merged.recibo.content_cid, None).unwrap_or(false) {
+            continue;
+        }
+        let earlier = match &merged.recibo.timestamp {
+            Some(current) => {
+                (token.info.time, &token.info.tsa, token.info.serial)
+                    < (current.info.time, &current.info.tsa, current.info.serial)
+            }
+            None => true,
+        };
+        if earlier {
+            merged.recibo.timestamp = Some(token.clone());
+        }
+    }
+
+    let signing_cid = rc::signing_cid(&merged.recibo)?;
+    for sig in &merged.recibo.signatures {
+        if !keystore::verify_cid(sig, &signing_cid)? {
+            return Err(RhoError::Signature(format!(
+                "Signature by {} does not verify over the signing CID",
+                sig.public_key
+            )));
+        }
+    }
+    canonicalize_signatures(&mut merged.recibo.signatures);
+    Ok(merged)
+}
+
+/// Check a card against a threshold
+///
+/// Only valid signatures by listed signers count.
+pub fn status(card: &ReciboCard, threshold: &Threshold) -> Result<CosignStatus> {
+    let signing_cid = rc::signing_cid(&card.recibo)?;
+    let mut signed = BTreeSet::new();
+    for sig in &card.recibo.signatures {
+        let Ok(signer) = keystore::signer_id(sig) else {
+            continue;
+        };
+        if threshold.signers.contains(&signer)
+            && keystore::verify_cid(sig, &signing_cid).unwrap_or(false)
+        {
+            signed.insert(signer);
+        }
+    }
+
+    let missing = threshold
+        .signers
+        .iter()
+        .filter(|s| !signed.contains(*s))
+        .cloned()
+        .collect();
+    Ok(CosignStatus {
+        k: threshold.k,
+        complete: signed.len() >= threshold.k,
+        signed: signed.into_iter().collect(),
+        missing,
+    })
+}
+
+/// True once `threshold` is met
+pub fn is_complete(card: &ReciboCard, threshold: &Threshold) -> Result<bool> {
+    Ok(status(card, threshold)?.complete)
+}
+
+/// Sort signatures and keep one per (algorithm, public_key)
+fn canonicalize_signatures(signatures: &mut Vec<Signature>) {
+    signatures.sort();
+    signatures.dedup_by(|b, a| a.algorithm == b.algorithm && a.public_key == b.public_key);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::KeyAlgorithm;
+    use serde_json::json;
+
+    fn auditors() -> Vec<KeyPair> {
+        vec![
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            KeyPair::generate(KeyAlgorithm::MlDsa3),
+        ]
+    }
+
+    #[test]
+    fn test_two_of_three_sign_off() {
+        let keys = auditors();
+        let threshold = Threshold::new(2, keys.iter().map(|k| k.key_id()).collect()).unwrap();
+        let draft = rc::emit(json!({"passport": "model-x"})).unwrap();
+
+        let a = cosign(&draft, &keys[0]).unwrap();
+        assert!(!is_complete(&merge(&[draft.clone(), a.clone()]).unwrap(), &threshold).unwrap());
+
+        let c = cosign(&draft, &keys[2]).unwrap();
+        let merged = merge(&[a, c]).unwrap();
+        let status = status(&merged, &threshold).unwrap();
+        assert!(status.complete);
+        assert_eq!(status.missing, vec![keys[1].key_id()]);
+        assert!(rc::verify_card(&merged, "true").unwrap().valid);
+    }
+
+    #[test]
+    fn test_merge_is_order_independent_and_deduplicates() {
+        let keys = auditors();
+        let draft = rc::emit(json!({"transaction": 1})).unwrap();
+        let partials: Vec<ReciboCard> = keys.iter().map(|k| cosign(&draft, k).unwrap()).collect();
+
+        let forward = merge(&[
+            partials[0].clone(),
+            partials[1].clone(),
+            partials[2].clone(),
+            partials[0].clone(),
+        ])
+        .unwrap();
+        let backward = merge(&[
+            partials[2].clone(),
+            partials[1].clone(),
+            partials[0].clone(),
+        ])
+        .unwrap();
+
+        assert_eq!(forward.recibo.signatures.len(), 3);
+        assert_eq!(forward.recibo.signatures, backward.recibo.signatures);
+        assert_eq!(
+            rc::card_cid(&forward).unwrap(),
+            rc::card_cid(&backward).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_rejects_different_content() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let a = cosign(&rc::emit(json!({"a": 1})).unwrap(), &key).unwrap();
+        let b = cosign(&rc::emit(json!({"a": 2})).unwrap(), &key).unwrap();
+        assert!(merge(&[a, b]).is_err());
+    }
+
+    #[test]
+    fn test_merge_rejects_different_kind() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let draft = rc::emit(json!({"a": 1})).unwrap();
+        let mut relabelled = draft.clone();
+        relabelled.recibo.kind = Some("rc.log.v1".to_string());
+        let err = merge(&[cosign(&draft, &key).unwrap(), relabelled]).unwrap_err();
+        assert!(err.to_string().contains("different kinds"));
+    }
+
+    #[test]
+    fn test_merge_keeps_earliest_valid_timestamp() {
+        let tsa_at = |time: &str| {
+            crate::tsa::LocalTsa::new(
+                KeyPair::generate(KeyAlgorithm::Ed25519),
+                Box::new(crate::clock::FixedClock::at(time).unwrap()),
+            )
+        };
+        let (early, late) = (
+            tsa_at("2024-01-01T00:00:00Z"),
+            tsa_at("2024-02-01T00:00:00Z"),
+        );
+        let keys = auditors();
+        let draft = rc::emit(json!({"a": 1})).unwrap();
+
+        let mut a = cosign(&draft, &keys[0]).unwrap();
+        rc::timestamp(&mut a, &late).unwrap();
+        // An earlier token that was tampered with is dropped
+        let mut b = cosign(&draft, &keys[1]).unwrap();
+        rc::timestamp(&mut b, &early).unwrap();
+        b.recibo.timestamp.as_mut().unwrap().info.serial += 1;
+
+        let merged = merge(&[b.clone(), a.clone()]).unwrap();
+        assert_eq!(merged.recibo.timestamp, a.recibo.timestamp);
+
+        // With only a bad token, the merged card carries none
+        let merged = merge(&[b, cosign(&draft, &keys[2]).unwrap()]).unwrap();
+        assert!(merged.recibo.timestamp.is_none());
+    }
+
+    #[test]
+    fn test_merge_rejects_invalid_signature() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let draft = rc::emit(json!({"a": 1})).unwrap();
+        let mut forged = draft.clone();
+        forged
+            .recibo
+            .signatures
+            .push(key.sign_cid(&"other".to_string()).unwrap());
+        assert!(merge(&[cosign(&draft, &key).unwrap(), forged]).is_err());
+    }
+
+    #[test]
+    fn test_unlisted_signers_do_not_count() {
+        let keys = auditors();
+        let outsider = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let threshold = Threshold::new(1, vec![keys[0].key_id()]).unwrap();
+        let draft = rc::emit(json!({"a": 1})).unwrap();
+
+        let card = cosign(&draft, &outsider).unwrap();
+        assert!(!is_complete(&card, &threshold).unwrap());
+    }
+
+    #[test]
+    fn test_threshold_bounds() {
+        assert!(Threshold::new(0, vec!["a".to_string()]).is_err());
+        assert!(Threshold::new(2, vec!["a".to_string(), "a".to_string()]).is_err());
+        assert!(Threshold::new(2, vec!["a".to_string(), "b".to_string()]).is_ok());
+    }
+}
@@ -0,0 +1,598 @@
+// Standard signed envelopes for Recibo Cards
+//
+// A card can be exported as a COSE_Sign1 message (RFC 9052, CBOR) or as a
+// JWS in general JSON serialization (RFC 7515, several signatures). In both
+// the payload is the canonical body bytes, so hashing the payload yields the
+// card's content_cid, which is also carried in the protected header. The
+// native Recibo (signatures over content_cid, parents, timestamp) travels in
+// an unprotected field so that importing gives back the original card.
+//
+// Envelope signatures are made over the envelope's own signing input, which
+// is what off-the-shelf COSE and JOSE verifiers check.
+
+use crate::chips::normalize;
+use crate::keystore::{self, KeyAlgorithm, KeyPair};
+use crate::types::{Cid, Recibo, ReciboCard, Signature};
+use crate::{Result, RhoError};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64URL};
+use base64::Engine as _;
+use ciborium::Value as Cbor;
+use serde_json::{json, Value};
+
+/// CBOR tag of a COSE_Sign1 message
+const COSE_SIGN1_TAG: u64 = 18;
+
+/// COSE header labels
+const COSE_ALG: i64 = 1;
+const COSE_CONTENT_TYPE: i64 = 3;
+const COSE_KID: i64 = 4;
+
+/// Private header parameters, shared by both envelope formats
+const CONTENT_CID_HEADER: &str = "rho.content_cid";
+const PUBLIC_KEY_HEADER: &str = "rho.public_key";
+const RECIBO_HEADER: &str = "rho.recibo";
+const JWS_CONTENT_CID_HEADER: &str = "rho_content_cid";
+const JWS_RECIBO_MEMBER: &str = "rho_recibo";
+
+/// COSE algorithm identifier (EdDSA = -8, ML-DSA-65 = -49)
+fn cose_alg(algorithm: KeyAlgorithm) -> i64 {
+    match algorithm {
+        KeyAlgorithm::Ed25519 => -8,
+        KeyAlgorithm::MlDsa3 => -49,
+    }
+}
+
+fn from_cose_alg(alg: i64) -> Result<KeyAlgorithm> {
+    match alg {
+        -8 => Ok(KeyAlgorithm::Ed25519),
+        -49 => Ok(KeyAlgorithm::MlDsa3),
+        other => Err(RhoError::Envelope(format!(
+            "Unsupported COSE algorithm: {}",
+            other
+        ))),
+    }
+}
+
+/// JOSE algorithm name
+fn jws_alg(algorithm: KeyAlgorithm) -> &'static str {
+    match algorithm {
+        KeyAlgorithm::Ed25519 => "EdDSA",
+        KeyAlgorithm::MlDsa3 => "ML-DSA-65",
+    }
+}
+
+fn from_jws_alg(alg: &str) -> Result<KeyAlgorithm> {
+    match alg {
+        "EdDSA" => Ok(KeyAlgorithm::Ed25519),
+        "ML-DSA-65" => Ok(KeyAlgorithm::MlDsa3),
+        other => Err(RhoError::Envelope(format!(
+            "Unsupported JWS algorithm: {}",
+            other
+        ))),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// COSE_Sign1
+// ---------------------------------------------------------------------------
+
+/// Export a card as a tagged COSE_Sign1 message signed by `key`
+pub fn to_cose_sign1(card: &ReciboCard, key: &KeyPair) -> Result<Vec<u8>> {
+    let payload = canonical_payload(card)?;
+
+    let protected = encode_cbor(&Cbor::Map(vec![
+        (COSE_ALG.into(), cose_alg(key.algorithm()).into()),
+        (COSE_CONTENT_TYPE.into(), "application/json".into()),
+        (COSE_KID.into(), Cbor::Bytes(key.key_id().into_bytes())),
+        (
+            PUBLIC_KEY_HEADER.into(),
+            Cbor::Bytes(key.public_key().to_vec()),
+        ),
+        (
+            CONTENT_CID_HEADER.into(),
+            card.recibo.content_cid.as_str().into(),
+        ),
+    ]))?;
+    let unprotected = Cbor::Map(vec![(
+        RECIBO_HEADER.into(),
+        Cbor::serialized(&card.recibo).map_err(envelope_error)?,
+    )]);
+
+    let signature = key.sign(&sig_structure(&protected, &payload)?)?;
+    let message = Cbor::Tag(
+        COSE_SIGN1_TAG,
+        Box::new(Cbor::Array(vec![
+            Cbor::Bytes(protected),
+            unprotected,
+            Cbor::Bytes(payload),
+            Cbor::Bytes(BASE64.decode(&signature.signature)?),
+        ])),
+    );
+    encode_cbor(&message)
+}
+
+/// Verify the envelope signature of a COSE_Sign1 message
+///
+/// Also checks that the payload hashes to the content_cid in the protected
+/// header. Malformed messages are errors.
+pub fn verify_cose_sign1(bytes: &[u8]) -> Result<bool> {
+    let sign1 = CoseSign1::decode(bytes)?;
+    sign1.verify()
+}
+
+/// Import a COSE_Sign1 message as a card
+///
+/// Fails unless the envelope signature verifies. The native signatures are
+/// returned untouched; check them with `verify_card`.
+pub fn from_cose_sign1(bytes: &[u8]) -> Result<ReciboCard> {
+    let sign1 = CoseSign1::decode(bytes)?;
+    if !sign1.verify()? {
+        return Err(RhoError::Signature(
+            "COSE_Sign1 signature does not verify".to_string(),
+        ));
+    }
+    rebuild_card(&sign1.payload, &sign1.content_cid, sign1.recibo)
+}
+
+struct CoseSign1 {
+    protected: Vec<u8>,
+    algorithm: KeyAlgorithm,
+    public_key: Vec<u8>,
+    content_cid: Cid,
+    recibo: Option<Recibo>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let value: Cbor = ciborium::from_reader(bytes).map_err(envelope_error)?;
+        let items = match value {
+            Cbor::Tag(COSE_SIGN1_TAG, inner) => match *inner {
+                Cbor::Array(items) => items,
+                _ => return Err(malformed("COSE_Sign1 is not an array")),
+            },
+            Cbor::Array(items) => items,
+            _ => return Err(malformed("not a COSE_Sign1 message")),
+        };
+        let [protected, unprotected, payload, signature]: [Cbor; 4] = items
+            .try_into()
+            .map_err(|_| malformed("COSE_Sign1 must have 4 elements"))?;
+
+        let protected = into_bytes(protected, "protected header")?;
+        let header: Cbor = ciborium::from_reader(protected.as_slice()).map_err(envelope_error)?;
+        let header = into_map(header, "protected header")?;
+        let unprotected = into_map(unprotected, "unprotected header")?;
+
+        let alg = header_value(&header, &COSE_ALG.into())
+            .and_then(|v| v.as_integer())
+            .and_then(|i| i64::try_from(i).ok())
+            .ok_or_else(|| malformed("missing alg"))?;
+        let public_key = header_value(&header, &PUBLIC_KEY_HEADER.into())
+            .and_then(|v| v.as_bytes())
+            .ok_or_else(|| malformed("missing public key"))?
+            .clone();
+        let content_cid = header_value(&header, &CONTENT_CID_HEADER.into())
+            .and_then(|v| v.as_text())
+            .ok_or_else(|| malformed("missing content_cid"))?
+            .to_string();
+        let recibo = header_value(&unprotected, &RECIBO_HEADER.into())
+            .map(|v| v.deserialized::<Recibo>().map_err(envelope_error))
+            .transpose()?;
+
+        Ok(Self {
+            protected,
+            algorithm: from_cose_alg(alg)?,
+            public_key,
+            content_cid,
+            recibo,
+            payload: into_bytes(payload, "payload")?,
+            signature: into_bytes(signature, "signature")?,
+        })
+    }
+
+    fn verify(&self) -> Result<bool> {
+        if normalize_payload(&self.payload)?.1 != self.content_cid {
+            return Ok(false);
+        }
+        let signature = Signature {
+            algorithm: self.algorithm.as_str().to_string(),
+            public_key: BASE64.encode(&self.public_key),
+            signature: BASE64.encode(&self.signature),
+        };
+        keystore::verify(&signature, &sig_structure(&self.protected, &self.payload)?)
+    }
+}
+
+/// Sig_structure for COSE_Sign1 with empty external AAD
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Result<Vec<u8>> {
+    encode_cbor(&Cbor::Array(vec![
+        "Signature1".into(),
+        Cbor::Bytes(protected.to_vec()),
+        Cbor::Bytes(vec![]),
+        Cbor::Bytes(payload.to_vec()),
+    ]))
+}
+
+fn encode_cbor(value: &Cbor) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(envelope_error)?;
+    Ok(bytes)
+}
+
+fn header_value<'a>(map: &'a [(Cbor, Cbor)], label: &Cbor) -> Option<&'a Cbor> {
+    map.iter().find(|(k, _)| k == label).map(|(_, v)| v)
+}
+
+fn into_bytes(value: Cbor, what: &str) -> Result<Vec<u8>> {
+    value
+        .into_bytes()
+        .map_err(|_| malformed(&format!("{} must be a byte string", what)))
+}
+
+fn into_map(value: Cbor, what: &str) -> Result<Vec<(Cbor, Cbor)>> {
+    value
+        .into_map()
+        .map_err(|_| malformed(&format!("{} must be a map", what)))
+}
+
+// ---------------------------------------------------------------------------
+// JWS (general JSON serialization)
+// ---------------------------------------------------------------------------
+
+/// Export a card as a JWS in general JSON serialization
+///
+/// Each key adds one entry to `signatures`; its protected header carries the
+/// algorithm, a JWK of the public key and the content_cid.
+pub fn to_jws(card: &ReciboCard, keys: &[&KeyPair]) -> Result<Value> {
+    if keys.is_empty() {
+        return Err(RhoError::InvalidInput(
+            "A JWS needs at least one signing key".to_string(),
+        ));
+    }
+    let payload = BASE64URL.encode(canonical_payload(card)?);
+
+    let mut signatures = Vec::with_capacity(keys.len());
+    for key in keys {
+        let header = json!({
+            "alg": jws_alg(key.algorithm()),
+            "cty": "json",
+            "kid": key.key_id(),
+            "jwk": jwk(key.algorithm(), key.public_key()),
+            JWS_CONTENT_CID_HEADER: card.recibo.content_cid,
+        });
+        let protected = BASE64URL.encode(serde_json::to_vec(&header)?);
+        let signing_input = format!("{}.{}", protected, payload);
+        let signature = key.sign(signing_input.as_bytes())?;
+        signatures.push(json!({
+            "protected": protected,
+            "signature": BASE64URL.encode(BASE64.decode(&signature.signature)?),
+        }));
+    }
+
+    Ok(json!({
+        "payload": payload,
+        "signatures": signatures,
+        JWS_RECIBO_MEMBER: card.recibo,
+    }))
+}
+
+/// Verify every signature of a general JSON JWS
+///
+/// All signatures must verify and name the content_cid the payload hashes to.
+pub fn verify_jws(jws: &Value) -> Result<bool> {
+    let parsed = Jws::decode(jws)?;
+    parsed.verify()
+}
+
+/// Import a general JSON JWS as a card
+///
+/// Fails unless every envelope signature verifies.
+pub fn from_jws(jws: &Value) -> Result<ReciboCard> {
+    let parsed = Jws::decode(jws)?;
+    if !parsed.verify()? {
+        return Err(RhoError::Signature(
+            "JWS signature does not verify".to_string(),
+        ));
+    }
+    rebuild_card(&parsed.payload, &parsed.content_cid, parsed.recibo)
+}
+
+struct Jws {
+    payload_b64: String,
+    payload: Vec<u8>,
+    content_cid: Cid,
+    recibo: Option<Recibo>,
+    signatures: Vec<JwsSignature>,
+}
+
+struct JwsSignature {
+    protected_b64: String,
+    algorithm: KeyAlgorithm,
+    public_key: Vec<u8>,
+    content_cid: Cid,
+    signature: Vec<u8>,
+}
+
+impl Jws {
+    fn decode(jws: &Value) -> Result<Self> {
+        let payload_b64 = jws["payload"]
+            .as_str()
+            .ok_or_else(|| malformed("JWS payload must be a string"))?
+            .to_string();
+        let entries = jws["signatures"]
+            .as_array()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| malformed("JWS must have at least one signature"))?;
+
+        let signatures = entries
+            .iter()
+            .map(JwsSignature::decode)
+            .collect::<Result<Vec<_>>>()?;
+        let content_cid = signatures[0].content_cid.clone();
+        let recibo = match jws.get(JWS_RECIBO_MEMBER) {
+            Some(recibo) => Some(serde_json::from_value(recibo.clone())?),
+            None => None,
+        };
+
+        Ok(Self {
+            payload: BASE64URL.decode(&payload_b64)?,
+            payload_b64,
+            content_cid,
+            recibo,
+            signatures,
+        })
+    }
+
+    fn verify(&self) -> Result<bool> {
+        if normalize_payload(&self.payload)?.1 != self.content_cid {
+            return Ok(false);
+        }
+        for sig in &self.signatures {
+            if sig.content_cid != self.content_cid {
+                return Ok(false);
+            }
+            let signature = Signature {
+                algorithm: sig.algorithm.as_str().to_string(),
+                public_key: BASE64.encode(&sig.public_key),
+                signature: BASE64.encode(&sig.signature),
+            };
+            let signing_input = format!("{}.{}", sig.protected_b64, self.payload_b64);
+            if !keystore::verify(&signature, signing_input.as_bytes())? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl JwsSignature {
+    fn decode(entry: &Value) -> Result<Self> {
+        let protected_b64 = entry["protected"]
+            .as_str()
+            .ok_or_else(|| malformed("JWS protected header must be a string"))?
+            .to_string();
+        let header: Value = serde_json::from_slice(&BASE64URL.decode(&protected_b64)?)?;
+        let algorithm = from_jws_alg(
+            header["alg"]
+                .as_str()
+                .ok_or_else(|| malformed("missing alg"))?,
+        )?;
+        let content_cid = header[JWS_CONTENT_CID_HEADER]
+            .as_str()
+            .ok_or_else(|| malformed("missing content_cid"))?
+            .to_string();
+        let signature = entry["signature"]
+            .as_str()
+            .ok_or_else(|| malformed("JWS signature must be a string"))?;
+
+        Ok(Self {
+            public_key: jwk_public_key(algorithm, &header["jwk"])?,
+            protected_b64,
+            algorithm,
+            content_cid,
+            signature: BASE64URL.decode(signature)?,
+        })
+    }
+}
+
+/// JWK of a public key (OKP for Ed25519, AKP for ML-DSA)
+fn jwk(algorithm: KeyAlgorithm, public_key: &[u8]) -> Value {
+    match algorithm {
+        KeyAlgorithm::Ed25519 => json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": BASE64URL.encode(public_key),
+        }),
+        KeyAlgorithm::MlDsa3 => json!({
+            "kty": "AKP",
+            "alg": "ML-DSA-65",
+            "pub": BASE64URL.encode(public_key),
+        }),
+    }
+}
+
+fn jwk_public_key(algorithm: KeyAlgorithm, jwk: &Value) -> Result<Vec<u8>> {
+    let member = match algorithm {
+        KeyAlgorithm::Ed25519 => "x",
+        KeyAlgorithm::MlDsa3 => "pub",
+    };
+    let encoded = jwk[member]
+        .as_str()
+        .ok_or_else(|| malformed("missing or unsupported jwk"))?;
+    Ok(BASE64URL.decode(encoded)?)
+}
+
+// ---------------------------------------------------------------------------
+// Shared
+// ---------------------------------------------------------------------------
+
+/// Canonical body bytes of a card whose content_cid matches its body
+fn canonical_payload(card: &ReciboCard) -> Result<Vec<u8>> {
+    let normalized = normalize(card.body.clone())?;
+    if normalized.cid != card.recibo.content_cid {
+        return Err(RhoError::CidMismatch {
+            expected: card.recibo.content_cid.clone(),
+            actual: normalized.cid,
+        });
+    }
+    Ok(BASE64.decode(&normalized.bytes)?)
+}
+
+/// Parse a payload and return its body and content CID
+///
+/// The payload must already be in canonical form.
+fn normalize_payload(payload: &[u8]) -> Result<(Value, Cid)> {
+    let body: Value = serde_json::from_slice(payload)?;
+    let normalized = normalize(body.clone())?;
+    if BASE64.decode(&normalized.bytes)? != payload {
+        return Err(malformed("payload is not canonical JSON"));
+    }
+    Ok((body, normalized.cid))
+}
+
+fn rebuild_card(payload: &[u8], content_cid: &Cid, recibo: Option<Recibo>) -> Result<ReciboCard> {
+    let (body, _) = normalize_payload(payload)?;
+    let recibo = recibo.unwrap_or_else(|| Recibo {
+        content_cid: content_cid.clone(),
+        kind: None,
+        signatures: vec![],
+        parents: vec![],
+        timestamp: None,
+    });
+    if recibo.content_cid != *content_cid {
+        return Err(RhoError::CidMismatch {
+            expected: content_cid.clone(),
+            actual: recibo.content_cid,
+        });
+    }
+    Ok(ReciboCard { body, recibo })
+}
+
+fn malformed(message: &str) -> RhoError {
+    RhoError::Envelope(format!("Malformed envelope: {}", message))
+}
+
+fn envelope_error(e: impl std::fmt::Display) -> RhoError {
+    RhoError::Envelope(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::{self, verify_card};
+
+    fn signed_card(keys: &[&KeyPair]) -> ReciboCard {
+        let mut card = rc::emit_with_parents(
+            json!({"order": 42, "item": "widget"}),
+            vec!["parent".to_string()],
+        )
+        .unwrap();
+        for key in keys {
+            rc::sign(&mut card, key).unwrap();
+        }
+        card
+    }
+
+    #[test]
+    fn test_cose_round_trip() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = signed_card(&[&key]);
+
+        let bytes = to_cose_sign1(&card, &key).unwrap();
+        assert!(verify_cose_sign1(&bytes).unwrap());
+
+        let imported = from_cose_sign1(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&imported).unwrap(),
+            serde_json::to_value(&card).unwrap()
+        );
+        assert!(verify_card(&imported, "ed25519").unwrap().valid);
+    }
+
+    #[test]
+    fn test_cose_mldsa() {
+        let key = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let card = signed_card(&[&key]);
+        let bytes = to_cose_sign1(&card, &key).unwrap();
+        assert!(verify_cose_sign1(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_cose_payload_hashes_to_content_cid() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = signed_card(&[&key]);
+        let bytes = to_cose_sign1(&card, &key).unwrap();
+
+        let sign1 = CoseSign1::decode(&bytes).unwrap();
+        assert_eq!(
+            BASE64.encode(blake3::hash(&sign1.payload).as_bytes()),
+            card.recibo.content_cid
+        );
+    }
+
+    #[test]
+    fn test_cose_rejects_tampered_payload() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = signed_card(&[&key]);
+        let bytes = to_cose_sign1(&card, &key).unwrap();
+
+        let mut sign1 = CoseSign1::decode(&bytes).unwrap();
+        sign1.payload = br#"{"item":"gadget","order":42}"#.to_vec();
+        assert!(!sign1.verify().unwrap());
+    }
+
+    #[test]
+    fn test_export_rejects_inconsistent_card() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut card = signed_card(&[&key]);
+        card.body = json!({"order": 43});
+        assert!(to_cose_sign1(&card, &key).is_err());
+        assert!(to_jws(&card, &[&key]).is_err());
+    }
+
+    #[test]
+    fn test_jws_round_trip_multiple_signatures() {
+        let ed = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let pq = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let card = signed_card(&[&ed, &pq]);
+
+        let jws = to_jws(&card, &[&ed, &pq]).unwrap();
+        assert_eq!(jws["signatures"].as_array().unwrap().len(), 2);
+        assert!(verify_jws(&jws).unwrap());
+
+        let imported = from_jws(&jws).unwrap();
+        assert_eq!(
+            serde_json::to_value(&imported).unwrap(),
+            serde_json::to_value(&card).unwrap()
+        );
+        assert!(
+            verify_card(&imported, "hybrid-and(ed25519,mldsa3)")
+                .unwrap()
+                .valid
+        );
+    }
+
+    #[test]
+    fn test_jws_rejects_tampered_payload() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = signed_card(&[&key]);
+        let mut jws = to_jws(&card, &[&key]).unwrap();
+
+        jws["payload"] = json!(BASE64URL.encode(br#"{"item":"gadget","order":42}"#));
+        assert!(!verify_jws(&jws).unwrap());
+        assert!(from_jws(&jws).is_err());
+    }
+
+    #[test]
+    fn test_jws_without_recibo_member() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = signed_card(&[&key]);
+        let mut jws = to_jws(&card, &[&key]).unwrap();
+        jws.as_object_mut().unwrap().remove(JWS_RECIBO_MEMBER);
+
+        let imported = from_jws(&jws).unwrap();
+        assert_eq!(imported.recibo.content_cid, card.recibo.content_cid);
+        assert!(imported.recibo.signatures.is_empty());
+    }
+}
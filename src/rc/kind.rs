@@ -0,0 +1,230 @@
+// Receipt kinds
+//
+// A kind names the shape of an RC body (e.g. `rc.log.v1`) and is bound to a
+// JSON Schema stored in CAS under the ref `rc.kind:<kind>`. Cards emitted
+// through `emit_kind` or `emit_typed` carry their kind in `recibo.kind` and
+// have been validated against its schema. Rust types implement `Kind` to get
+// a schema, typed emission and typed decoding.
+
+use crate::cas::Cas;
+use crate::chips::validate::{schema_options, validation_error};
+use crate::chips::{schema_cache, validate};
+use crate::rc;
+use crate::types::{Cid, ReciboCard, Signature, ValidationError};
+use crate::{Result, RhoError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// CAS ref namespace mapping a kind to its schema CID
+const KIND_REF: &str = "rc.kind";
+
+/// A typed RC body
+pub trait Kind: Serialize + DeserializeOwned {
+    /// Kind identifier, e.g. `rc.log.v1`
+    const KIND: &'static str;
+
+    /// JSON Schema of the body
+    fn schema() -> Value;
+}
+
+/// Store `schema` in CAS and bind `kind` to it
+///
+/// Re-registering a kind rebinds it; the schema must compile. The compiled
+/// validator is put in the shared schema cache.
+pub fn register_kind(kind: &str, schema: Value, cas: &Cas) -> Result<Cid> {
+    schema_cache::register(schema, &kind_ref(kind), cas)
+        .map_err(|e| RhoError::Validate(format!("Invalid schema for {}: {}", kind, e)))
+}
+
+/// Register the schema of a `Kind` unless its kind is already bound
+pub fn ensure_registered<T: Kind>(cas: &Cas) -> Result<Cid> {
+    match kind_schema(T::KIND, cas) {
+        Some(schema_cid) => Ok(schema_cid),
+        None => register::<T>(cas),
+    }
+}
+
+/// Register the schema of a `Kind`
+pub fn register<T: Kind>(cas: &Cas) -> Result<Cid> {
+    register_kind(T::KIND, T::schema(), cas)
+}
+
+/// Schema CID bound to `kind`, if registered
+pub fn kind_schema(kind: &str, cas: &Cas) -> Option<Cid> {
+    cas.get_ref(&kind_ref(kind))
+}
+
+/// Emit an RC of a registered kind
+///
+/// The body is validated against the kind's schema in CAS.
+pub fn emit_kind(kind: &str, body: Value, cas: &Cas) -> Result<ReciboCard> {
+    check_body(kind, &body, cas)?;
+    let mut card = rc::emit(body)?;
+    card.recibo.kind = Some(kind.to_string());
+    Ok(card)
+}
+
+/// Emit an RC from a typed body
+///
+/// Validates against `T::schema()`, so no registry is needed. `signatures`
+/// must cover `typed_signing_cid(value)`.
+pub fn emit_typed<T: Kind>(value: &T, signatures: Vec<Signature>) -> Result<ReciboCard> {
+    let body = serde_json::to_value(value)?;
+    check_typed::<T>(&body)?;
+
+    let mut card = rc::emit_with_signatures(body, signatures)?;
+    card.recibo.kind = Some(T::KIND.to_string());
+    Ok(card)
+}
+
+/// CID to sign for a card `emit_typed` will build from `value`
+pub fn typed_signing_cid<T: Kind>(value: &T) -> Result<Cid> {
+    let content_cid = rc::content_cid(&serde_json::to_value(value)?)?;
+    rc::signing_cid_of(&content_cid, Some(T::KIND), &[])
+}
+
+/// Check a body against `T::schema()`
+pub fn check_typed<T: Kind>(body: &Value) -> Result<()> {
+    let schema = T::schema();
+    let compiled = schema_options()
+        .compile(&schema)
+        .map_err(|e| RhoError::Validate(format!("Invalid schema for {}: {}", T::KIND, e)))?;
+    if let Err(errors) = compiled.validate(body) {
+        let errors: Vec<_> = errors.map(validation_error).collect();
+        return Err(RhoError::ValidationFailed(format!(
+            "{} body validation failed: {}",
+            T::KIND,
+            ValidationError::join(&errors)
+        )));
+    }
+    Ok(())
+}
+
+/// Decode the body of a card of kind `T`
+pub fn decode<T: Kind>(card: &ReciboCard) -> Result<T> {
+    match card.recibo.kind.as_deref() {
+        Some(kind) if kind == T::KIND => Ok(serde_json::from_value(card.body.clone())?),
+        other => Err(RhoError::InvalidInput(format!(
+            "Expected kind {}, card has {}",
+            T::KIND,
+            other.unwrap_or("no kind")
+        ))),
+    }
+}
+
+/// Check a received card's body against the registered schema of its kind
+///
+/// Cards without a kind pass.
+pub fn validate_card(card: &ReciboCard, cas: &Cas) -> Result<()> {
+    match &card.recibo.kind {
+        Some(kind) => check_body(kind, &card.body, cas),
+        None => Ok(()),
+    }
+}
+
+fn check_body(kind: &str, body: &Value, cas: &Cas) -> Result<()> {
+    let schema_cid = kind_schema(kind, cas)
+        .ok_or_else(|| RhoError::Validate(format!("Unknown kind: {}", kind)))?;
+    let validation = validate(body.clone(), schema_cid, cas)?;
+    if !validation.valid {
+        return Err(RhoError::ValidationFailed(format!(
+            "{} body validation failed: {}",
+            kind,
+            ValidationError::join(validation.errors.as_deref().unwrap_or_default())
+        )));
+    }
+    Ok(())
+}
+
+fn kind_ref(kind: &str) -> String {
+    format!("{}:{}", KIND_REF, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Ping {
+        seq: i64,
+    }
+
+    impl Kind for Ping {
+        const KIND: &'static str = "test.ping.v1";
+
+        fn schema() -> Value {
+            json!({
+                "type": "object",
+                "properties": {"seq": {"type": "integer", "minimum": 0}},
+                "required": ["seq"],
+                "additionalProperties": false
+            })
+        }
+    }
+
+    #[test]
+    fn test_emit_kind_validates_against_registry() {
+        let cas = Cas::new();
+        assert!(emit_kind(Ping::KIND, json!({"seq": 1}), &cas).is_err());
+
+        let schema_cid = register::<Ping>(&cas).unwrap();
+        assert_eq!(kind_schema(Ping::KIND, &cas), Some(schema_cid));
+
+        let card = emit_kind(Ping::KIND, json!({"seq": 1}), &cas).unwrap();
+        assert_eq!(card.recibo.kind.as_deref(), Some("test.ping.v1"));
+        assert!(emit_kind(Ping::KIND, json!({"seq": -1}), &cas).is_err());
+        assert!(emit_kind(Ping::KIND, json!({"seq": 1, "x": 2}), &cas).is_err());
+    }
+
+    #[test]
+    fn test_typed_round_trip() {
+        let card = emit_typed(&Ping { seq: 7 }, vec![]).unwrap();
+        assert_eq!(decode::<Ping>(&card).unwrap(), Ping { seq: 7 });
+
+        let untyped = rc::emit(json!({"seq": 7})).unwrap();
+        assert!(decode::<Ping>(&untyped).is_err());
+        assert!(emit_typed(&Ping { seq: -7 }, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_kind_does_not_change_content_cid() {
+        let typed = emit_typed(&Ping { seq: 7 }, vec![]).unwrap();
+        let untyped = rc::emit(json!({"seq": 7})).unwrap();
+        assert_eq!(typed.recibo.content_cid, untyped.recibo.content_cid);
+    }
+
+    #[test]
+    fn test_validate_card() {
+        let cas = Cas::new();
+        register::<Ping>(&cas).unwrap();
+
+        let mut card = emit_typed(&Ping { seq: 7 }, vec![]).unwrap();
+        assert!(validate_card(&card, &cas).is_ok());
+
+        card.body = json!({"seq": "seven"});
+        assert!(validate_card(&card, &cas).is_err());
+    }
+
+    #[test]
+    fn test_registered_schema_is_compiled_once() {
+        let cas = Cas::new();
+        register::<Ping>(&cas).unwrap();
+        ensure_registered::<Ping>(&cas).unwrap();
+        for seq in 0..3 {
+            emit_kind(Ping::KIND, json!({"seq": seq}), &cas).unwrap();
+        }
+        // The cache is shared with concurrently running tests, so check the
+        // entry rather than the counters
+        let schema_cid = kind_schema(Ping::KIND, &cas).unwrap();
+        assert!(validate::schema_cache().contains(&schema_cid));
+    }
+
+    #[test]
+    fn test_register_rejects_invalid_schema() {
+        let cas = Cas::new();
+        assert!(register_kind("bad.v1", json!({"type": 5}), &cas).is_err());
+    }
+}
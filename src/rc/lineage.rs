@@ -0,0 +1,123 @@
+use crate::cas::Cas;
+use crate::rc::load;
+use crate::types::{Cid, ReciboCard};
+use crate::Result;
+use std::collections::{BTreeSet, VecDeque};
+
+/// CAS ref namespace mapping an artifact CID to the RC that produced it
+const PRODUCER_REF: &str = "rc.producer";
+
+/// A card reached while walking provenance
+#[derive(Debug, Clone)]
+pub struct LineageNode {
+    pub cid: Cid,
+    pub card: ReciboCard,
+    /// Distance from the starting card (0 for the card itself)
+    pub depth: usize,
+}
+
+/// Record that the stored RC `card_cid` produced `artifact_cid`
+///
+/// Modules call this after storing their receipt so that downstream modules
+/// can find it and list it as a parent. The index is advisory: it is a
+/// mutable CAS ref, last writer wins, and nothing authenticates it. A card's
+/// own signed `parents` are the record of its provenance.
+pub fn record_producer(artifact_cid: &Cid, card_cid: &Cid, cas: &Cas) {
+    cas.set_ref(&producer_ref(artifact_cid), card_cid.clone());
+}
+
+/// CID of the stored RC that produced `artifact_cid`, if one was recorded
+///
+/// Advisory, see `record_producer`.
+pub fn producer(artifact_cid: &Cid, cas: &Cas) -> Option<Cid> {
+    cas.get_ref(&producer_ref(artifact_cid))
+}
+
+/// Walk the provenance DAG from a stored RC back to its origins
+///
+/// Returns every reachable card once, in breadth-first order starting with
+/// the card itself. Fails if any parent is missing from the CAS.
+pub fn lineage(card_cid: &Cid, cas: &Cas) -> Result<Vec<LineageNode>> {
+    let mut nodes = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut queue = VecDeque::from([(card_cid.clone(), 0)]);
+
+    while let Some((cid, depth)) = queue.pop_front() {
+        if !seen.insert(cid.clone()) {
+            continue;
+        }
+        let card = load(&cid, cas)?;
+        for parent in &card.recibo.parents {
+            queue.push_back((parent.clone(), depth + 1));
+        }
+        nodes.push(LineageNode { cid, card, depth });
+    }
+
+    Ok(nodes)
+}
+
+/// CIDs of the origin RCs (those without parents) reachable from a stored RC
+pub fn origins(card_cid: &Cid, cas: &Cas) -> Result<Vec<Cid>> {
+    let mut origins: Vec<Cid> = lineage(card_cid, cas)?
+        .into_iter()
+        .filter(|node| node.card.recibo.parents.is_empty())
+        .map(|node| node.cid)
+        .collect();
+    origins.sort();
+    Ok(origins)
+}
+
+fn producer_ref(artifact_cid: &Cid) -> String {
+    format!("{}:{}", PRODUCER_REF, artifact_cid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rc::{emit, emit_with_parents, store};
+    use serde_json::json;
+
+    #[test]
+    fn test_lineage_walks_to_origins() {
+        let cas = Cas::new();
+        let a = store(&emit(json!({"step": "a"})).unwrap(), &cas).unwrap();
+        let b = store(&emit(json!({"step": "b"})).unwrap(), &cas).unwrap();
+        let c = store(
+            &emit_with_parents(json!({"step": "c"}), vec![a.clone(), b.clone()]).unwrap(),
+            &cas,
+        )
+        .unwrap();
+        let d = store(
+            &emit_with_parents(json!({"step": "d"}), vec![c.clone(), a.clone()]).unwrap(),
+            &cas,
+        )
+        .unwrap();
+
+        let nodes = lineage(&d, &cas).unwrap();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[0].cid, d);
+        assert_eq!(nodes.iter().find(|n| n.cid == a).unwrap().depth, 1);
+
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(origins(&d, &cas).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_lineage_missing_parent() {
+        let cas = Cas::new();
+        let card = emit_with_parents(json!({"x": 1}), vec!["missing".to_string()]).unwrap();
+        let cid = store(&card, &cas).unwrap();
+        assert!(lineage(&cid, &cas).is_err());
+    }
+
+    #[test]
+    fn test_producer_index() {
+        let cas = Cas::new();
+        let card_cid = store(&emit(json!({"x": 1})).unwrap(), &cas).unwrap();
+        assert_eq!(producer(&"artifact".to_string(), &cas), None);
+
+        record_producer(&"artifact".to_string(), &card_cid, &cas);
+        assert_eq!(producer(&"artifact".to_string(), &cas), Some(card_cid));
+    }
+}
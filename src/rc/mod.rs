@@ -1,7 +1,30 @@
+pub mod cosign;
+pub mod envelope;
+pub mod kind;
+pub mod lineage;
+pub mod revocation;
+pub mod verify;
+
+pub use cosign::{cosign, CosignStatus, Threshold};
+pub use envelope::{
+    from_cose_sign1, from_jws, to_cose_sign1, to_jws, verify_cose_sign1, verify_jws,
+};
+pub use kind::{decode, emit_kind, emit_typed, register_kind, typed_signing_cid, Kind};
+pub use lineage::{lineage, origins, producer, record_producer, LineageNode};
+pub use revocation::{revoke, RevocationEntry, StatusList};
+pub use verify::{
+    verify_card, verify_card_with, verify_typed, SignatureCheck, TimestampStatus, Verdict,
+    VerifyOptions,
+};
+
+use crate::cas::Cas;
 use crate::chips::normalize;
-use crate::types::{Recibo, ReciboCard, Signature};
+use crate::keystore::KeyPair;
+use crate::tsa::{self, TimestampAuthority};
+use crate::types::{Cid, Recibo, ReciboCard, Signature};
 use crate::Result;
-use serde_json::Value;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
 
 /// RC (Recibo) emission
 ///
@@ -20,12 +43,104 @@ pub fn emit_with_signatures(body: Value, signatures: Vec<Signature>) -> Result<R
 
     let recibo = Recibo {
         content_cid: normalized.cid,
+        kind: None,
         signatures,
+        parents: vec![],
+        timestamp: None,
     };
 
     Ok(ReciboCard { body, recibo })
 }
 
+/// Emit RC derived from parent RCs
+///
+/// `parents` are the CIDs of stored RCs (see `store`). They are kept sorted
+/// and deduplicated so the card CID does not depend on discovery order.
+/// Signatures added afterwards cover them (see `signing_cid`).
+pub fn emit_with_parents(body: Value, mut parents: Vec<Cid>) -> Result<ReciboCard> {
+    parents.sort();
+    parents.dedup();
+
+    let mut card = emit(body)?;
+    card.recibo.parents = parents;
+    Ok(card)
+}
+
+/// Obtain a trusted timestamp for an RC's content_cid and embed it
+pub fn timestamp(card: &mut ReciboCard, tsa: &dyn TimestampAuthority) -> Result<()> {
+    card.recibo.timestamp = Some(tsa::request(tsa, &card.recibo.content_cid)?);
+    Ok(())
+}
+
+/// CID of a whole card (body and recibo), as assigned by `store`
+pub fn card_cid(card: &ReciboCard) -> Result<Cid> {
+    Ok(normalize(serde_json::to_value(card)?)?.cid)
+}
+
+/// Store a normalized card in CAS and return its card CID
+pub fn store(card: &ReciboCard, cas: &Cas) -> Result<Cid> {
+    let normalized = normalize(serde_json::to_value(card)?)?;
+    let bytes = BASE64.decode(&normalized.bytes)?;
+    let cid = cas.put(bytes)?;
+
+    if cid != normalized.cid {
+        return Err(crate::RhoError::CidMismatch {
+            expected: normalized.cid,
+            actual: cid,
+        });
+    }
+    Ok(cid)
+}
+
+/// Load a stored card by its card CID
+pub fn load(card_cid: &Cid, cas: &Cas) -> Result<ReciboCard> {
+    let bytes = cas.get(card_cid)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Content CID a body would receive as an RC
+///
+/// Signers use this to sign a body before it is emitted.
+pub fn content_cid(body: &Value) -> Result<Cid> {
+    Ok(normalize(body.clone())?.cid)
+}
+
+/// CID that signatures on a card cover
+///
+/// Binds `kind` and `parents` to the content, so a signed card cannot be
+/// relabelled or re-parented without invalidating its signatures. A card
+/// with neither signs its content_cid directly.
+pub fn signing_cid(recibo: &Recibo) -> Result<Cid> {
+    signing_cid_of(&recibo.content_cid, recibo.kind.as_deref(), &recibo.parents)
+}
+
+/// `signing_cid` of a card that has not been emitted yet
+///
+/// `parents` must be sorted and deduplicated, as `emit_with_parents` leaves
+/// them.
+pub fn signing_cid_of(content_cid: &Cid, kind: Option<&str>, parents: &[Cid]) -> Result<Cid> {
+    if kind.is_none() && parents.is_empty() {
+        return Ok(content_cid.clone());
+    }
+    let mut binding = json!({
+        "content_cid": content_cid,
+        "parents": parents,
+    });
+    if let Some(kind) = kind {
+        binding["kind"] = json!(kind);
+    }
+    Ok(normalize(binding)?.cid)
+}
+
+/// Sign an RC's `signing_cid` and append the signature
+///
+/// Set `kind` and `parents` before signing.
+pub fn sign(card: &mut ReciboCard, key: &KeyPair) -> Result<()> {
+    let signature = key.sign_cid(&signing_cid(&card.recibo)?)?;
+    card.recibo.signatures.push(signature);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,6 +169,61 @@ mod tests {
         assert_eq!(result.recibo.signatures[0].algorithm, "ed25519");
     }
 
+    #[test]
+    fn test_sign_appends_signature() {
+        let key = KeyPair::generate(crate::keystore::KeyAlgorithm::Ed25519);
+        let mut card = emit(json!({"test": "value"})).unwrap();
+        assert_eq!(card.recibo.content_cid, content_cid(&card.body).unwrap());
+
+        sign(&mut card, &key).unwrap();
+        assert_eq!(card.recibo.signatures.len(), 1);
+        assert!(
+            crate::keystore::verify_cid(&card.recibo.signatures[0], &card.recibo.content_cid)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_signatures_bind_kind_and_parents() {
+        let key = KeyPair::generate(crate::keystore::KeyAlgorithm::Ed25519);
+        let plain = emit(json!({"x": 1})).unwrap();
+        assert_eq!(
+            signing_cid(&plain.recibo).unwrap(),
+            plain.recibo.content_cid
+        );
+
+        let mut card = emit_with_parents(json!({"x": 1}), vec!["a".into()]).unwrap();
+        sign(&mut card, &key).unwrap();
+        let signed = signing_cid(&card.recibo).unwrap();
+        assert_ne!(signed, card.recibo.content_cid);
+        assert!(crate::keystore::verify_cid(&card.recibo.signatures[0], &signed).unwrap());
+
+        let mut reparented = card.recibo.clone();
+        reparented.parents = vec!["b".into()];
+        let cid = signing_cid(&reparented).unwrap();
+        assert!(!crate::keystore::verify_cid(&reparented.signatures[0], &cid).unwrap());
+
+        let mut relabelled = card.recibo.clone();
+        relabelled.kind = Some("rc.log.v1".to_string());
+        let cid = signing_cid(&relabelled).unwrap();
+        assert!(!crate::keystore::verify_cid(&relabelled.signatures[0], &cid).unwrap());
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let cas = Cas::new();
+        let card =
+            emit_with_parents(json!({"x": 1}), vec!["b".into(), "a".into(), "b".into()]).unwrap();
+        assert_eq!(card.recibo.parents, vec!["a".to_string(), "b".to_string()]);
+
+        let cid = store(&card, &cas).unwrap();
+        assert_eq!(cid, card_cid(&card).unwrap());
+
+        let loaded = load(&cid, &cas).unwrap();
+        assert_eq!(loaded.recibo.content_cid, card.recibo.content_cid);
+        assert_eq!(loaded.recibo.parents, card.recibo.parents);
+    }
+
     #[test]
     fn test_emit_deterministic() {
         let body = json!({"b": 2, "a": 1});
@@ -0,0 +1,234 @@
+// Revocation
+//
+// A revocation is itself an RC, signed by a revocation authority, naming the
+// content_cid it withdraws and why. Revocations are folded into a
+// `StatusList` (a CID set) that verifiers consult through
+// `verify_card_with`. A status list can be published as an RC of its own.
+
+use crate::clock::{Clock, Timestamp};
+use crate::keystore::{self, KeyPair};
+use crate::rc::{self, verify_card};
+use crate::types::{Cid, ReciboCard};
+use crate::{Result, RhoError};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Body type of a revocation RC
+pub const REVOCATION_TYPE: &str = "rc.revocation";
+
+/// Body type of a published status list RC
+pub const STATUS_LIST_TYPE: &str = "rc.status_list";
+
+/// Body of a revocation RC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revocation {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub revoked_cid: Cid,
+    pub reason: String,
+    pub revoked_at: Timestamp,
+}
+
+/// What a status list records about a revoked content_cid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationEntry {
+    pub reason: String,
+    pub revoked_at: Timestamp,
+    /// content_cid of the revocation RC
+    pub revocation_cid: Cid,
+    /// Key ID of the authority that signed it
+    pub authority: Cid,
+}
+
+/// Emit a revocation RC for `content_cid`, signed by `authority`
+pub fn revoke(
+    content_cid: &Cid,
+    reason: &str,
+    authority: &KeyPair,
+    clock: &dyn Clock,
+) -> Result<ReciboCard> {
+    let revocation = Revocation {
+        kind: REVOCATION_TYPE.to_string(),
+        revoked_cid: content_cid.clone(),
+        reason: reason.to_string(),
+        revoked_at: clock.now(),
+    };
+    let mut card = rc::emit(serde_json::to_value(&revocation)?)?;
+    rc::sign(&mut card, authority)?;
+    Ok(card)
+}
+
+/// Set of revoked content CIDs, accepted from a fixed set of authorities
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusList {
+    /// Key IDs whose revocations are accepted
+    pub authorities: BTreeSet<Cid>,
+    pub revoked: BTreeMap<Cid, RevocationEntry>,
+}
+
+impl StatusList {
+    pub fn new(authorities: Vec<Cid>) -> Self {
+        Self {
+            authorities: authorities.into_iter().collect(),
+            revoked: BTreeMap::new(),
+        }
+    }
+
+    /// Add a revocation RC
+    ///
+    /// The card must be a valid revocation signed by one of the authorities.
+    /// If a CID is revoked more than once the earliest revocation is kept.
+    pub fn add(&mut self, card: &ReciboCard) -> Result<()> {
+        let revocation: Revocation = serde_json::from_value(card.body.clone())
+            .map_err(|e| RhoError::InvalidInput(format!("Not a revocation: {}", e)))?;
+        if revocation.kind != REVOCATION_TYPE {
+            return Err(RhoError::InvalidInput(format!(
+                "Not a revocation: type {}",
+                revocation.kind
+            )));
+        }
+        let authority = self.signing_authority(card)?;
+
+        let entry = RevocationEntry {
+            reason: revocation.reason,
+            revoked_at: revocation.revoked_at,
+            revocation_cid: card.recibo.content_cid.clone(),
+            authority,
+        };
+        match self.revoked.get(&revocation.revoked_cid) {
+            Some(existing) if existing.revoked_at <= entry.revoked_at => {}
+            _ => {
+                self.revoked.insert(revocation.revoked_cid, entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Revocation entry for `content_cid`, if it has been revoked
+    pub fn status(&self, content_cid: &Cid) -> Option<&RevocationEntry> {
+        self.revoked.get(content_cid)
+    }
+
+    pub fn is_revoked(&self, content_cid: &Cid) -> bool {
+        self.revoked.contains_key(content_cid)
+    }
+
+    /// Publish the list as an RC signed by one of its authorities
+    pub fn publish(&self, authority: &KeyPair, clock: &dyn Clock) -> Result<ReciboCard> {
+        if !self.authorities.contains(&authority.key_id()) {
+            return Err(RhoError::Signature(
+                "Status list must be signed by one of its authorities".to_string(),
+            ));
+        }
+        let body = json!({
+            "type": STATUS_LIST_TYPE,
+            "authorities": self.authorities,
+            "revoked": self.revoked,
+            "updated_at": clock.now(),
+        });
+        let mut card = rc::emit(body)?;
+        rc::sign(&mut card, authority)?;
+        Ok(card)
+    }
+
+    /// Load a published status list
+    ///
+    /// `trusted` are the authorities the verifier accepts; the list must be
+    /// signed by one of them and may only name authorities among them.
+    pub fn from_card(card: &ReciboCard, trusted: &[Cid]) -> Result<Self> {
+        if card.body["type"] != STATUS_LIST_TYPE {
+            return Err(RhoError::InvalidInput("Not a status list".to_string()));
+        }
+        let list = Self {
+            authorities: serde_json::from_value(card.body["authorities"].clone())?,
+            revoked: serde_json::from_value(card.body["revoked"].clone())?,
+        };
+        let trusted = Self::new(trusted.to_vec());
+        trusted.signing_authority(card)?;
+        if !list.authorities.is_subset(&trusted.authorities) {
+            return Err(RhoError::Signature(
+                "Status list names untrusted authorities".to_string(),
+            ));
+        }
+        Ok(list)
+    }
+
+    /// Key ID of an authority with a valid signature on `card`
+    fn signing_authority(&self, card: &ReciboCard) -> Result<Cid> {
+        if !verify_card(card, "true")?.valid {
+            return Err(RhoError::Signature("Card does not verify".to_string()));
+        }
+        card.recibo
+            .signatures
+            .iter()
+            .filter_map(|sig| keystore::signer_id(sig).ok())
+            .find(|id| self.authorities.contains(id))
+            .ok_or_else(|| RhoError::Signature("Not signed by a revocation authority".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::test_clock;
+    use crate::keystore::KeyAlgorithm;
+
+    #[test]
+    fn test_revoke_and_lookup() {
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let card = rc::emit(json!({"passport": "model-x"})).unwrap();
+        let revocation = revoke(
+            &card.recibo.content_cid,
+            "non-compliant",
+            &authority,
+            &test_clock(),
+        )
+        .unwrap();
+
+        let mut list = StatusList::new(vec![authority.key_id()]);
+        list.add(&revocation).unwrap();
+
+        let entry = list.status(&card.recibo.content_cid).unwrap();
+        assert_eq!(entry.reason, "non-compliant");
+        assert_eq!(entry.authority, authority.key_id());
+        assert_eq!(entry.revocation_cid, revocation.recibo.content_cid);
+    }
+
+    #[test]
+    fn test_rejects_unauthorized_revocation() {
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let intruder = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let revocation = revoke(&"cid".to_string(), "spite", &intruder, &test_clock()).unwrap();
+
+        let mut list = StatusList::new(vec![authority.key_id()]);
+        assert!(list.add(&revocation).is_err());
+        assert!(!list.is_revoked(&"cid".to_string()));
+    }
+
+    #[test]
+    fn test_rejects_non_revocation_card() {
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut card = rc::emit(json!({"type": "other"})).unwrap();
+        rc::sign(&mut card, &authority).unwrap();
+
+        let mut list = StatusList::new(vec![authority.key_id()]);
+        assert!(list.add(&card).is_err());
+    }
+
+    #[test]
+    fn test_publish_and_load() {
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let clock = test_clock();
+        let mut list = StatusList::new(vec![authority.key_id()]);
+        list.add(&revoke(&"cid".to_string(), "key-compromise", &authority, &clock).unwrap())
+            .unwrap();
+
+        let published = list.publish(&authority, &clock).unwrap();
+        let loaded = StatusList::from_card(&published, &[authority.key_id()]).unwrap();
+        assert_eq!(loaded, list);
+
+        let stranger = KeyPair::generate(KeyAlgorithm::Ed25519);
+        assert!(StatusList::from_card(&published, &[stranger.key_id()]).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::cas::Cas;
+use crate::chips::{normalize, policy_eval, policy_eval_with};
+use crate::clock::Timestamp;
+use crate::keystore;
+use crate::rc::revocation::{RevocationEntry, StatusList};
+use crate::rc::{self, kind, Kind};
+use crate::tsa;
+use crate::types::{Cid, Proof, ReciboCard};
+use crate::{Result, RhoError};
+use serde::{Deserialize, Serialize};
+
+/// Outcome of verifying one signature on an RC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    pub algorithm: String,
+    pub public_key: String,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<Cid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Standing of a card's embedded timestamp token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampStatus {
+    /// Verifies and was issued by a TSA in `VerifyOptions.trusted_tsas`
+    Trusted,
+    /// Verifies, but its TSA is not trusted; proves nothing to third parties
+    Untrusted,
+    /// Names another CID or does not verify under the key it names
+    Invalid,
+}
+
+/// Structured result of `verify_card`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verdict {
+    /// True when every check below passed
+    pub valid: bool,
+    /// content_cid recomputed from the body
+    pub content_cid: Cid,
+    pub content_cid_ok: bool,
+    /// Present when the body's kind was checked against a schema: the
+    /// registered one in `VerifyOptions.cas`, or a Rust type's for
+    /// `verify_typed`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub kind_ok: Option<bool>,
+    pub signatures: Vec<SignatureCheck>,
+    pub policy: String,
+    pub policy_ok: bool,
+    /// Present when the card embeds a timestamp token
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timestamp: Option<TimestampStatus>,
+    /// Set when the status list names this card's content_cid
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub revoked: Option<RevocationEntry>,
+    /// Present when the body has `expires_at` and a current time was given
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expired: Option<bool>,
+}
+
+/// Extra checks for `verify_card_with`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerifyOptions<'a> {
+    /// Revocations to consult
+    pub status_list: Option<&'a StatusList>,
+    /// Current time, for `expires_at` in the body
+    pub now: Option<Timestamp>,
+    /// Where `keyset:` atoms in the policy are resolved
+    pub cas: Option<&'a Cas>,
+    /// Key IDs of the timestamp authorities whose tokens are trusted
+    pub trusted_tsas: &'a [Cid],
+}
+
+/// Verify a Recibo Card
+///
+/// THE CANON:
+/// 1. Re-normalize the body and compare its CID with `recibo.content_cid`
+/// 2. Cryptographically verify every signature over `rc::signing_cid`, which
+///    binds `kind` and `parents` to `content_cid`
+/// 3. Evaluate `policy_expr` against the signatures that verified
+/// 4. Verify the embedded timestamp token, if any, against `content_cid`
+///
+/// A timestamp only counts as evidence when its TSA is trusted; without
+/// `VerifyOptions.trusted_tsas` any well-formed token is reported as
+/// `Untrusted`.
+///
+/// A card's kind is checked against its registered schema when
+/// `VerifyOptions.cas` is given; use `verify_typed` to check it against a
+/// `Kind` instead.
+///
+/// Malformed signatures are reported in the verdict rather than as errors;
+/// an unparseable body or policy is an error.
+pub fn verify_card(card: &ReciboCard, policy_expr: &str) -> Result<Verdict> {
+    verify_card_with(card, policy_expr, &VerifyOptions::default())
+}
+
+/// Verify a Recibo Card, also checking revocation, expiry and the TSA
+///
+/// A card is invalid if `options.status_list` revokes its content_cid, if
+/// its body carries an RFC 3339 `expires_at` that is not after `options.now`,
+/// or if `options.trusted_tsas` is given and its timestamp is not `Trusted`.
+pub fn verify_card_with(
+    card: &ReciboCard,
+    policy_expr: &str,
+    options: &VerifyOptions,
+) -> Result<Verdict> {
+    let content_cid = normalize(card.body.clone())?.cid;
+    let content_cid_ok = content_cid == card.recibo.content_cid;
+    let kind_ok = match (&card.recibo.kind, options.cas) {
+        (Some(_), Some(cas)) => Some(kind::validate_card(card, cas).is_ok()),
+        _ => None,
+    };
+    let signing_cid = rc::signing_cid(&card.recibo)?;
+
+    let mut checks = Vec::with_capacity(card.recibo.signatures.len());
+    let mut proofs = Vec::new();
+    for sig in &card.recibo.signatures {
+        let (valid, error) = match keystore::verify_cid(sig, &signing_cid) {
+            Ok(valid) => (valid, None),
+            Err(e) => (false, Some(e.to_string())),
+        };
+        if valid {
+            proofs.push(Proof {
+                algorithm: sig.algorithm.clone(),
+                public_key: sig.public_key.clone(),
+                signature: sig.signature.clone(),
+                message_cid: signing_cid.clone(),
+            });
+        }
+        checks.push(SignatureCheck {
+            algorithm: sig.algorithm.clone(),
+            public_key: sig.public_key.clone(),
+            valid,
+            key_id: keystore::signer_id(sig).ok(),
+            error,
+        });
+    }
+
+    let expr = policy_expr.to_string();
+    let policy_ok = match options.cas {
+        Some(cas) => policy_eval_with(expr, proofs, &signing_cid, cas)?,
+        None => policy_eval(expr, proofs, &signing_cid)?,
+    }
+    .result;
+
+    let timestamp = card.recibo.timestamp.as_ref().map(|token| {
+        if !tsa::verify_token(token, &card.recibo.content_cid, None).unwrap_or(false) {
+            TimestampStatus::Invalid
+        } else if options.trusted_tsas.contains(&token.info.tsa) {
+            TimestampStatus::Trusted
+        } else {
+            TimestampStatus::Untrusted
+        }
+    });
+    let timestamp_ok = match timestamp {
+        Some(TimestampStatus::Invalid) => false,
+        Some(TimestampStatus::Untrusted) => options.trusted_tsas.is_empty(),
+        _ => true,
+    };
+
+    let revoked = options
+        .status_list
+        .and_then(|list| list.status(&card.recibo.content_cid))
+        .cloned();
+
+    let expired = match (card.body.get("expires_at"), options.now) {
+        (Some(expires_at), Some(now)) => {
+            let expires_at = expires_at
+                .as_str()
+                .ok_or_else(|| RhoError::InvalidInput("expires_at must be a string".to_string()))?;
+            Some(now >= Timestamp::parse_rfc3339(expires_at)?)
+        }
+        _ => None,
+    };
+
+    let valid = content_cid_ok
+        && kind_ok != Some(false)
+        && policy_ok
+        && checks.iter().all(|c| c.valid)
+        && timestamp_ok
+        && revoked.is_none()
+        && expired != Some(true);
+
+    Ok(Verdict {
+        valid,
+        content_cid,
+        content_cid_ok,
+        kind_ok,
+        signatures: checks,
+        policy: policy_expr.to_string(),
+        policy_ok,
+        timestamp,
+        revoked,
+        expired,
+    })
+}
+
+/// Verify a card that must be of kind `T`
+///
+/// Like `verify_card_with`; the card must also carry `T::KIND` and a body
+/// that satisfies `T::schema()`.
+pub fn verify_typed<T: Kind>(
+    card: &ReciboCard,
+    policy_expr: &str,
+    options: &VerifyOptions,
+) -> Result<Verdict> {
+    let mut verdict = verify_card_with(card, policy_expr, options)?;
+    let kind_ok =
+        card.recibo.kind.as_deref() == Some(T::KIND) && kind::check_typed::<T>(&card.body).is_ok();
+    verdict.kind_ok = Some(kind_ok);
+    verdict.valid &= kind_ok;
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::test_clock;
+    use crate::keystore::{KeyAlgorithm, KeyPair};
+    use serde_json::json;
+
+    #[test]
+    fn test_verify_signed_card() {
+        let ed = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let pq = KeyPair::generate(KeyAlgorithm::MlDsa3);
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        rc::sign(&mut card, &ed).unwrap();
+        rc::sign(&mut card, &pq).unwrap();
+
+        let verdict = verify_card(&card, "hybrid-and(ed25519,mldsa3)").unwrap();
+        assert!(verdict.valid);
+        assert!(verdict.content_cid_ok);
+        assert_eq!(verdict.signatures.len(), 2);
+        assert_eq!(verdict.signatures[0].key_id, Some(ed.key_id()));
+    }
+
+    #[test]
+    fn test_verify_detects_tampered_body() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        rc::sign(&mut card, &key).unwrap();
+        card.body = json!({"a": 2});
+
+        let verdict = verify_card(&card, "true").unwrap();
+        assert!(!verdict.valid);
+        assert!(!verdict.content_cid_ok);
+    }
+
+    #[test]
+    fn test_verify_rejects_forged_signature() {
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        card.recibo.signatures.push(crate::types::Signature {
+            algorithm: "ed25519".to_string(),
+            public_key: "forged_key".to_string(),
+            signature: "forged_sig".to_string(),
+        });
+
+        let verdict = verify_card(&card, "true").unwrap();
+        assert!(!verdict.valid);
+        assert!(!verdict.signatures[0].valid);
+        assert!(verdict.signatures[0].error.is_some());
+    }
+
+    #[test]
+    fn test_verify_policy_counts_only_valid_signatures() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let other = rc::emit(json!({"other": true})).unwrap();
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        // Signature over a different CID
+        card.recibo
+            .signatures
+            .push(key.sign_cid(&other.recibo.content_cid).unwrap());
+
+        let verdict = verify_card(&card, "ed25519").unwrap();
+        assert!(!verdict.policy_ok);
+        assert!(!verdict.valid);
+    }
+
+    #[test]
+    fn test_verify_detects_reparented_card() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut card = rc::emit_with_parents(json!({"a": 1}), vec!["p1".into()]).unwrap();
+        rc::sign(&mut card, &key).unwrap();
+        assert!(verify_card(&card, "ed25519").unwrap().valid);
+
+        card.recibo.parents = vec!["p2".into()];
+        let verdict = verify_card(&card, "ed25519").unwrap();
+        assert!(verdict.content_cid_ok);
+        assert!(!verdict.signatures[0].valid);
+        assert!(!verdict.valid);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Note {
+        text: String,
+    }
+
+    impl Kind for Note {
+        const KIND: &'static str = "test.note.v1";
+
+        fn schema() -> serde_json::Value {
+            json!({
+                "type": "object",
+                "properties": {"text": {"type": "string"}},
+                "required": ["text"],
+                "additionalProperties": false
+            })
+        }
+    }
+
+    #[test]
+    fn test_verify_detects_relabelled_card() {
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let note = Note {
+            text: "hi".to_string(),
+        };
+        let signature = key
+            .sign_cid(&rc::typed_signing_cid(&note).unwrap())
+            .unwrap();
+        let mut card = rc::emit_typed(&note, vec![signature]).unwrap();
+
+        let verdict = verify_typed::<Note>(&card, "ed25519", &VerifyOptions::default()).unwrap();
+        assert_eq!(verdict.kind_ok, Some(true));
+        assert!(verdict.valid);
+
+        card.recibo.kind = Some("rc.log.v1".to_string());
+        assert!(!verify_card(&card, "ed25519").unwrap().valid);
+        let verdict = verify_typed::<Note>(&card, "true", &VerifyOptions::default()).unwrap();
+        assert_eq!(verdict.kind_ok, Some(false));
+    }
+
+    #[test]
+    fn test_verify_revalidates_body_against_kind() {
+        let cas = Cas::new();
+        kind::register::<Note>(&cas).unwrap();
+        // Well signed, but the body does not fit test.note.v1
+        let key = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let mut card = rc::emit(json!({"text": 5})).unwrap();
+        card.recibo.kind = Some(Note::KIND.to_string());
+        rc::sign(&mut card, &key).unwrap();
+
+        let options = VerifyOptions {
+            cas: Some(&cas),
+            ..Default::default()
+        };
+        let verdict = verify_card_with(&card, "ed25519", &options).unwrap();
+        assert_eq!(verdict.kind_ok, Some(false));
+        assert!(!verdict.valid);
+        // Without a registry the kind is not checked
+        assert_eq!(verify_card(&card, "ed25519").unwrap().kind_ok, None);
+
+        // A kind missing from the registry does not pass either
+        card.recibo.kind = Some("test.unknown.v1".to_string());
+        let verdict = verify_card_with(&card, "true", &options).unwrap();
+        assert_eq!(verdict.kind_ok, Some(false));
+    }
+
+    #[test]
+    fn test_verify_timestamp_token() {
+        let tsa = crate::tsa::LocalTsa::new(
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            Box::new(test_clock()),
+        );
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        rc::timestamp(&mut card, &tsa).unwrap();
+
+        let trusted = [tsa.key_id()];
+        let options = VerifyOptions {
+            trusted_tsas: &trusted,
+            ..Default::default()
+        };
+        let verdict = verify_card_with(&card, "true", &options).unwrap();
+        assert_eq!(verdict.timestamp, Some(TimestampStatus::Trusted));
+        assert!(verdict.valid);
+
+        // Without trusted TSAs the token is reported but proves nothing
+        let verdict = verify_card(&card, "true").unwrap();
+        assert_eq!(verdict.timestamp, Some(TimestampStatus::Untrusted));
+        assert!(verdict.valid);
+
+        // A token for another CID is rejected
+        let other = rc::emit(json!({"a": 2})).unwrap();
+        card.recibo.timestamp = Some(crate::tsa::request(&tsa, &other.recibo.content_cid).unwrap());
+        let verdict = verify_card(&card, "true").unwrap();
+        assert_eq!(verdict.timestamp, Some(TimestampStatus::Invalid));
+        assert!(!verdict.valid);
+    }
+
+    #[test]
+    fn test_verify_rejects_self_issued_timestamp() {
+        let trusted = KeyPair::generate(KeyAlgorithm::Ed25519);
+        // The author runs a TSA of its own
+        let own_tsa = crate::tsa::LocalTsa::new(
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            Box::new(test_clock()),
+        );
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        rc::timestamp(&mut card, &own_tsa).unwrap();
+
+        let trusted = [trusted.key_id()];
+        let options = VerifyOptions {
+            trusted_tsas: &trusted,
+            ..Default::default()
+        };
+        let verdict = verify_card_with(&card, "true", &options).unwrap();
+        assert_eq!(verdict.timestamp, Some(TimestampStatus::Untrusted));
+        assert!(!verdict.valid);
+    }
+
+    #[test]
+    fn test_verify_with_status_list() {
+        let authority = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let clock = crate::clock::FixedClock::at("2024-06-01T00:00:00Z").unwrap();
+        let card = rc::emit(json!({"a": 1})).unwrap();
+
+        let mut list = StatusList::new(vec![authority.key_id()]);
+        let options = VerifyOptions {
+            status_list: Some(&list),
+            ..Default::default()
+        };
+        assert!(verify_card_with(&card, "true", &options).unwrap().valid);
+
+        list.add(&rc::revoke(&card.recibo.content_cid, "withdrawn", &authority, &clock).unwrap())
+            .unwrap();
+        let options = VerifyOptions {
+            status_list: Some(&list),
+            ..Default::default()
+        };
+        let verdict = verify_card_with(&card, "true", &options).unwrap();
+        assert!(!verdict.valid);
+        assert_eq!(verdict.revoked.unwrap().reason, "withdrawn");
+    }
+
+    #[test]
+    fn test_verify_expiry() {
+        let card = rc::emit(json!({"expires_at": "2025-01-01T00:00:00Z"})).unwrap();
+        let at = |s: &str| VerifyOptions {
+            now: Some(Timestamp::parse_rfc3339(s).unwrap()),
+            ..Default::default()
+        };
+
+        let verdict = verify_card_with(&card, "true", &at("2024-12-31T23:59:59Z")).unwrap();
+        assert_eq!(verdict.expired, Some(false));
+        assert!(verdict.valid);
+
+        let verdict = verify_card_with(&card, "true", &at("2025-01-01T00:00:00Z")).unwrap();
+        assert_eq!(verdict.expired, Some(true));
+        assert!(!verdict.valid);
+
+        // Without a current time expiry is not checked
+        assert_eq!(verify_card(&card, "true").unwrap().expired, None);
+    }
+
+    #[test]
+    fn test_verify_keyset_policy() {
+        let cas = Cas::new();
+        let auditor = KeyPair::generate(KeyAlgorithm::Ed25519);
+        let keyset = crate::chips::store_keyset(&[auditor.key_id()], &cas).unwrap();
+        let policy = format!("keyset:{}", keyset);
+
+        let mut card = rc::emit(json!({"a": 1})).unwrap();
+        rc::sign(&mut card, &auditor).unwrap();
+
+        let options = VerifyOptions {
+            cas: Some(&cas),
+            ..Default::default()
+        };
+        assert!(verify_card_with(&card, &policy, &options).unwrap().valid);
+        assert!(verify_card(&card, &policy).is_err());
+    }
+
+    #[test]
+    fn test_verify_invalid_policy_is_error() {
+        let card = rc::emit(json!({"a": 1})).unwrap();
+        assert!(verify_card(&card, "nonsense").is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// Trusted timestamping
+//
+// A request/response protocol modeled on RFC 3161: the client sends the CID
+// it wants timestamped, and the timestamp authority (TSA) returns a token in
+// which it signs `(content_cid, time, serial)`. Anyone holding the TSA's
+// public key can later verify that the content existed at that time.
+
+use crate::chips::normalize;
+use crate::clock::{Clock, Timestamp};
+use crate::keystore::{self, KeyPair};
+use crate::types::{Cid, Signature};
+use crate::{Result, RhoError};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Policy identifier of tokens issued by `LocalTsa`
+pub const LOCAL_TSA_POLICY: &str = "rho.tsa.local.v1";
+
+/// Timestamp request (cf. RFC 3161 TimeStampReq)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimestampRequest {
+    pub content_cid: Cid,
+    /// Echoed back in the token to bind the response to this request
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nonce: Option<i64>,
+}
+
+/// The statement a TSA signs (cf. RFC 3161 TSTInfo)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TstInfo {
+    pub version: i64,
+    pub policy: String,
+    pub content_cid: Cid,
+    pub time: Timestamp,
+    pub serial: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nonce: Option<i64>,
+    /// Key ID of the TSA signing key
+    pub tsa: Cid,
+}
+
+/// Signed timestamp token
+///
+/// The signature covers the CID of the normalized `info`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimestampToken {
+    pub info: TstInfo,
+    pub signature: Signature,
+}
+
+/// Outcome of a timestamp request (cf. RFC 3161 PKIStatus)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TsaStatus {
+    Granted,
+    Rejection,
+}
+
+/// Timestamp response (cf. RFC 3161 TimeStampResp)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimestampResponse {
+    pub status: TsaStatus,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub token: Option<TimestampToken>,
+}
+
+impl TimestampResponse {
+    /// The token of a granted response that answers `request`
+    pub fn into_token(self, request: &TimestampRequest) -> Result<TimestampToken> {
+        match (self.status, self.token) {
+            (TsaStatus::Granted, Some(token)) => {
+                if token.info.content_cid != request.content_cid
+                    || token.info.nonce != request.nonce
+                {
+                    return Err(RhoError::InvalidInput(
+                        "Timestamp token does not match the request".to_string(),
+                    ));
+                }
+                Ok(token)
+            }
+            _ => Err(RhoError::InvalidInput(format!(
+                "Timestamp request rejected: {}",
+                self.status_string.unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+/// A timestamp authority
+pub trait TimestampAuthority {
+    fn timestamp(&self, request: &TimestampRequest) -> Result<TimestampResponse>;
+}
+
+/// In-process TSA for tests and self-hosting
+pub struct LocalTsa {
+    key: KeyPair,
+    clock: Box<dyn Clock>,
+    serial: AtomicU64,
+}
+
+impl LocalTsa {
+    pub fn new(key: KeyPair, clock: Box<dyn Clock>) -> Self {
+        Self {
+            key,
+            clock,
+            serial: AtomicU64::new(1),
+        }
+    }
+
+    /// Key ID that verifiers should trust for this TSA
+    pub fn key_id(&self) -> Cid {
+        self.key.key_id()
+    }
+}
+
+impl TimestampAuthority for LocalTsa {
+    fn timestamp(&self, request: &TimestampRequest) -> Result<TimestampResponse> {
+        if request.content_cid.is_empty() {
+            return Ok(TimestampResponse {
+                status: TsaStatus::Rejection,
+                status_string: Some("Empty content_cid".to_string()),
+                token: None,
+            });
+        }
+
+        let info = TstInfo {
+            version: 1,
+            policy: LOCAL_TSA_POLICY.to_string(),
+            content_cid: request.content_cid.clone(),
+            time: self.clock.now(),
+            serial: self.serial.fetch_add(1, Ordering::SeqCst),
+            nonce: request.nonce,
+            tsa: self.key.key_id(),
+        };
+        let signature = self.key.sign_cid(&info_cid(&info)?)?;
+
+        Ok(TimestampResponse {
+            status: TsaStatus::Granted,
+            status_string: None,
+            token: Some(TimestampToken { info, signature }),
+        })
+    }
+}
+
+/// Request a token for `content_cid`
+pub fn request(tsa: &dyn TimestampAuthority, content_cid: &Cid) -> Result<TimestampToken> {
+    let request = TimestampRequest {
+        content_cid: content_cid.clone(),
+        nonce: None,
+    };
+    tsa.timestamp(&request)?.into_token(&request)
+}
+
+/// Verify a timestamp token for `content_cid`
+///
+/// Checks that the token names `content_cid`, that it was signed by the key
+/// named in `info.tsa`, and, when `trusted_tsa` is given, that this key is
+/// the trusted one.
+pub fn verify_token(
+    token: &TimestampToken,
+    content_cid: &Cid,
+    trusted_tsa: Option<&Cid>,
+) -> Result<bool> {
+    if token.info.content_cid != *content_cid {
+        return Ok(false);
+    }
+    if keystore::signer_id(&token.signature)? != token.info.tsa {
+        return Ok(false);
+    }
+    if trusted_tsa.is_some_and(|trusted| *trusted != token.info.tsa) {
+        return Ok(false);
+    }
+    keystore::verify_cid(&token.signature, &info_cid(&token.info)?)
+}
+
+fn info_cid(info: &TstInfo) -> Result<Cid> {
+    Ok(normalize(serde_json::to_value(info)?)?.cid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::test_clock;
+    use crate::keystore::KeyAlgorithm;
+
+    fn local_tsa() -> LocalTsa {
+        LocalTsa::new(
+            KeyPair::generate(KeyAlgorithm::Ed25519),
+            Box::new(test_clock()),
+        )
+    }
+
+    #[test]
+    fn test_timestamp_and_verify() {
+        let tsa = local_tsa();
+        let token = request(&tsa, &"content".to_string()).unwrap();
+
+        assert_eq!(token.info.time.to_rfc3339(), "2024-01-01T12:00:00Z");
+        assert_eq!(token.info.serial, 1);
+        assert!(verify_token(&token, &"content".to_string(), Some(&tsa.key_id())).unwrap());
+        assert!(!verify_token(&token, &"other".to_string(), None).unwrap());
+    }
+
+    #[test]
+    fn test_serials_increase() {
+        let tsa = local_tsa();
+        let t1 = request(&tsa, &"a".to_string()).unwrap();
+        let t2 = request(&tsa, &"a".to_string()).unwrap();
+        assert!(t2.info.serial > t1.info.serial);
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_time() {
+        let tsa = local_tsa();
+        let mut token = request(&tsa, &"content".to_string()).unwrap();
+        token.info.time = token.info.time.add_seconds(-3600).unwrap();
+        assert!(!verify_token(&token, &"content".to_string(), None).unwrap());
+    }
+
+    #[test]
+    fn test_verify_rejects_untrusted_tsa() {
+        let tsa = local_tsa();
+        let other = local_tsa();
+        let token = request(&tsa, &"content".to_string()).unwrap();
+        assert!(!verify_token(&token, &"content".to_string(), Some(&other.key_id())).unwrap());
+    }
+
+    #[test]
+    fn test_nonce_is_bound() {
+        let tsa = local_tsa();
+        let req = TimestampRequest {
+            content_cid: "content".to_string(),
+            nonce: Some(42),
+        };
+        let response = tsa.timestamp(&req).unwrap();
+        assert_eq!(
+            response.clone().into_token(&req).unwrap().info.nonce,
+            Some(42)
+        );
+
+        let other = TimestampRequest {
+            content_cid: "content".to_string(),
+            nonce: Some(43),
+        };
+        assert!(response.into_token(&other).is_err());
+    }
+
+    #[test]
+    fn test_rejects_empty_cid() {
+        let tsa = local_tsa();
+        assert!(request(&tsa, &String::new()).is_err());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::tsa::TimestampToken;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 
 /// Content Identifier (CID) - a blake3 hash encoded as base64
 pub type Cid = String;
@@ -23,7 +26,7 @@ pub struct ChipSpec {
     pub wiring: Option<Vec<serde_json::Value>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ChipType {
     Base,
@@ -43,13 +46,61 @@ pub struct NormalizeOutput {
 pub struct ValidateOutput {
     pub valid: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub errors: Option<Vec<String>>,
+    pub errors: Option<Vec<ValidationError>>,
+}
+
+/// One failed schema check
+///
+/// Paths are JSON Pointers. `params` holds the keyword's arguments (e.g.
+/// `limit`, `property`), with numbers outside i64 written as strings so the
+/// error normalizes and can be carried in an RC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub keyword: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, serde_json::Value>,
+}
+
+impl ValidationError {
+    /// Join errors into one line for error messages
+    pub fn join(errors: &[ValidationError]) -> String {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.instance_path, self.message)
+    }
 }
 
 /// Policy evaluation output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicyEvalOutput {
     pub result: bool,
+    /// Set by `policy_trace`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub trace: Option<PolicyTrace>,
+}
+
+/// Outcome of one node of an evaluated policy tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyTrace {
+    /// Canonical form of the node
+    pub policy: String,
+    pub result: bool,
+    /// Key IDs of the verified proofs that satisfied an atom, sorted
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub satisfied_by: Vec<Cid>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub children: Vec<PolicyTrace>,
 }
 
 /// Compilation output
@@ -86,12 +137,24 @@ pub struct ReciboCard {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recibo {
     pub content_cid: Cid,
+    /// Body kind (e.g. `rc.log.v1`), bound to a schema in CAS; see `rc::kind`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub kind: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub signatures: Vec<Signature>,
+    /// CIDs of the stored parent RCs this receipt was derived from
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub parents: Vec<Cid>,
+    /// Trusted timestamp over `content_cid`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timestamp: Option<TimestampToken>,
 }
 
 /// Signature
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Orders by (algorithm, public_key, signature), the canonical order of
+/// `Recibo.signatures` after a co-signing merge.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Signature {
     pub algorithm: String,
     pub public_key: String,
@@ -6,7 +6,7 @@ import * as RhoWasm from '../../../packages/rho-wasm/index';
 
 interface RhoCore {
   normalize: (input: any) => Promise<{ normalized: string; cid: string }>;
-  validate: (value: any, schemaCid: string) => Promise<{ ok: boolean; errors?: string[] }>;
+  validate: (value: any, schemaCid: string) => Promise<RhoWasm.ValidateOutput>;
   hash: (data: Uint8Array) => Promise<string>;
   isReady: boolean;
 }